@@ -5,11 +5,15 @@ use crate::{
     syntax_error::SyntaxError,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Block {
     pub statements: Vec<Statement>,
 }
 
+#[allow(
+    clippy::collapsible_match,
+    reason = "braces are matched as a `Token` and then as a `SimpleToken`"
+)]
 impl Parser<'_> {
     pub fn get_next_block(&mut self) -> Result<Option<Block>, SyntaxError> {
         let old_lexer = self.lexer.clone();
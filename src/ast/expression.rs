@@ -1,22 +1,133 @@
 use crate::{
+    ast::{block::Block, pattern::Pattern, type_annotation::TypeAnnotation},
+    diagnostic::Span,
     lexer::{SimpleToken, Token},
     parser::Parser,
     syntax_error::SyntaxError,
+    typeck::types::Type,
 };
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+    // Filled in by the type checker.
+    pub ty: Option<Type>,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression {
+        Expression {
+            kind,
+            span,
+            ty: None,
+        }
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Expression {
+        Expression::new(kind, Span::default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind {
     FunctionCall(FunctionCall),
     BinaryOperation(BinaryOperation),
     UnaryOperation(UnaryOperation),
     Literal(Literal),
     Identifier(String),
+    EnumVariant(EnumVariant),
+    Match(Match),
+    MapLiteral(MapLiteral),
+    Index(Index),
+    Tuple(Tuple),
+    Closure(Closure),
+    Call(Call),
+    Cast(Cast),
+    Try(Try),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionCall {
     pub function_name: String,
-    pub argument: Box<Expression>,
+    pub arguments: Vec<Expression>,
+}
+
+// Calls a function value, e.g. `make_adder(1)(2)`. Calls by name are `FunctionCall`s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub callee: Box<Expression>,
+    pub arguments: Vec<Expression>,
+}
+
+// `expression as u8`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub expression: Box<Expression>,
+    pub type_annotation: TypeAnnotation,
+}
+
+// `expression?`, which returns an error from the enclosing closure, or ends
+// the program if there is none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    pub expression: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Closure {
+    pub parameters: Vec<String>,
+    pub body: ClosureBody,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClosureBody {
+    Expression(Box<Expression>),
+    Block(Block),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub enum_name: String,
+    pub variant_name: String,
+    pub arguments: Vec<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tuple {
+    pub elements: Vec<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapLiteral {
+    pub entries: Vec<(Expression, Expression)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub expression: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub expression: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: MatchArmBody,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchArmBody {
+    Expression(Expression),
+    Block(Block),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -66,7 +177,7 @@ pub struct UnaryOperation {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     StringLiteral(String),
-    IntegerLiteral(u32),
+    IntegerLiteral(u64),
 }
 
 fn token_to_unary_operation_type(token: Token) -> Option<UnaryOperationType> {
@@ -134,18 +245,20 @@ fn get_operator_precedence(op: BinaryOperationType) -> u32 {
     }
 }
 
+#[allow(
+    clippy::collapsible_match,
+    reason = "tokens are matched by kind and then by value, as in the rest of the parser"
+)]
 impl Parser<'_> {
     pub fn get_next_expression(&mut self) -> Result<Option<Expression>, SyntaxError> {
-        if let Some(expression) = self.get_next_function_call()? {
-            return Ok(Some(expression));
-        } else if let Some(expression) = self.get_next_primary()? {
+        if let Some(expression) = self.get_next_operand()? {
             return self.get_next_expression_1(expression, 0);
         }
 
         Ok(None)
     }
 
-    pub fn get_next_function_call(&mut self) -> Result<Option<Expression>, SyntaxError> {
+    pub fn get_next_function_call(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
         let old_lexer = self.lexer.clone();
 
         let function_name = match self.lexer.get_next_token()? {
@@ -162,20 +275,212 @@ impl Parser<'_> {
             },
         };
 
-        let argument = match self.get_next_expression_parens()? {
+        let arguments = match self.get_next_arguments()? {
             None => {
                 self.lexer = old_lexer;
                 return Ok(None);
             }
-            Some(expression) => expression,
+            Some(arguments) => arguments,
         };
 
-        Ok(Some(Expression::FunctionCall(FunctionCall {
+        Ok(Some(ExpressionKind::FunctionCall(FunctionCall {
             function_name,
-            argument: Box::new(argument),
+            arguments,
+        })))
+    }
+
+    fn get_next_arguments(&mut self) -> Result<Option<Vec<Expression>>, SyntaxError> {
+        if self.consume_simple_token(SimpleToken::LParen)?.is_none() {
+            return Ok(None);
+        }
+
+        let mut arguments: Vec<Expression> = vec![];
+        if self.consume_simple_token(SimpleToken::RParen)?.is_some() {
+            return Ok(Some(arguments));
+        }
+
+        loop {
+            match self.get_next_expression()? {
+                None => return Err(SyntaxError::NoExpressionInArgumentList),
+                Some(expression) => arguments.push(expression),
+            }
+
+            if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+            return Err(SyntaxError::UnmatchedParen);
+        }
+
+        Ok(Some(arguments))
+    }
+
+    // `Enum::Variant(arguments)`, or `module::name` for a name from an
+    // imported file, whose enums are `module::Enum`.
+    fn get_next_enum_variant(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
+        let old_lexer = self.lexer.clone();
+
+        let mut path = match self.get_next_path()? {
+            None => return Ok(None),
+            Some(path) => path,
+        };
+        if path.len() == 1 {
+            self.lexer = old_lexer;
+            return Ok(None);
+        }
+
+        let arguments = self.get_next_arguments()?;
+
+        if path.len() == 2 && self.modules.contains(&path[0]) {
+            let name = path.join("::");
+            return Ok(Some(match arguments {
+                None => ExpressionKind::Identifier(name),
+                Some(arguments) => ExpressionKind::FunctionCall(FunctionCall {
+                    function_name: name,
+                    arguments,
+                }),
+            }));
+        }
+
+        let variant_name = path.pop().unwrap();
+        Ok(Some(ExpressionKind::EnumVariant(EnumVariant {
+            enum_name: path.join("::"),
+            variant_name,
+            arguments: arguments.unwrap_or_default(),
+        })))
+    }
+
+    fn get_next_match(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
+        if self.consume_simple_token(SimpleToken::Match)?.is_none() {
+            return Ok(None);
+        }
+
+        let expression = match self.get_next_expression()? {
+            None => return Err(SyntaxError::NoExpressionInMatch),
+            Some(expression) => expression,
+        };
+
+        if self.consume_simple_token(SimpleToken::LBrace)?.is_none() {
+            return Err(SyntaxError::NoArmsInMatch);
+        }
+
+        let mut arms: Vec<MatchArm> = vec![];
+        while let Some(arm) = self.get_next_match_arm()? {
+            arms.push(arm);
+
+            if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RBrace)?.is_none() {
+            return Err(SyntaxError::UnmatchedBrace);
+        }
+
+        Ok(Some(ExpressionKind::Match(Match {
+            expression: Box::new(expression),
+            arms,
         })))
     }
 
+    fn get_next_map_literal(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
+        if self.consume_simple_token(SimpleToken::LBrace)?.is_none() {
+            return Ok(None);
+        }
+
+        let mut entries: Vec<(Expression, Expression)> = vec![];
+        while let Some(key) = self.get_next_expression()? {
+            if self.consume_simple_token(SimpleToken::Colon)?.is_none() {
+                return Err(SyntaxError::NoColonInMapEntry);
+            }
+
+            let value = match self.get_next_expression()? {
+                None => return Err(SyntaxError::NoValueInMapEntry),
+                Some(value) => value,
+            };
+
+            entries.push((key, value));
+
+            if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RBrace)?.is_none() {
+            return Err(SyntaxError::UnmatchedBrace);
+        }
+
+        Ok(Some(ExpressionKind::MapLiteral(MapLiteral { entries })))
+    }
+
+    fn get_next_closure(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
+        let mut parameters: Vec<String> = vec![];
+
+        // `||` is lexed as a single token, which is a closure without parameters.
+        if self.consume_simple_token(SimpleToken::LogicalOr)?.is_none() {
+            if self.consume_simple_token(SimpleToken::BitwiseOr)?.is_none() {
+                return Ok(None);
+            }
+
+            while let Some(parameter) = self.get_next_identifier()? {
+                parameters.push(parameter);
+
+                if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                    break;
+                }
+            }
+
+            if self.consume_simple_token(SimpleToken::BitwiseOr)?.is_none() {
+                return Err(SyntaxError::UnmatchedPipe);
+            }
+        }
+
+        let body = if let Some(block) = self.get_next_block()? {
+            ClosureBody::Block(block)
+        } else if let Some(expression) = self.get_next_expression()? {
+            ClosureBody::Expression(Box::new(expression))
+        } else {
+            return Err(SyntaxError::NoBodyInClosure);
+        };
+
+        Ok(Some(ExpressionKind::Closure(Closure { parameters, body })))
+    }
+
+    fn get_next_match_arm(&mut self) -> Result<Option<MatchArm>, SyntaxError> {
+        let pattern = match self.get_next_pattern()? {
+            None => return Ok(None),
+            Some(pattern) => pattern,
+        };
+
+        let guard = match self.consume_simple_token(SimpleToken::If)? {
+            None => None,
+            Some(_) => match self.get_next_expression()? {
+                None => return Err(SyntaxError::NoConditionInMatchGuard),
+                Some(guard) => Some(guard),
+            },
+        };
+
+        if self.consume_simple_token(SimpleToken::FatArrow)?.is_none() {
+            return Err(SyntaxError::NoFatArrowInMatchArm);
+        }
+
+        let body = if let Some(block) = self.get_next_block()? {
+            MatchArmBody::Block(block)
+        } else if let Some(expression) = self.get_next_expression()? {
+            MatchArmBody::Expression(expression)
+        } else {
+            return Err(SyntaxError::NoExpressionInMatchArm);
+        };
+
+        Ok(Some(MatchArm {
+            pattern,
+            guard,
+            body,
+        }))
+    }
+
     pub fn get_next_expression_1(
         &mut self,
         mut lhs: Expression,
@@ -203,12 +508,12 @@ impl Parser<'_> {
 
             let p = get_operator_precedence(op);
 
-            if !(p >= min_precedence) {
+            if p < min_precedence {
                 self.lexer = old_lexer;
                 break;
             }
 
-            let mut rhs = match self.get_next_primary()? {
+            let mut rhs = match self.get_next_operand()? {
                 None => return Err(SyntaxError::NoExpressionAfterBinaryOperator),
                 Some(primary) => primary,
             };
@@ -234,7 +539,7 @@ impl Parser<'_> {
 
                 let p2 = get_operator_precedence(op2);
 
-                if !(p2 > p) {
+                if p2 <= p {
                     self.lexer = old_lexer;
                     break;
                 }
@@ -247,33 +552,132 @@ impl Parser<'_> {
                 };
             }
 
-            lhs = Expression::BinaryOperation(BinaryOperation {
-                operation_type: op,
-                left_expression: Box::new(lhs),
-                right_expression: Box::new(rhs),
-            });
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression::new(
+                ExpressionKind::BinaryOperation(BinaryOperation {
+                    operation_type: op,
+                    left_expression: Box::new(lhs),
+                    right_expression: Box::new(rhs),
+                }),
+                span,
+            );
         }
 
         Ok(Some(lhs))
     }
 
+    // A primary expression followed by any number of `as` casts, which bind
+    // more tightly than every binary operator and less than unary ones.
+    fn get_next_operand(&mut self) -> Result<Option<Expression>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
+
+        let mut expression = match self.get_next_primary()? {
+            None => return Ok(None),
+            Some(expression) => expression,
+        };
+
+        while self.consume_simple_token(SimpleToken::As)?.is_some() {
+            let type_annotation = match self.get_next_type_annotation()? {
+                None => return Err(SyntaxError::NoTypeInCast),
+                Some(type_annotation) => type_annotation,
+            };
+
+            expression = Expression::new(
+                ExpressionKind::Cast(Cast {
+                    expression: Box::new(expression),
+                    type_annotation,
+                }),
+                self.span_from(start),
+            );
+        }
+
+        Ok(Some(expression))
+    }
+
     fn get_next_primary(&mut self) -> Result<Option<Expression>, SyntaxError> {
-        if let Some(expression) = self.get_next_expression_parens()? {
-            return Ok(Some(expression));
+        let start = self.lexer.get_next_token_start();
+
+        let mut expression = match self.get_next_atom()? {
+            None => return Ok(None),
+            Some(expression) => expression,
+        };
+
+        loop {
+            if let Some(index) = self.get_next_index()? {
+                expression = Expression::new(
+                    ExpressionKind::Index(Index {
+                        expression: Box::new(expression),
+                        index: Box::new(index),
+                    }),
+                    self.span_from(start),
+                );
+            } else if let Some(arguments) = self.get_next_arguments()? {
+                expression = Expression::new(
+                    ExpressionKind::Call(Call {
+                        callee: Box::new(expression),
+                        arguments,
+                    }),
+                    self.span_from(start),
+                );
+            } else if self.consume_simple_token(SimpleToken::Question)?.is_some() {
+                expression = Expression::new(
+                    ExpressionKind::Try(Try {
+                        expression: Box::new(expression),
+                    }),
+                    self.span_from(start),
+                );
+            } else {
+                break;
+            }
         }
 
-        if let Some(expression) = self.get_next_unit()? {
-            return Ok(Some(expression));
+        Ok(Some(expression))
+    }
+
+    pub fn get_next_index(&mut self) -> Result<Option<Expression>, SyntaxError> {
+        if self.consume_simple_token(SimpleToken::LBracket)?.is_none() {
+            return Ok(None);
         }
 
-        if let Some(unary_operation) = self.get_next_unary_operation()? {
-            return Ok(Some(Expression::UnaryOperation(unary_operation)));
+        let index = match self.get_next_expression()? {
+            None => return Err(SyntaxError::NoExpressionInIndex),
+            Some(index) => index,
+        };
+
+        if self.consume_simple_token(SimpleToken::RBracket)?.is_none() {
+            return Err(SyntaxError::UnmatchedBracket);
         }
 
-        Ok(None)
+        Ok(Some(index))
     }
 
-    fn get_next_expression_parens(&mut self) -> Result<Option<Expression>, SyntaxError> {
+    fn get_next_atom(&mut self) -> Result<Option<Expression>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
+
+        let kind = if let Some(kind) = self.get_next_expression_parens()? {
+            kind
+        } else if let Some(kind) = self.get_next_match()? {
+            kind
+        } else if let Some(kind) = self.get_next_map_literal()? {
+            kind
+        } else if let Some(kind) = self.get_next_closure()? {
+            kind
+        } else if let Some(kind) = self.get_next_enum_variant()? {
+            kind
+        } else if let Some(kind) = self.get_next_function_call()? {
+            kind
+        } else if let Some(kind) = self.get_next_unit()? {
+            kind
+        } else if let Some(unary_operation) = self.get_next_unary_operation()? {
+            ExpressionKind::UnaryOperation(unary_operation)
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(Expression::new(kind, self.span_from(start))))
+    }
+
+    fn get_next_expression_parens(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
         let old_lexer = self.lexer.clone();
 
         let next_token = match self.lexer.get_next_token()? {
@@ -298,11 +702,25 @@ impl Parser<'_> {
             }
         }
 
-        let expression = match self.get_next_expression()? {
+        let mut expression = match self.get_next_expression()? {
             None => return Err(SyntaxError::NoExpressionAfterLParen),
             Some(expression) => expression,
         };
 
+        // A comma after the first expression makes this a tuple, e.g. `(a, b)` or `(a,)`.
+        if self.consume_simple_token(SimpleToken::Comma)?.is_some() {
+            let mut elements = vec![expression];
+            while let Some(element) = self.get_next_expression()? {
+                elements.push(element);
+
+                if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                    break;
+                }
+            }
+            expression =
+                Expression::new(ExpressionKind::Tuple(Tuple { elements }), Span::default());
+        }
+
         let last_token = match self.lexer.get_next_token()? {
             None => {
                 return Err(SyntaxError::UnmatchedParen);
@@ -322,10 +740,11 @@ impl Parser<'_> {
             }
         }
 
-        Ok(Some(expression))
+        // `(a)` is just `a`, but its span includes the parentheses.
+        Ok(Some(expression.kind))
     }
 
-    fn get_next_unit(&mut self) -> Result<Option<Expression>, SyntaxError> {
+    fn get_next_unit(&mut self) -> Result<Option<ExpressionKind>, SyntaxError> {
         let old_lexer = self.lexer.clone();
         let token = match self.lexer.get_next_token()? {
             None => {
@@ -336,9 +755,13 @@ impl Parser<'_> {
         };
 
         let expression = match token {
-            Token::Identifier(identifier) => Expression::Identifier(identifier),
-            Token::IntegerLiteral(literal) => Expression::Literal(Literal::IntegerLiteral(literal)),
-            Token::StringLiteral(literal) => Expression::Literal(Literal::StringLiteral(literal)),
+            Token::Identifier(identifier) => ExpressionKind::Identifier(identifier),
+            Token::IntegerLiteral(literal) => {
+                ExpressionKind::Literal(Literal::IntegerLiteral(literal))
+            }
+            Token::StringLiteral(literal) => {
+                ExpressionKind::Literal(Literal::StringLiteral(literal))
+            }
             _ => {
                 self.lexer = old_lexer;
                 return Ok(None);
@@ -383,38 +806,178 @@ impl Parser<'_> {
 mod tests {
     use crate::parser::Parser;
 
-    use super::{BinaryOperation, BinaryOperationType, Expression};
+    use super::{
+        BinaryOperation, BinaryOperationType, Call, Cast, Closure, ClosureBody, Expression,
+        ExpressionKind, FunctionCall, Index, Literal, MapLiteral, Try, UnaryOperation,
+        UnaryOperationType,
+    };
+    use crate::ast::type_annotation::{TypeAnnotation, TypeAnnotationKind};
+
+    fn identifier(name: &str) -> Expression {
+        ExpressionKind::Identifier(name.to_string()).into()
+    }
+
+    fn integer(literal: u64) -> Expression {
+        ExpressionKind::Literal(Literal::IntegerLiteral(literal)).into()
+    }
+
+    fn binary(
+        operation_type: BinaryOperationType,
+        left_expression: Expression,
+        right_expression: Expression,
+    ) -> Expression {
+        ExpressionKind::BinaryOperation(BinaryOperation {
+            operation_type,
+            left_expression: Box::new(left_expression),
+            right_expression: Box::new(right_expression),
+        })
+        .into()
+    }
 
     #[test]
     fn test_get_next_expression() {
         let mut p = Parser::new("a + b * c + (-d)");
         let e = p.get_next_expression().unwrap().unwrap();
 
-        let a = Expression::Identifier("a".to_string());
-        let b = Expression::Identifier("b".to_string());
-        let c = Expression::Identifier("c".to_string());
-        let d = Expression::Identifier("d".to_string());
-        let minus_d = Expression::UnaryOperation(super::UnaryOperation {
-            operation_type: super::UnaryOperationType::Minus,
-            expression: Box::new(d),
-        });
-        let b_times_c = Expression::BinaryOperation(BinaryOperation {
-            operation_type: BinaryOperationType::Multiply,
-            left_expression: Box::new(b),
-            right_expression: Box::new(c),
-        });
-        let a_plus_b_times_c = Expression::BinaryOperation(BinaryOperation {
-            operation_type: BinaryOperationType::Add,
-            left_expression: Box::new(a),
-            right_expression: Box::new(b_times_c),
+        let minus_d = ExpressionKind::UnaryOperation(UnaryOperation {
+            operation_type: UnaryOperationType::Minus,
+            expression: Box::new(identifier("d")),
+        })
+        .into();
+        let b_times_c = binary(
+            BinaryOperationType::Multiply,
+            identifier("b"),
+            identifier("c"),
+        );
+        let a_plus_b_times_c = binary(BinaryOperationType::Add, identifier("a"), b_times_c);
+        let a_plus_b_times_c_plus_minus_d =
+            binary(BinaryOperationType::Add, a_plus_b_times_c, minus_d);
+
+        assert_eq!(e, a_plus_b_times_c_plus_minus_d);
+    }
+
+    #[test]
+    fn test_get_next_expression_spans() {
+        let mut p = Parser::new("  f(a)[1] + (b)");
+        let e = p.get_next_expression().unwrap().unwrap();
+        assert_eq!((e.span.start, e.span.end), (2, 15));
+
+        let ExpressionKind::BinaryOperation(operation) = e.kind else {
+            panic!("expected a binary operation");
+        };
+        let left = operation.left_expression.span;
+        let right = operation.right_expression.span;
+        assert_eq!((left.start, left.end), (2, 9));
+        assert_eq!((right.start, right.end), (12, 15));
+    }
+
+    #[test]
+    fn test_get_next_expression_map_literal() {
+        let mut p = Parser::new("{\"a\": 1, 2: b}[k]");
+        let e = p.get_next_expression().unwrap().unwrap();
+
+        let map = ExpressionKind::MapLiteral(MapLiteral {
+            entries: vec![
+                (
+                    ExpressionKind::Literal(Literal::StringLiteral("a".to_string())).into(),
+                    integer(1),
+                ),
+                (integer(2), identifier("b")),
+            ],
         });
 
-        let a_plus_b_times_c_plus_minus_d = Expression::BinaryOperation(BinaryOperation {
-            operation_type: BinaryOperationType::Add,
-            left_expression: Box::new(a_plus_b_times_c),
-            right_expression: Box::new(minus_d),
+        assert_eq!(
+            e,
+            ExpressionKind::Index(Index {
+                expression: Box::new(map.into()),
+                index: Box::new(identifier("k")),
+            })
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_get_next_expression_closure() {
+        let mut p = Parser::new("(|x, y| x + y)(1, 2)");
+        let e = p.get_next_expression().unwrap().unwrap();
+
+        let closure = ExpressionKind::Closure(Closure {
+            parameters: vec!["x".to_string(), "y".to_string()],
+            body: ClosureBody::Expression(Box::new(binary(
+                BinaryOperationType::Add,
+                identifier("x"),
+                identifier("y"),
+            ))),
         });
 
-        assert_eq!(e, a_plus_b_times_c_plus_minus_d);
+        assert_eq!(
+            e,
+            ExpressionKind::Call(Call {
+                callee: Box::new(closure.into()),
+                arguments: vec![integer(1), integer(2)],
+            })
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_get_next_expression_cast() {
+        let mut p = Parser::new("-a as u8 as i64 * b");
+        let e = p.get_next_expression().unwrap().unwrap();
+
+        let cast = |expression: Expression, name: &str| -> Expression {
+            ExpressionKind::Cast(Cast {
+                expression: Box::new(expression),
+                type_annotation: TypeAnnotation {
+                    kind: TypeAnnotationKind::Named(name.to_string()),
+                    span: Default::default(),
+                },
+            })
+            .into()
+        };
+        let minus_a = ExpressionKind::UnaryOperation(UnaryOperation {
+            operation_type: UnaryOperationType::Minus,
+            expression: Box::new(identifier("a")),
+        })
+        .into();
+
+        assert_eq!(
+            e,
+            binary(
+                BinaryOperationType::Multiply,
+                cast(cast(minus_a, "u8"), "i64"),
+                identifier("b"),
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_next_expression_try() {
+        let mut p = Parser::new("-f(a)?[1]? + 2");
+        let e = p.get_next_expression().unwrap().unwrap();
+
+        let try_expression = |expression: Expression| -> Expression {
+            ExpressionKind::Try(Try {
+                expression: Box::new(expression),
+            })
+            .into()
+        };
+        let call = ExpressionKind::FunctionCall(FunctionCall {
+            function_name: "f".to_string(),
+            arguments: vec![identifier("a")],
+        })
+        .into();
+        let index = ExpressionKind::Index(Index {
+            expression: Box::new(try_expression(call)),
+            index: Box::new(integer(1)),
+        })
+        .into();
+        let minus = ExpressionKind::UnaryOperation(UnaryOperation {
+            operation_type: UnaryOperationType::Minus,
+            expression: Box::new(try_expression(index)),
+        })
+        .into();
+
+        assert_eq!(e, binary(BinaryOperationType::Add, minus, integer(2)));
     }
 }
@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use crate::{
+    diagnostic::Span,
+    lexer::{SimpleToken, Token},
+    parser::Parser,
+    syntax_error::SyntaxError,
+};
+
+// `import "path/to/other.l";`, which makes the names in the file available as
+// `other::name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    // Relative to the file the import is in.
+    pub path: String,
+    // The name of the file without its extension.
+    pub module_name: String,
+    pub span: Span,
+}
+
+impl Parser<'_> {
+    // Imports have to come before everything else in a file, so that the
+    // names they bring in are known when the rest of it is parsed.
+    pub fn get_imports(&mut self) -> Result<Vec<Import>, SyntaxError> {
+        let mut imports: Vec<Import> = vec![];
+        while let Some(import) = self.get_next_import()? {
+            self.modules.insert(import.module_name.clone());
+            imports.push(import);
+        }
+        Ok(imports)
+    }
+
+    fn get_next_import(&mut self) -> Result<Option<Import>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
+
+        if self.consume_simple_token(SimpleToken::Import)?.is_none() {
+            return Ok(None);
+        }
+
+        let path = match self.lexer.get_next_token()? {
+            Some(Token::StringLiteral(path)) => path,
+            _ => return Err(SyntaxError::NoPathInImport),
+        };
+        let module_name = get_module_name(&path).ok_or(SyntaxError::InvalidModuleName)?;
+
+        if self.consume_semicolon()?.is_none() {
+            return Err(SyntaxError::StatementWithoutSemicolon);
+        }
+
+        Ok(Some(Import {
+            path,
+            module_name,
+            span: self.span_from(start),
+        }))
+    }
+}
+
+// The name of the file without its extension, if it can be used as an
+// identifier.
+fn get_module_name(path: &str) -> Option<String> {
+    let name = Path::new(path).file_stem()?.to_str()?;
+
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if !(first.is_alphabetic() || first == '_') || !chars.all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Import;
+    use crate::{
+        ast::expression::{ExpressionKind, FunctionCall},
+        parser::Parser,
+    };
+
+    #[test]
+    fn test_get_imports() {
+        let mut parser = Parser::new("import \"lib/geo.l\"; geo::area(geo::unit);");
+        assert_eq!(
+            parser.get_imports().unwrap(),
+            vec![Import {
+                path: "lib/geo.l".to_string(),
+                module_name: "geo".to_string(),
+                span: Default::default(),
+            }]
+        );
+
+        let expression = parser.get_next_expression().unwrap().unwrap();
+        assert_eq!(
+            expression.kind,
+            ExpressionKind::FunctionCall(FunctionCall {
+                function_name: "geo::area".to_string(),
+                arguments: vec![crate::ast::expression::Expression::new(
+                    ExpressionKind::Identifier("geo::unit".to_string()),
+                    Default::default()
+                )],
+            })
+        );
+
+        assert!(Parser::new("import \"my-lib.l\";").get_imports().is_err());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod block;
 pub mod expression;
+pub mod import;
+pub mod pattern;
 pub mod statement;
+pub mod type_annotation;
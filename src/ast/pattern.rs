@@ -0,0 +1,226 @@
+use crate::{
+    diagnostic::Span,
+    lexer::{SimpleToken, Token},
+    parser::Parser,
+    syntax_error::SyntaxError,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl From<PatternKind> for Pattern {
+    fn from(kind: PatternKind) -> Pattern {
+        Pattern {
+            kind,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    Wildcard,
+    Binding(String),
+    IntegerLiteral(i128),
+    Variant(VariantPattern),
+    Tuple(Vec<Pattern>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantPattern {
+    pub enum_name: String,
+    pub variant_name: String,
+    pub fields: Vec<Pattern>,
+}
+
+impl Parser<'_> {
+    pub fn get_next_pattern(&mut self) -> Result<Option<Pattern>, SyntaxError> {
+        let old_lexer = self.lexer.clone();
+        let start = self.lexer.get_next_token_start();
+
+        let first_token = match self.lexer.get_next_token()? {
+            None => {
+                self.lexer = old_lexer;
+                return Ok(None);
+            }
+            Some(token) => token,
+        };
+
+        let identifier = match first_token {
+            Token::IntegerLiteral(literal) => {
+                let kind = PatternKind::IntegerLiteral(literal as i128);
+                return Ok(Some(self.pattern_from(kind, start)));
+            }
+            Token::SimpleToken(SimpleToken::Subtract) => match self.lexer.get_next_token()? {
+                Some(Token::IntegerLiteral(literal)) => {
+                    let kind = PatternKind::IntegerLiteral(-(literal as i128));
+                    return Ok(Some(self.pattern_from(kind, start)));
+                }
+                _ => return Err(SyntaxError::NoIntegerAfterMinusInPattern),
+            },
+            Token::SimpleToken(SimpleToken::LParen) => return self.get_next_tuple_pattern(start),
+            Token::Identifier(identifier) => identifier,
+            _ => {
+                self.lexer = old_lexer;
+                return Ok(None);
+            }
+        };
+
+        let mut path = vec![identifier];
+        while self
+            .consume_simple_token(SimpleToken::PathSeparator)?
+            .is_some()
+        {
+            match self.get_next_identifier()? {
+                None => return Err(SyntaxError::NoIdentifierAfterPathSeparator),
+                Some(identifier) => path.push(identifier),
+            }
+        }
+
+        let variant_name = path.pop().unwrap();
+        if path.is_empty() {
+            let kind = match variant_name.as_str() {
+                "_" => PatternKind::Wildcard,
+                _ => PatternKind::Binding(variant_name),
+            };
+            return Ok(Some(self.pattern_from(kind, start)));
+        }
+
+        let mut fields: Vec<Pattern> = vec![];
+        if self.consume_simple_token(SimpleToken::LParen)?.is_some() {
+            loop {
+                match self.get_next_pattern()? {
+                    None => break,
+                    Some(pattern) => fields.push(pattern),
+                }
+
+                if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                    break;
+                }
+            }
+
+            if self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+                return Err(SyntaxError::UnmatchedParen);
+            }
+        }
+
+        let kind = PatternKind::Variant(VariantPattern {
+            enum_name: path.join("::"),
+            variant_name,
+            fields,
+        });
+        Ok(Some(self.pattern_from(kind, start)))
+    }
+
+    fn pattern_from(&self, kind: PatternKind, start: usize) -> Pattern {
+        Pattern {
+            kind,
+            span: self.span_from(start),
+        }
+    }
+
+    // Parses the rest of a parenthesized pattern after the `(`. Like tuple
+    // expressions, `(p)` is just `p` and `(p,)` is a tuple with one element.
+    fn get_next_tuple_pattern(&mut self, start: usize) -> Result<Option<Pattern>, SyntaxError> {
+        let mut elements: Vec<Pattern> = vec![];
+        let mut has_trailing_comma = false;
+
+        while let Some(pattern) = self.get_next_pattern()? {
+            elements.push(pattern);
+
+            has_trailing_comma = self.consume_simple_token(SimpleToken::Comma)?.is_some();
+            if !has_trailing_comma {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+            return Err(SyntaxError::UnmatchedParen);
+        }
+
+        let kind = if elements.len() == 1 && !has_trailing_comma {
+            elements.pop().unwrap().kind
+        } else {
+            PatternKind::Tuple(elements)
+        };
+        Ok(Some(self.pattern_from(kind, start)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parser;
+
+    use super::{Pattern, PatternKind, VariantPattern};
+
+    fn binding(identifier: &str) -> Pattern {
+        PatternKind::Binding(identifier.to_string()).into()
+    }
+
+    fn tuple(elements: Vec<Pattern>) -> Pattern {
+        PatternKind::Tuple(elements).into()
+    }
+
+    #[test]
+    fn test_get_next_tuple_pattern() {
+        let mut p = Parser::new("((a, _), (b,), (c))");
+        let pattern = p.get_next_pattern().unwrap().unwrap();
+
+        assert_eq!(
+            pattern,
+            tuple(vec![
+                tuple(vec![binding("a"), PatternKind::Wildcard.into()]),
+                tuple(vec![binding("b")]),
+                binding("c"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_get_next_pattern() {
+        let mut p = Parser::new("Shape::Rect(_, 3, h)");
+        let pattern = p.get_next_pattern().unwrap().unwrap();
+
+        assert_eq!(
+            pattern,
+            PatternKind::Variant(VariantPattern {
+                enum_name: "Shape".to_string(),
+                variant_name: "Rect".to_string(),
+                fields: vec![
+                    PatternKind::Wildcard.into(),
+                    PatternKind::IntegerLiteral(3).into(),
+                    binding("h"),
+                ],
+            })
+            .into()
+        );
+
+        let mut p = Parser::new("(-3, 4)");
+        assert_eq!(
+            p.get_next_pattern().unwrap().unwrap(),
+            tuple(vec![
+                PatternKind::IntegerLiteral(-3).into(),
+                PatternKind::IntegerLiteral(4).into()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_get_next_pattern_spans() {
+        let mut p = Parser::new("  (a, -1)");
+        let pattern = p.get_next_pattern().unwrap().unwrap();
+        assert_eq!((pattern.span.start, pattern.span.end), (2, 9));
+
+        let PatternKind::Tuple(elements) = pattern.kind else {
+            panic!("expected a tuple pattern");
+        };
+        let spans: Vec<_> = elements
+            .iter()
+            .map(|element| (element.span.start, element.span.end))
+            .collect();
+        assert_eq!(spans, vec![(3, 4), (6, 8)]);
+    }
+}
@@ -1,14 +1,22 @@
 use crate::{
     ast::block::Block,
-    ast::expression::Expression,
+    ast::expression::{Expression, ExpressionKind},
+    ast::pattern::Pattern,
+    ast::type_annotation::TypeAnnotation,
+    diagnostic::Span,
     lexer::{SimpleToken, Token},
     parser::Parser,
     syntax_error::SyntaxError,
+    typeck::types::Type,
 };
 
-use super::expression::{BinaryOperation, BinaryOperationType};
+use super::expression::{BinaryOperation, BinaryOperationType, Index, Tuple};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(
+    clippy::enum_variant_names,
+    reason = "the variants are named after the statements they hold"
+)]
 pub enum Statement {
     LetStatement(LetStatement),
     Assignment(AssignmentStatement),
@@ -17,52 +25,126 @@ pub enum Statement {
     ContinueStatement(ContinueStatement),
     LoopStatement(LoopStatement),
     WhileStatement(WhileStatement),
+    EnumDeclaration(EnumDeclaration),
     Expression(Expression),
     EmptyStatement,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
-    pub identifier: String,
+    pub pattern: Pattern,
+    pub type_annotation: Option<TypeAnnotation>,
     pub expression: Option<Expression>,
-    pub _mutable: bool,
+    pub mutable: bool,
+    pub span: Span,
+    // The type of the bound value, filled in by the type checker.
+    pub ty: Option<Type>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AssignmentStatement {
-    pub identifier: String,
+    pub target: AssignmentTarget,
     pub expression: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssignmentTarget {
+    Identifier(String),
+    Index(Index),
+    Tuple(Vec<AssignmentTarget>),
 }
 
-#[derive(Debug)]
+impl AssignmentTarget {
+    // The expression that reads the current value of the target.
+    pub fn to_expression(&self, span: Span) -> Expression {
+        let kind = match self {
+            AssignmentTarget::Identifier(identifier) => {
+                ExpressionKind::Identifier(identifier.clone())
+            }
+            AssignmentTarget::Index(index) => ExpressionKind::Index(index.clone()),
+            AssignmentTarget::Tuple(targets) => ExpressionKind::Tuple(Tuple {
+                elements: targets
+                    .iter()
+                    .map(|target| target.to_expression(span))
+                    .collect(),
+            }),
+        };
+        Expression::new(kind, span)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct IfStatement {
     pub _if: ConditionWithBlock,
     pub else_if: Vec<ConditionWithBlock>,
     pub _else: Option<Block>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConditionWithBlock {
     pub condition: Expression,
     pub block: Block,
 }
 
-#[derive(Debug)]
-pub struct BreakStatement;
-#[derive(Debug)]
-pub struct ContinueStatement;
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakStatement {
+    pub span: Span,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinueStatement {
+    pub span: Span,
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LoopStatement {
     pub block: Block,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WhileStatement {
     pub condition: Expression,
     pub block: Block,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDeclaration {
+    pub identifier: String,
+    pub variants: Vec<EnumVariantDeclaration>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariantDeclaration {
+    pub identifier: String,
+    pub fields: Vec<String>,
+}
+
+impl Statement {
+    // Empty statements have no span of their own.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Statement::LetStatement(statement) => Some(statement.span),
+            Statement::Assignment(statement) => Some(statement.span),
+            Statement::IfStatement(statement) => Some(statement.span),
+            Statement::BreakStatement(statement) => Some(statement.span),
+            Statement::ContinueStatement(statement) => Some(statement.span),
+            Statement::LoopStatement(statement) => Some(statement.span),
+            Statement::WhileStatement(statement) => Some(statement.span),
+            Statement::EnumDeclaration(declaration) => Some(declaration.span),
+            Statement::Expression(expression) => Some(expression.span),
+            Statement::EmptyStatement => None,
+        }
+    }
+}
+
+#[allow(
+    clippy::collapsible_match,
+    reason = "keywords are matched as a `Token` and then as a `SimpleToken`"
+)]
 impl Parser<'_> {
     pub fn consume_semicolon(&mut self) -> Result<Option<()>, SyntaxError> {
         let old_lexer = self.lexer.clone();
@@ -76,53 +158,65 @@ impl Parser<'_> {
 
         match token {
             Token::SimpleToken(simple_token) => match simple_token {
-                SimpleToken::Semicolon => return Ok(Some(())),
+                SimpleToken::Semicolon => Ok(Some(())),
                 _ => {
                     self.lexer = old_lexer;
-                    return Ok(None);
+                    Ok(None)
                 }
             },
             _ => {
                 self.lexer = old_lexer;
-                return Ok(None);
+                Ok(None)
             }
         }
     }
 
     pub fn get_next_statement(&mut self) -> Result<Option<Statement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let mut next_statement: Option<Statement> = None;
 
+        if self.consume_simple_token(SimpleToken::Import)?.is_some() {
+            return Err(SyntaxError::ImportAfterStatement);
+        }
+
         if let Some(statement) = self.get_next_let_statement()? {
             next_statement = Some(Statement::LetStatement(statement));
         } else if let Some(statement) = self.get_next_assignment_statement()? {
             next_statement = Some(Statement::Assignment(statement));
         } else if let Some(statement) = self.get_next_if_statement()? {
             next_statement = Some(Statement::IfStatement(statement));
-        } else if let Some(_) = self.get_next_break_statement()? {
-            next_statement = Some(Statement::BreakStatement(BreakStatement));
-        } else if let Some(_) = self.get_next_continue_statement()? {
-            next_statement = Some(Statement::ContinueStatement(ContinueStatement));
+        } else if self.get_next_break_statement()?.is_some() {
+            next_statement = Some(Statement::BreakStatement(BreakStatement {
+                span: self.span_from(start),
+            }));
+        } else if self.get_next_continue_statement()?.is_some() {
+            next_statement = Some(Statement::ContinueStatement(ContinueStatement {
+                span: self.span_from(start),
+            }));
         } else if let Some(statement) = self.get_next_loop_statement()? {
             next_statement = Some(Statement::LoopStatement(statement));
         } else if let Some(statement) = self.get_next_while_statement()? {
             next_statement = Some(Statement::WhileStatement(statement));
+        } else if let Some(statement) = self.get_next_enum_declaration()? {
+            next_statement = Some(Statement::EnumDeclaration(statement));
         } else if let Some(statement) = self.get_next_expression()? {
             next_statement = Some(Statement::Expression(statement));
         }
 
         match next_statement {
             None => match self.consume_semicolon()? {
-                None => return Ok(None),
-                Some(_) => return Ok(Some(Statement::EmptyStatement)),
+                None => Ok(None),
+                Some(_) => Ok(Some(Statement::EmptyStatement)),
             },
             Some(_) => match self.consume_semicolon()? {
-                None => return Err(SyntaxError::StatementWithoutSemicolon),
-                Some(_) => return Ok(next_statement),
+                None => Err(SyntaxError::StatementWithoutSemicolon),
+                Some(_) => Ok(next_statement),
             },
         }
     }
 
     fn get_next_let_statement(&mut self) -> Result<Option<LetStatement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let old_lexer = self.lexer.clone();
 
         let first_token = match self.lexer.get_next_token()? {
@@ -169,14 +263,17 @@ impl Parser<'_> {
             },
         };
 
-        let next_token = match self.lexer.get_next_token()? {
+        let pattern = match self.get_next_pattern()? {
             None => return Err(SyntaxError::NoIdentifierInLetStatement),
-            Some(token) => token,
+            Some(pattern) => pattern,
         };
 
-        let identifier = match next_token {
-            Token::Identifier(identifier) => identifier,
-            _ => return Err(SyntaxError::NoIdentifierInLetStatement),
+        let type_annotation = match self.consume_simple_token(SimpleToken::Colon)? {
+            None => None,
+            Some(_) => match self.get_next_type_annotation()? {
+                None => return Err(SyntaxError::NoTypeInTypeAnnotation),
+                Some(type_annotation) => Some(type_annotation),
+            },
         };
 
         let old_lexer = self.lexer.clone();
@@ -205,32 +302,26 @@ impl Parser<'_> {
         };
 
         Ok(Some(LetStatement {
-            identifier,
-            _mutable: mutable,
+            pattern,
+            type_annotation,
+            mutable,
             expression,
+            span: self.span_from(start),
+            ty: None,
         }))
     }
 
     fn get_next_assignment_statement(
         &mut self,
     ) -> Result<Option<AssignmentStatement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let old_lexer = self.lexer.clone();
 
-        let first_token = match self.lexer.get_next_token()? {
-            None => {
-                self.lexer = old_lexer;
-                return Ok(None);
-            }
-            Some(token) => token,
-        };
-
-        let identifier = match first_token {
-            Token::Identifier(identifier) => identifier,
-            _ => {
-                self.lexer = old_lexer;
-                return Ok(None);
-            }
+        let target = match self.get_next_assignment_target()? {
+            None => return Ok(None),
+            Some(target) => target,
         };
+        let target_span = self.span_from(start);
 
         let second_token = match self.lexer.get_next_token()? {
             None => {
@@ -265,25 +356,39 @@ impl Parser<'_> {
             }
         };
 
+        // Tuples can only be assigned to as a whole.
+        if binary_operation_type.is_some() && matches!(target, AssignmentTarget::Tuple(_)) {
+            self.lexer = old_lexer;
+            return Ok(None);
+        }
+
         let expression = match self.get_next_expression()? {
             None => return Err(SyntaxError::NoExpressionInAssignmentStatement),
             Some(expression) => expression,
         };
 
+        let span = self.span_from(start);
+
         let assignment_statement = match binary_operation_type {
             None => AssignmentStatement {
-                identifier,
+                target,
                 expression,
+                span,
             },
             Some(operation_type) => {
-                let ident = identifier.clone();
+                let left_expression = target.to_expression(target_span);
+                let expression_span = target_span.to(expression.span);
                 AssignmentStatement {
-                    identifier,
-                    expression: Expression::BinaryOperation(BinaryOperation {
-                        operation_type,
-                        left_expression: Box::new(Expression::Identifier(ident)),
-                        right_expression: Box::new(expression),
-                    }),
+                    target,
+                    expression: Expression::new(
+                        ExpressionKind::BinaryOperation(BinaryOperation {
+                            operation_type,
+                            left_expression: Box::new(left_expression),
+                            right_expression: Box::new(expression),
+                        }),
+                        expression_span,
+                    ),
+                    span,
                 }
             }
         };
@@ -291,7 +396,60 @@ impl Parser<'_> {
         Ok(Some(assignment_statement))
     }
 
+    fn get_next_assignment_target(&mut self) -> Result<Option<AssignmentTarget>, SyntaxError> {
+        let old_lexer = self.lexer.clone();
+
+        if self.consume_simple_token(SimpleToken::LParen)?.is_some() {
+            let mut targets: Vec<AssignmentTarget> = vec![];
+            let mut has_trailing_comma = false;
+
+            while let Some(target) = self.get_next_assignment_target()? {
+                targets.push(target);
+
+                has_trailing_comma = self.consume_simple_token(SimpleToken::Comma)?.is_some();
+                if !has_trailing_comma {
+                    break;
+                }
+            }
+
+            if targets.is_empty() || self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+                self.lexer = old_lexer;
+                return Ok(None);
+            }
+
+            if targets.len() == 1 && !has_trailing_comma {
+                return Ok(targets.pop());
+            }
+
+            return Ok(Some(AssignmentTarget::Tuple(targets)));
+        }
+
+        let start = self.lexer.get_next_token_start();
+
+        let mut target = match self.get_next_identifier()? {
+            None => return Ok(None),
+            Some(identifier) => AssignmentTarget::Identifier(identifier),
+        };
+
+        loop {
+            let span = self.span_from(start);
+
+            let index = match self.get_next_index()? {
+                None => break,
+                Some(index) => index,
+            };
+
+            target = AssignmentTarget::Index(Index {
+                expression: Box::new(target.to_expression(span)),
+                index: Box::new(index),
+            });
+        }
+
+        Ok(Some(target))
+    }
+
     fn get_next_if_statement(&mut self) -> Result<Option<IfStatement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let _if = match self.get_next_if_statement_1()? {
             None => return Ok(None),
             Some(_if) => _if,
@@ -303,6 +461,7 @@ impl Parser<'_> {
             _if,
             else_if,
             _else,
+            span: self.span_from(start),
         }))
     }
 
@@ -462,17 +621,17 @@ impl Parser<'_> {
 
         match first_token {
             Token::SimpleToken(simple_token) => match simple_token {
-                SimpleToken::Break => return Ok(Some(())),
+                SimpleToken::Break => Ok(Some(())),
                 _ => {
                     self.lexer = old_lexer;
-                    return Ok(None);
+                    Ok(None)
                 }
             },
             _ => {
                 self.lexer = old_lexer;
-                return Ok(None);
+                Ok(None)
             }
-        };
+        }
     }
 
     fn get_next_continue_statement(&mut self) -> Result<Option<()>, SyntaxError> {
@@ -488,20 +647,21 @@ impl Parser<'_> {
 
         match first_token {
             Token::SimpleToken(simple_token) => match simple_token {
-                SimpleToken::Continue => return Ok(Some(())),
+                SimpleToken::Continue => Ok(Some(())),
                 _ => {
                     self.lexer = old_lexer;
-                    return Ok(None);
+                    Ok(None)
                 }
             },
             _ => {
                 self.lexer = old_lexer;
-                return Ok(None);
+                Ok(None)
             }
-        };
+        }
     }
 
     fn get_next_loop_statement(&mut self) -> Result<Option<LoopStatement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let old_lexer = self.lexer.clone();
 
         let first_token = match self.lexer.get_next_token()? {
@@ -531,10 +691,14 @@ impl Parser<'_> {
             Some(block) => block,
         };
 
-        Ok(Some(LoopStatement { block }))
+        Ok(Some(LoopStatement {
+            block,
+            span: self.span_from(start),
+        }))
     }
 
     fn get_next_while_statement(&mut self) -> Result<Option<WhileStatement>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
         let old_lexer = self.lexer.clone();
 
         let first_token = match self.lexer.get_next_token()? {
@@ -569,6 +733,74 @@ impl Parser<'_> {
             Some(block) => block,
         };
 
-        Ok(Some(WhileStatement { condition, block }))
+        Ok(Some(WhileStatement {
+            condition,
+            block,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn get_next_enum_declaration(&mut self) -> Result<Option<EnumDeclaration>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
+        if self.consume_simple_token(SimpleToken::Enum)?.is_none() {
+            return Ok(None);
+        }
+
+        let identifier = match self.get_next_identifier()? {
+            None => return Err(SyntaxError::NoIdentifierInEnumDeclaration),
+            Some(identifier) => identifier,
+        };
+
+        if self.consume_simple_token(SimpleToken::LBrace)?.is_none() {
+            return Err(SyntaxError::NoVariantsInEnumDeclaration);
+        }
+
+        let mut variants: Vec<EnumVariantDeclaration> = vec![];
+        while let Some(variant) = self.get_next_enum_variant_declaration()? {
+            variants.push(variant);
+
+            if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RBrace)?.is_none() {
+            return Err(SyntaxError::UnmatchedBrace);
+        }
+
+        Ok(Some(EnumDeclaration {
+            identifier,
+            variants,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn get_next_enum_variant_declaration(
+        &mut self,
+    ) -> Result<Option<EnumVariantDeclaration>, SyntaxError> {
+        let identifier = match self.get_next_identifier()? {
+            None => return Ok(None),
+            Some(identifier) => identifier,
+        };
+
+        let mut fields: Vec<String> = vec![];
+        if self.consume_simple_token(SimpleToken::LParen)?.is_some() {
+            loop {
+                match self.get_next_identifier()? {
+                    None => break,
+                    Some(field) => fields.push(field),
+                }
+
+                if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                    break;
+                }
+            }
+
+            if self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+                return Err(SyntaxError::UnmatchedParen);
+            }
+        }
+
+        Ok(Some(EnumVariantDeclaration { identifier, fields }))
     }
 }
@@ -0,0 +1,146 @@
+use crate::{diagnostic::Span, lexer::SimpleToken, parser::Parser, syntax_error::SyntaxError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    pub kind: TypeAnnotationKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeAnnotationKind {
+    // `u32`, `string` or the name of an enum, which is `module::Enum` for
+    // enums from imported files.
+    Named(String),
+    Tuple(Vec<TypeAnnotation>),
+    // `{K: V}`
+    Map(Box<TypeAnnotation>, Box<TypeAnnotation>),
+    // `fn(A, B) -> R`
+    Function(Vec<TypeAnnotation>, Box<TypeAnnotation>),
+    // `Result<T, E>`
+    Result(Box<TypeAnnotation>, Box<TypeAnnotation>),
+}
+
+impl Parser<'_> {
+    pub fn get_next_type_annotation(&mut self) -> Result<Option<TypeAnnotation>, SyntaxError> {
+        let start = self.lexer.get_next_token_start();
+
+        let kind = if self.consume_simple_token(SimpleToken::LParen)?.is_some() {
+            let (elements, has_trailing_comma) = self.get_next_type_annotation_list()?;
+
+            if elements.len() == 1 && !has_trailing_comma {
+                return Ok(elements.into_iter().next());
+            }
+            TypeAnnotationKind::Tuple(elements)
+        } else if self.consume_simple_token(SimpleToken::LBrace)?.is_some() {
+            let key = self.get_next_type_annotation_or_error()?;
+            if self.consume_simple_token(SimpleToken::Colon)?.is_none() {
+                return Err(SyntaxError::NoColonInMapType);
+            }
+            let value = self.get_next_type_annotation_or_error()?;
+            if self.consume_simple_token(SimpleToken::RBrace)?.is_none() {
+                return Err(SyntaxError::UnmatchedBrace);
+            }
+            TypeAnnotationKind::Map(Box::new(key), Box::new(value))
+        } else {
+            let name = match self.get_next_path()? {
+                None => return Ok(None),
+                Some(path) => path.join("::"),
+            };
+
+            if name == "fn" && self.consume_simple_token(SimpleToken::LParen)?.is_some() {
+                let (parameters, _) = self.get_next_type_annotation_list()?;
+                if self.consume_simple_token(SimpleToken::Arrow)?.is_none() {
+                    return Err(SyntaxError::NoResultInFunctionType);
+                }
+                let result = self.get_next_type_annotation_or_error()?;
+                TypeAnnotationKind::Function(parameters, Box::new(result))
+            } else if name == "Result" && self.consume_simple_token(SimpleToken::Less)?.is_some() {
+                let ok = self.get_next_type_annotation_or_error()?;
+                if self.consume_simple_token(SimpleToken::Comma)?.is_none() {
+                    return Err(SyntaxError::NoCommaInResultType);
+                }
+                let err = self.get_next_type_annotation_or_error()?;
+                if self.consume_simple_token(SimpleToken::Greater)?.is_none() {
+                    return Err(SyntaxError::UnmatchedAngleBracket);
+                }
+                TypeAnnotationKind::Result(Box::new(ok), Box::new(err))
+            } else {
+                TypeAnnotationKind::Named(name)
+            }
+        };
+
+        Ok(Some(TypeAnnotation {
+            kind,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn get_next_type_annotation_or_error(&mut self) -> Result<TypeAnnotation, SyntaxError> {
+        match self.get_next_type_annotation()? {
+            None => Err(SyntaxError::NoTypeInTypeAnnotation),
+            Some(annotation) => Ok(annotation),
+        }
+    }
+
+    // Parses the rest of a parenthesized list of types after the `(`.
+    fn get_next_type_annotation_list(
+        &mut self,
+    ) -> Result<(Vec<TypeAnnotation>, bool), SyntaxError> {
+        let mut elements: Vec<TypeAnnotation> = vec![];
+        let mut has_trailing_comma = false;
+
+        while let Some(element) = self.get_next_type_annotation()? {
+            elements.push(element);
+
+            has_trailing_comma = self.consume_simple_token(SimpleToken::Comma)?.is_some();
+            if !has_trailing_comma {
+                break;
+            }
+        }
+
+        if self.consume_simple_token(SimpleToken::RParen)?.is_none() {
+            return Err(SyntaxError::UnmatchedParen);
+        }
+
+        Ok((elements, has_trailing_comma))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parser;
+
+    use super::{TypeAnnotation, TypeAnnotationKind};
+
+    fn named(name: &str) -> TypeAnnotation {
+        TypeAnnotation {
+            kind: TypeAnnotationKind::Named(name.to_string()),
+            span: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_get_next_type_annotation() {
+        let mut p = Parser::new("fn((u32, string), {string: Shape}) -> Result<u32, string>");
+        let annotation = p.get_next_type_annotation().unwrap().unwrap();
+
+        let tuple = TypeAnnotation {
+            kind: TypeAnnotationKind::Tuple(vec![named("u32"), named("string")]),
+            span: Default::default(),
+        };
+        let map = TypeAnnotation {
+            kind: TypeAnnotationKind::Map(Box::new(named("string")), Box::new(named("Shape"))),
+            span: Default::default(),
+        };
+
+        let result = TypeAnnotation {
+            kind: TypeAnnotationKind::Result(Box::new(named("u32")), Box::new(named("string"))),
+            span: Default::default(),
+        };
+
+        assert_eq!(
+            annotation.kind,
+            TypeAnnotationKind::Function(vec![tuple, map], Box::new(result))
+        );
+    }
+}
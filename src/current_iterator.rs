@@ -25,6 +25,10 @@ where
     pub fn current(&self) -> Option<I::Item> {
         self.current.clone()
     }
+
+    pub fn inner(&self) -> &I {
+        &self.iterator
+    }
 }
 
 impl<I> Iterator for CurrentIterator<I>
@@ -0,0 +1,316 @@
+use core::fmt;
+
+// A range of bytes in the source text.
+#[derive(Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    // The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+// Spans are ignored when comparing syntax trees, so that trees built by hand
+// in tests compare equal to parsed ones.
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// A message about the source, pointing at the code it is about.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // The name of the lint that reported it, if any.
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub label: Label,
+    // Other code that explains the primary label.
+    pub secondary_labels: Vec<Label>,
+    // More information about the problem.
+    pub note: Option<String>,
+    // A suggestion for how to fix the problem.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span, label: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message,
+            label: Label {
+                span,
+                message: label,
+            },
+            secondary_labels: vec![],
+            note: None,
+            help: None,
+        }
+    }
+
+    pub fn warning(message: String, span: Span, label: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message, span, label)
+        }
+    }
+
+    pub fn with_secondary_label(mut self, span: Span, message: String) -> Diagnostic {
+        self.secondary_labels.push(Label { span, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.note = Some(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Diagnostic {
+        self.help = Some(help);
+        self
+    }
+
+    // Renders the diagnostic like rustc does, e.g.
+    //
+    // error: mismatched types
+    //  --> examples/fib.l:1:14
+    //   |
+    // 1 | let a: u32 = "a";
+    //   |              ^^^ expected `u32`, found `string`
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let (line, column) = get_line_and_column(source, self.label.span.start);
+
+        let last_line = self
+            .secondary_labels
+            .iter()
+            .chain([&self.label])
+            .map(|label| get_line_and_column(source, label.span.start).0)
+            .max()
+            .unwrap();
+        let gutter = " ".repeat(last_line.to_string().len());
+
+        let mut rendered = match self.code {
+            None => format!("{}: {}\n", self.severity, self.message),
+            Some(code) => format!("{}[{}]: {}\n", self.severity, code, self.message),
+        };
+        rendered.push_str(&format!("{}--> {}:{}:{}\n", gutter, path, line, column));
+        rendered.push_str(&format!("{} |\n", gutter));
+
+        // Labels are shown in source order, the primary one underlined with `^`.
+        let mut labels: Vec<(&Label, char)> = std::iter::once((&self.label, '^'))
+            .chain(self.secondary_labels.iter().map(|label| (label, '-')))
+            .collect();
+        labels.sort_by_key(|(label, _)| get_line_and_column(source, label.span.start).0);
+
+        let mut previous_line = None;
+        for (label, marker) in labels {
+            render_label(
+                &mut rendered,
+                source,
+                &gutter,
+                label,
+                marker,
+                &mut previous_line,
+            );
+        }
+
+        if let Some(note) = &self.note {
+            rendered.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        if let Some(help) = &self.help {
+            rendered.push_str(&format!("{} = help: {}\n", gutter, help));
+        }
+
+        rendered
+    }
+}
+
+// A file of the program, whose spans start at `start`.
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+    pub start: usize,
+}
+
+// The files of a program. Each file gets its own range of offsets, so that a
+// span says which file it is in.
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    // Adds a file and returns the offset its spans start at.
+    pub fn add(&mut self, path: String, source: String) -> usize {
+        // The gap keeps a span at the end of one file out of the next.
+        let start = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.source.len() + 1);
+        self.files.push(SourceFile {
+            path,
+            source,
+            start,
+        });
+        start
+    }
+
+    pub fn file(&self, span: Span) -> &SourceFile {
+        self.files
+            .iter()
+            .rev()
+            .find(|file| file.start <= span.start)
+            .unwrap()
+    }
+
+    // The source code `span` covers.
+    pub fn text(&self, span: Span) -> &str {
+        let file = self.file(span);
+        &file.source[span.start - file.start..span.end - file.start]
+    }
+
+    // Renders the diagnostic in the file of its primary label. Secondary
+    // labels in other files are left out.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let file = self.file(diagnostic.label.span);
+        let end = file.start + file.source.len();
+        let shift = |label: &Label| Label {
+            span: Span {
+                start: label.span.start - file.start,
+                end: label.span.end - file.start,
+            },
+            message: label.message.clone(),
+        };
+
+        let diagnostic = Diagnostic {
+            label: shift(&diagnostic.label),
+            secondary_labels: diagnostic
+                .secondary_labels
+                .iter()
+                .filter(|label| (file.start..=end).contains(&label.span.start))
+                .map(shift)
+                .collect(),
+            ..diagnostic.clone()
+        };
+        diagnostic.render(&file.source, &file.path)
+    }
+}
+
+// Labels on the same line as the previous one share its copy of the source line.
+fn render_label(
+    rendered: &mut String,
+    source: &str,
+    gutter: &str,
+    label: &Label,
+    marker: char,
+    previous_line: &mut Option<usize>,
+) {
+    let (line, column) = get_line_and_column(source, label.span.start);
+    let text = source.lines().nth(line - 1).unwrap_or("");
+
+    // Spans over several lines are underlined up to the end of the first one.
+    let line_end = source[label.span.start.min(source.len())..]
+        .find('\n')
+        .map_or(source.len(), |offset| label.span.start + offset);
+    let width = source
+        .get(label.span.start..label.span.end.min(line_end))
+        .map_or(1, |text| text.chars().count().max(1));
+
+    if *previous_line != Some(line) {
+        rendered.push_str(&format!(
+            "{:>width$} | {}\n",
+            line,
+            text,
+            width = gutter.len()
+        ));
+        *previous_line = Some(line);
+    }
+    let underline = format!(
+        "{} | {}{}",
+        gutter,
+        " ".repeat(column - 1),
+        marker.to_string().repeat(width)
+    );
+    if label.message.is_empty() {
+        rendered.push_str(&format!("{}\n", underline));
+    } else {
+        rendered.push_str(&format!("{} {}\n", underline, label.message));
+    }
+}
+
+// One-based line and column (in characters) of a byte offset.
+fn get_line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Diagnostic, Span};
+
+    #[test]
+    fn test_render() {
+        let source = "let a = 1;\nlet b: u32 = \"b\";\n";
+        let diagnostic = Diagnostic::error(
+            "mismatched types".to_string(),
+            Span { start: 24, end: 27 },
+            "expected `u32`, found `string`".to_string(),
+        )
+        .with_secondary_label(
+            Span { start: 18, end: 21 },
+            "expected due to this".to_string(),
+        )
+        .with_help("try a number".to_string());
+
+        assert_eq!(
+            diagnostic.render(source, "test.l"),
+            "error: mismatched types
+ --> test.l:2:14
+  |
+2 | let b: u32 = \"b\";
+  |              ^^^ expected `u32`, found `string`
+  |        --- expected due to this
+  = help: try a number
+"
+        );
+    }
+}
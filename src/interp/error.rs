@@ -0,0 +1,151 @@
+use std::fmt;
+
+use crate::{
+    ast::expression::BinaryOperationType,
+    diagnostic::{Diagnostic, SourceMap, Span},
+    ir::{Label, Register},
+    typeck::types::IntegerType,
+};
+
+// An error that stops the program while it runs.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    // The index of the failing instruction.
+    pub pc: usize,
+    // The code the failing instruction was generated from.
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RuntimeErrorKind {
+    Overflow {
+        operation_type: BinaryOperationType,
+        ty: IntegerType,
+    },
+    NegateOverflow {
+        ty: IntegerType,
+    },
+    DivisionByZero,
+    RemainderByZero,
+    // An error that `?` passed up out of the program.
+    UnhandledError(String),
+    AssertionFailed,
+    AssertionEqualFailed {
+        left: String,
+        right: String,
+    },
+    Panic(String),
+    // Reading a key that is not in a map, which is printed as it would be.
+    KeyNotFound(String),
+    ShiftOverflow {
+        ty: IntegerType,
+        amount: i128,
+    },
+    // Reading a register that was never written or jumping to a label that
+    // was never emitted are bugs in the compiler rather than the program.
+    UnsetRegister(Register),
+    MissingLabel(Label),
+}
+
+impl fmt::Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::Overflow { operation_type, .. } => {
+                let operation = match operation_type {
+                    BinaryOperationType::Add => "add",
+                    BinaryOperationType::Subtract => "subtract",
+                    BinaryOperationType::Multiply => "multiply",
+                    BinaryOperationType::Divide => "divide",
+                    BinaryOperationType::Exponentiate => "raise to a power",
+                    _ => unreachable!(),
+                };
+                write!(f, "attempt to {} with overflow", operation)
+            }
+            RuntimeErrorKind::NegateOverflow { .. } => write!(f, "attempt to negate with overflow"),
+            RuntimeErrorKind::DivisionByZero => write!(f, "attempt to divide by zero"),
+            RuntimeErrorKind::RemainderByZero => write!(
+                f,
+                "attempt to calculate the remainder with a divisor of zero"
+            ),
+            RuntimeErrorKind::UnhandledError(error) => write!(f, "unhandled error: {}", error),
+            RuntimeErrorKind::AssertionFailed => write!(f, "assertion failed"),
+            RuntimeErrorKind::AssertionEqualFailed { left, right } => write!(
+                f,
+                "assertion `left == right` failed: left is `{}`, right is `{}`",
+                left, right
+            ),
+            RuntimeErrorKind::Panic(message) => write!(f, "{}", message),
+            RuntimeErrorKind::KeyNotFound(key) => write!(f, "key {} not found in map", key),
+            RuntimeErrorKind::ShiftOverflow { ty, amount } => {
+                write!(f, "attempt to shift a `{}` by {}", ty, amount)
+            }
+            RuntimeErrorKind::UnsetRegister(register) => {
+                write!(f, "register {} was read before it was written", register)
+            }
+            RuntimeErrorKind::MissingLabel(label) => write!(f, "jump to missing label {}", label),
+        }
+    }
+}
+
+impl RuntimeError {
+    // `text` is the code `span` covers.
+    pub fn to_diagnostic(&self, text: &str, span: Span) -> Diagnostic {
+        let label = match &self.kind {
+            RuntimeErrorKind::Overflow { ty, .. } | RuntimeErrorKind::NegateOverflow { ty } => {
+                format!("the result does not fit into `{}`", ty)
+            }
+            RuntimeErrorKind::DivisionByZero | RuntimeErrorKind::RemainderByZero => {
+                "the divisor is zero".to_string()
+            }
+            RuntimeErrorKind::UnhandledError(_) => {
+                "this `?` returned the error from the program".to_string()
+            }
+            RuntimeErrorKind::AssertionFailed => "this is false".to_string(),
+            RuntimeErrorKind::AssertionEqualFailed { .. } => "the values are not equal".to_string(),
+            RuntimeErrorKind::Panic(_) => "the program panicked here".to_string(),
+            RuntimeErrorKind::KeyNotFound(_) => {
+                "use `contains` to check for the key first".to_string()
+            }
+            RuntimeErrorKind::ShiftOverflow { ty, .. } => {
+                format!("`{}` can only be shifted by 0 to {}", ty, ty.bits() - 1)
+            }
+            RuntimeErrorKind::UnsetRegister(_) | RuntimeErrorKind::MissingLabel(_) => String::new(),
+        };
+        // The span of a failed assertion is the condition that was false.
+        let message = match self.kind {
+            RuntimeErrorKind::AssertionFailed => {
+                format!("assertion failed: {}", text)
+            }
+            _ => self.kind.to_string(),
+        };
+        let diagnostic = Diagnostic::error(message, span, label)
+            .with_note(format!("at instruction {} of the IR", self.pc));
+
+        let name = match &self.kind {
+            RuntimeErrorKind::Overflow { operation_type, .. } => match operation_type {
+                BinaryOperationType::Add => "add",
+                BinaryOperationType::Subtract => "sub",
+                BinaryOperationType::Multiply => "mul",
+                BinaryOperationType::Exponentiate => "pow",
+                _ => return diagnostic,
+            },
+            _ => return diagnostic,
+        };
+        diagnostic.with_help(format!(
+            "use `wrapping_{}` or `saturating_{}` if overflow is expected",
+            name, name
+        ))
+    }
+
+    // Errors from code that has no span are printed without a snippet.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        match self.span {
+            Some(span) => sources.render(&self.to_diagnostic(sources.text(span), span)),
+            None => format!(
+                "error: {}\n = note: at instruction {} of the IR\n",
+                self.kind, self.pc
+            ),
+        }
+    }
+}
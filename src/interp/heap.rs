@@ -0,0 +1,199 @@
+use std::{
+    mem::size_of,
+    ops::{Index, IndexMut},
+};
+
+use super::value::{Object, Value};
+
+// Collections start once this many bytes are live, so small programs never collect.
+const INITIAL_COLLECTION_THRESHOLD: usize = 1 << 20;
+
+#[derive(Debug, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    pub objects_freed: usize,
+    pub bytes_freed: usize,
+}
+
+// Objects referenced by `Value::Reference`, reclaimed by a mark-sweep
+// collector. Freed slots are reused, so a reference is only valid while the
+// object it points to is reachable from a root.
+#[derive(Debug)]
+pub struct Heap<'a> {
+    objects: Vec<Option<Object<'a>>>,
+    free_slots: Vec<usize>,
+    // Estimated size of the objects allocated since the last collection plus
+    // the ones that survived it.
+    bytes_allocated: usize,
+    next_collection: usize,
+    // Collects before every allocation, to flush out missing roots in tests.
+    stress: bool,
+    pub stats: GcStats,
+}
+
+impl<'a> Heap<'a> {
+    pub fn new(stress: bool) -> Heap<'a> {
+        Heap {
+            objects: vec![],
+            free_slots: vec![],
+            bytes_allocated: 0,
+            next_collection: INITIAL_COLLECTION_THRESHOLD,
+            stress,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.stress || self.bytes_allocated >= self.next_collection
+    }
+
+    pub fn allocate(&mut self, object: Object<'a>) -> usize {
+        self.bytes_allocated += object_size(&object);
+
+        match self.free_slots.pop() {
+            Some(reference) => {
+                self.objects[reference] = Some(object);
+                reference
+            }
+            None => {
+                self.objects.push(Some(object));
+                self.objects.len() - 1
+            }
+        }
+    }
+
+    // Frees every object that is not reachable from `roots`.
+    pub fn collect(&mut self, roots: impl Iterator<Item = Value>) {
+        let mut marked = vec![false; self.objects.len()];
+        let mut worklist: Vec<usize> = roots.filter_map(as_reference).collect();
+
+        while let Some(reference) = worklist.pop() {
+            if marked[reference] {
+                continue;
+            }
+            marked[reference] = true;
+
+            worklist.extend(self[reference].references().filter_map(as_reference));
+        }
+
+        let mut live_bytes = 0;
+        for (reference, is_marked) in marked.into_iter().enumerate() {
+            let size = match &self.objects[reference] {
+                None => continue,
+                Some(object) => object_size(object),
+            };
+
+            if is_marked {
+                live_bytes += size;
+            } else {
+                self.objects[reference] = None;
+                self.free_slots.push(reference);
+                self.stats.objects_freed += 1;
+                self.stats.bytes_freed += size;
+            }
+        }
+
+        self.stats.collections += 1;
+        self.bytes_allocated = live_bytes;
+        self.next_collection = INITIAL_COLLECTION_THRESHOLD.max(live_bytes * 2);
+    }
+
+    #[cfg(test)]
+    fn live_objects(&self) -> usize {
+        self.objects.iter().flatten().count()
+    }
+}
+
+impl<'a> Index<usize> for Heap<'a> {
+    type Output = Object<'a>;
+
+    fn index(&self, reference: usize) -> &Self::Output {
+        self.objects[reference]
+            .as_ref()
+            .expect("dangling reference to a freed object")
+    }
+}
+
+impl IndexMut<usize> for Heap<'_> {
+    fn index_mut(&mut self, reference: usize) -> &mut Self::Output {
+        self.objects[reference]
+            .as_mut()
+            .expect("dangling reference to a freed object")
+    }
+}
+
+impl Object<'_> {
+    // The values this object points to, which the collector has to keep alive.
+    fn references(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        match self {
+            Object::Variant { fields, .. } => Box::new(fields.iter().copied()),
+            Object::String(_) => Box::new(std::iter::empty()),
+            Object::Map(map) => Box::new(map.entries().flat_map(|(key, value)| [*key, *value])),
+            Object::Tuple(elements) => Box::new(elements.iter().copied()),
+            Object::Closure { captures, .. } => Box::new(captures.iter().copied()),
+        }
+    }
+}
+
+fn as_reference(value: Value) -> Option<usize> {
+    match value {
+        Value::Integer(_) => None,
+        Value::Reference(reference) => Some(reference),
+    }
+}
+
+// An estimate of the memory an object owns, for scheduling collections and reporting.
+fn object_size(object: &Object) -> usize {
+    size_of::<Object>()
+        + match object {
+            Object::Variant { fields, .. } => fields.capacity() * size_of::<Value>(),
+            Object::String(string) => string.capacity(),
+            Object::Map(map) => map.heap_size(),
+            Object::Tuple(elements) => elements.capacity() * size_of::<Value>(),
+            Object::Closure { captures, .. } => captures.capacity() * size_of::<Value>(),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Heap;
+    use crate::interp::value::{Object, Value};
+
+    #[test]
+    fn test_collect_frees_unreachable_objects() {
+        let mut heap = Heap::new(true);
+
+        let string = heap.allocate(Object::String("kept".to_string()));
+        let tuple = heap.allocate(Object::Tuple(vec![
+            Value::Integer(1),
+            Value::Reference(string),
+        ]));
+        heap.allocate(Object::String("garbage".to_string()));
+
+        heap.collect([Value::Reference(tuple)].into_iter());
+
+        assert_eq!(heap.live_objects(), 2);
+        assert_eq!(heap.stats.collections, 1);
+        assert_eq!(heap.stats.objects_freed, 1);
+        assert!(matches!(&heap[string], Object::String(string) if string == "kept"));
+
+        // The freed slot is reused.
+        assert_eq!(heap.allocate(Object::Tuple(vec![])), 2);
+    }
+
+    #[test]
+    fn test_collect_handles_cycles() {
+        let mut heap = Heap::new(true);
+
+        let tuple = heap.allocate(Object::Tuple(vec![]));
+        if let Object::Tuple(elements) = &mut heap[tuple] {
+            elements.push(Value::Reference(tuple));
+        }
+
+        heap.collect([Value::Reference(tuple)].into_iter());
+        assert_eq!(heap.live_objects(), 1);
+
+        heap.collect(std::iter::empty());
+        assert_eq!(heap.live_objects(), 0);
+    }
+}
@@ -1,9 +1,70 @@
+mod error;
+mod heap;
+mod value;
+
 use std::collections::HashMap;
 
-use crate::ir::{IRStatement, Label, Register, IR};
+use crate::{
+    ast::expression::BinaryOperationType,
+    ir::{IRStatement, Label, Overflow, Register, IR},
+    typeck::types::IntegerType,
+};
+pub use error::{RuntimeError, RuntimeErrorKind};
+use heap::Heap;
+use value::{
+    copy_maps, format_nested_value, format_value, to_map_key, values_equal, Map, MapKey, Object,
+    Value,
+};
+
+// The caller's state, saved while a closure runs.
+struct Frame {
+    registers: HashMap<Register, Value>,
+    return_pc: usize,
+    rd: Register,
+}
+
+// What arithmetic without an explicit overflow behaviour does when the result
+// does not fit its type.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum OverflowMode {
+    Wrap,
+    #[default]
+    Trap,
+}
 
-pub fn interpret(ir: &IR) -> u32 {
-    let mut registers: HashMap<Register, u32> = HashMap::new();
+#[derive(Default)]
+pub struct Options {
+    pub gc_stats: bool,
+    pub gc_stress: bool,
+    pub overflow: OverflowMode,
+}
+
+// How a program ended when it did not fail.
+#[derive(Debug, PartialEq)]
+pub enum Exit {
+    // Ran to the end, with the value of the last instruction.
+    Finished(i128),
+    // Called `exit` with the code.
+    Code(i32),
+}
+
+pub fn interpret(ir: &IR, options: &Options) -> Result<Exit, RuntimeError> {
+    let mut pc = 0;
+    run(ir, options, &mut pc).map_err(|kind| RuntimeError {
+        kind,
+        pc,
+        span: ir.spans.get(pc).copied(),
+    })
+}
+
+// Runs the program, leaving `pc` at the instruction that failed if it does.
+fn run(ir: &IR, options: &Options, pc: &mut usize) -> Result<Exit, RuntimeErrorKind> {
+    let mut registers: HashMap<Register, Value> = HashMap::new();
+    let mut frames: Vec<Frame> = vec![];
+    // Passed from `Call` to the `Enter` of the closure being called.
+    let mut arguments: Vec<Value> = vec![];
+    let mut captures: Vec<Value> = vec![];
+    let mut heap = Heap::new(options.gc_stress);
     let mut label_locations: HashMap<Label, usize> = HashMap::new();
 
     for (i, statement) in ir.statements.iter().enumerate() {
@@ -12,205 +73,947 @@ pub fn interpret(ir: &IR) -> u32 {
         }
     }
 
-    let mut pc = 0;
-    let mut last_rd_val: u32 = 0;
+    let mut last_rd_val: i128 = 0;
+    let mut exit_code = None;
 
-    while pc < ir.statements.len() {
-        match &ir.statements[pc] {
+    while *pc < ir.statements.len() {
+        match &ir.statements[*pc] {
             IRStatement::LoadImmediate { rd, imm } => {
                 let rd_val = *imm;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
-            IRStatement::Add { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val + rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
-                last_rd_val = rd_val;
+            IRStatement::Move { rd, rs1 } => {
+                let rs1_val = get_value(&registers, rs1)?;
+                registers.insert(rd.clone(), rs1_val);
+                if let Value::Integer(rd_val) = rs1_val {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
             }
-            IRStatement::Subtract { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val - rs2_val;
+            IRStatement::Clone { rd, rs1 } => {
+                let rs1_val = get_value(&registers, rs1)?;
+                collect_garbage(&mut heap, &registers, &frames);
+                let rd_val = copy_maps(&mut heap, rs1_val);
                 registers.insert(rd.clone(), rd_val);
-                pc += 1;
-                last_rd_val = rd_val;
+                if let Value::Integer(rd_val) = rd_val {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
             }
-            IRStatement::Multiply { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val * rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
-                last_rd_val = rd_val;
+            IRStatement::Add {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                let result = arithmetic(
+                    BinaryOperationType::Add,
+                    rs1_val,
+                    rs2_val,
+                    *ty,
+                    *overflow,
+                    options.overflow,
+                );
+                let stored =
+                    store_arithmetic(&mut heap, &mut registers, &frames, rd, result, *overflow)?;
+                if let Some(rd_val) = stored {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
             }
-            IRStatement::Divide { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val / rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
-                last_rd_val = rd_val;
+            IRStatement::Subtract {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                let result = arithmetic(
+                    BinaryOperationType::Subtract,
+                    rs1_val,
+                    rs2_val,
+                    *ty,
+                    *overflow,
+                    options.overflow,
+                );
+                let stored =
+                    store_arithmetic(&mut heap, &mut registers, &frames, rd, result, *overflow)?;
+                if let Some(rd_val) = stored {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
+            }
+            IRStatement::Multiply {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                let result = arithmetic(
+                    BinaryOperationType::Multiply,
+                    rs1_val,
+                    rs2_val,
+                    *ty,
+                    *overflow,
+                    options.overflow,
+                );
+                let stored =
+                    store_arithmetic(&mut heap, &mut registers, &frames, rd, result, *overflow)?;
+                if let Some(rd_val) = stored {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
+            }
+            IRStatement::Divide {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                let result = arithmetic(
+                    BinaryOperationType::Divide,
+                    rs1_val,
+                    rs2_val,
+                    *ty,
+                    *overflow,
+                    options.overflow,
+                );
+                let stored =
+                    store_arithmetic(&mut heap, &mut registers, &frames, rd, result, *overflow)?;
+                if let Some(rd_val) = stored {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
             }
             IRStatement::Modulus { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                if rs2_val == 0 {
+                    return Err(RuntimeErrorKind::RemainderByZero);
+                }
                 let rd_val = rs1_val % rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
-            IRStatement::Exponentiate { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val.pow(rs2_val as u32);
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
-                last_rd_val = rd_val;
+            IRStatement::Exponentiate {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
+                let result = arithmetic(
+                    BinaryOperationType::Exponentiate,
+                    rs1_val,
+                    rs2_val,
+                    *ty,
+                    *overflow,
+                    options.overflow,
+                );
+                let stored =
+                    store_arithmetic(&mut heap, &mut registers, &frames, rd, result, *overflow)?;
+                if let Some(rd_val) = stored {
+                    last_rd_val = rd_val;
+                }
+                *pc += 1;
             }
             IRStatement::Equal { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = if rs1_val == rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                let rs1_val = get_value(&registers, rs1)?;
+                let rs2_val = get_value(&registers, rs2)?;
+                let rd_val = if values_equal(&heap, rs1_val, rs2_val) {
+                    1
+                } else {
+                    0
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::NotEqual { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = if rs1_val != rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                let rs1_val = get_value(&registers, rs1)?;
+                let rs2_val = get_value(&registers, rs2)?;
+                let rd_val = if !values_equal(&heap, rs1_val, rs2_val) {
+                    1
+                } else {
+                    0
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::GreaterEqual { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val >= rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::LessEqual { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val <= rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::Greater { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val > rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::Less { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val < rs2_val { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::LogicalAnd { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val != 0 && rs2_val != 0 { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::LogicalOr { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = if rs1_val != 0 || rs2_val != 0 { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::LogicalNot { rd, rs1 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
                 let rd_val = if rs1_val == 0 { 1 } else { 0 };
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::Negate { rd, rs1, ty } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                // Only the smallest value of a signed type overflows.
+                let rd_val = arithmetic(
+                    BinaryOperationType::Subtract,
+                    0,
+                    rs1_val,
+                    *ty,
+                    Overflow::Default,
+                    options.overflow,
+                )
+                .map_err(|_| RuntimeErrorKind::NegateOverflow { ty: *ty })?;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::BitwiseNot { rd, rs1, ty } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rd_val = ty.wrap(!rs1_val);
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::Cast { rd, rs1, ty } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rd_val = ty.wrap(rs1_val);
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::BitwiseAnd { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = rs1_val & rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::BitwiseOr { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = rs1_val | rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::BitwiseXor { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_integer(&registers, rs2)?;
                 let rd_val = rs1_val ^ rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
-            IRStatement::LeftShift { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
-                let rd_val = rs1_val << rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+            IRStatement::LeftShift { rd, rs1, rs2, ty } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_shift_amount(&registers, rs2, *ty)?;
+                let rd_val = ty.wrap(rs1_val.wrapping_shl(rs2_val));
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
-            IRStatement::RightShift { rd, rs1, rs2 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                let rs2_val = *registers.get(rs2).unwrap();
+            IRStatement::RightShift { rd, rs1, rs2, ty } => {
+                let rs1_val = get_integer(&registers, rs1)?;
+                let rs2_val = get_shift_amount(&registers, rs2, *ty)?;
                 let rd_val = rs1_val >> rs2_val;
-                registers.insert(rd.clone(), rd_val);
-                pc += 1;
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
                 last_rd_val = rd_val;
             }
             IRStatement::Branch { label } => {
-                pc = label_locations[label];
+                *pc = get_label(&label_locations, label)?;
             }
             IRStatement::BranchNotZero { rs1, label } => {
-                let rs1_val = *registers.get(rs1).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
                 if rs1_val != 0 {
-                    pc = label_locations.get(label).unwrap().clone();
+                    *pc = get_label(&label_locations, label)?;
                 } else {
-                    pc += 1;
+                    *pc += 1;
                 }
             }
             IRStatement::BranchZero { rs1, label } => {
-                let rs1_val = *registers.get(rs1).unwrap();
+                let rs1_val = get_integer(&registers, rs1)?;
                 if rs1_val == 0 {
-                    pc = label_locations.get(label).unwrap().clone();
+                    *pc = get_label(&label_locations, label)?;
                 } else {
-                    pc += 1;
+                    *pc += 1;
                 }
             }
             IRStatement::Label { label: _ } => {
-                pc += 1;
+                *pc += 1;
             }
             IRStatement::Print { rs1 } => {
-                let rs1_val = *registers.get(rs1).unwrap();
-                println!("{}", rs1_val);
-                pc += 1;
+                let rs1_val = get_value(&registers, rs1)?;
+                println!("{}", format_value(&heap, rs1_val));
+                *pc += 1;
+            }
+            IRStatement::MakeVariant {
+                rd,
+                enum_name,
+                variant_name,
+                tag,
+                fields,
+            } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| get_value(&registers, field))
+                    .collect::<Result<_, _>>()?;
+                let rd_val = allocate(
+                    &mut heap,
+                    &registers,
+                    &frames,
+                    Object::Variant {
+                        enum_name,
+                        variant_name,
+                        tag: *tag,
+                        fields,
+                    },
+                );
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::LoadTag { rd, rs1 } => {
+                let rd_val = match get_object(&heap, &registers, rs1)? {
+                    Object::Variant { tag, .. } => *tag as i128,
+                    _ => panic!("expected an enum variant in {}", rs1),
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::LoadField { rd, rs1, index } => {
+                let rd_val = match get_object(&heap, &registers, rs1)? {
+                    Object::Variant { fields, .. } => fields[*index as usize],
+                    Object::Tuple(elements) => elements[*index as usize],
+                    _ => panic!("expected an enum variant or a tuple in {}", rs1),
+                };
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::MakeTuple { rd, elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| get_value(&registers, element))
+                    .collect::<Result<_, _>>()?;
+                let rd_val = allocate(&mut heap, &registers, &frames, Object::Tuple(elements));
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::MakeClosure {
+                rd,
+                label,
+                captures,
+            } => {
+                collect_garbage(&mut heap, &registers, &frames);
+                let captures = captures
+                    .iter()
+                    .map(|capture| Ok(copy_maps(&mut heap, get_value(&registers, capture)?)))
+                    .collect::<Result<_, _>>()?;
+                let rd_val = Value::Reference(heap.allocate(Object::Closure { label, captures }));
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::Enter {
+                parameters,
+                captures: capture_registers,
+            } => {
+                if parameters.len() != arguments.len() {
+                    panic!(
+                        "expected {} arguments but got {}",
+                        parameters.len(),
+                        arguments.len()
+                    );
+                }
+                for (parameter, argument) in parameters.iter().zip(arguments.drain(..)) {
+                    registers.insert(parameter.clone(), argument);
+                }
+                for (register, capture) in capture_registers.iter().zip(captures.drain(..)) {
+                    registers.insert(register.clone(), capture);
+                }
+                *pc += 1;
+            }
+            IRStatement::Call {
+                rd,
+                rs1,
+                arguments: argument_registers,
+            } => {
+                let label = match get_object(&heap, &registers, rs1)? {
+                    Object::Closure {
+                        label,
+                        captures: closure_captures,
+                    } => {
+                        captures = closure_captures.clone();
+                        *label
+                    }
+                    _ => panic!("expected a closure in {}", rs1),
+                };
+                arguments = argument_registers
+                    .iter()
+                    .map(|argument| get_value(&registers, argument))
+                    .collect::<Result<_, _>>()?;
+
+                frames.push(Frame {
+                    registers: std::mem::take(&mut registers),
+                    return_pc: *pc + 1,
+                    rd: rd.clone(),
+                });
+                *pc = get_label(&label_locations, label)?;
+            }
+            IRStatement::Return { rs1 } => {
+                let rs1_val = get_value(&registers, rs1)?;
+                let frame = frames.pop().expect("returned outside of a closure");
+                registers = frame.registers;
+                registers.insert(frame.rd, rs1_val);
+                *pc = frame.return_pc;
+            }
+            IRStatement::LoadString { rd, value } => {
+                let rd_val = allocate(
+                    &mut heap,
+                    &registers,
+                    &frames,
+                    Object::String(value.clone()),
+                );
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::MakeMap { rd, entries } => {
+                let mut map = Map::default();
+                for (key, value) in entries {
+                    let key_val = get_value(&registers, key)?;
+                    let value_val = get_value(&registers, value)?;
+                    map.insert(to_map_key(&heap, key_val), key_val, value_val);
+                }
+                let rd_val = allocate(&mut heap, &registers, &frames, Object::Map(map));
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::MapGet { rd, rs1, rs2 } => {
+                let key_val = get_value(&registers, rs2)?;
+                let key = to_map_key(&heap, key_val);
+                let rd_val = match get_map(&heap, &registers, rs1)?.get(&key) {
+                    Some(rd_val) => rd_val,
+                    None => {
+                        return Err(RuntimeErrorKind::KeyNotFound(format_nested_value(
+                            &heap, key_val,
+                        )))
+                    }
+                };
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::MapInsert { rs1, rs2, rs3 } => {
+                let key_val = get_value(&registers, rs2)?;
+                let key = to_map_key(&heap, key_val);
+                let value_val = get_value(&registers, rs3)?;
+                get_map_mut(&mut heap, &registers, rs1)?.insert(key, key_val, value_val);
+                *pc += 1;
+            }
+            IRStatement::MapContains { rd, rs1, rs2 } => {
+                let key = to_map_key(&heap, get_value(&registers, rs2)?);
+                let rd_val = if get_map(&heap, &registers, rs1)?.contains(&key) {
+                    1
+                } else {
+                    0
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::MapRemove { rd, rs1, rs2 } => {
+                let key = to_map_key(&heap, get_value(&registers, rs2)?);
+                let rd_val = if get_map_mut(&mut heap, &registers, rs1)?.remove(&key) {
+                    1
+                } else {
+                    0
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
+            }
+            IRStatement::MapKeys { rd, rs1 } => {
+                // The keys are returned as a map from 0, 1, 2, ... in iteration order.
+                let mut keys = Map::default();
+                for (i, (key, _)) in get_map(&heap, &registers, rs1)?.entries().enumerate() {
+                    keys.insert(MapKey::Integer(i as i128), Value::Integer(i as i128), *key);
+                }
+                let rd_val = allocate(&mut heap, &registers, &frames, Object::Map(keys));
+                registers.insert(rd.clone(), rd_val);
+                *pc += 1;
+            }
+            IRStatement::ParseInteger { rd, rs1, ty } => {
+                let string = match get_object(&heap, &registers, rs1)? {
+                    Object::String(string) => string,
+                    _ => panic!("expected a string in {}", rs1),
+                };
+                let result = match string.parse::<i128>() {
+                    Ok(value) if value > ty.max() => {
+                        Err(format!("number too large to fit in `{}`", ty))
+                    }
+                    Ok(value) if value < ty.min() => {
+                        Err(format!("number too small to fit in `{}`", ty))
+                    }
+                    Ok(value) => Ok(Value::Integer(value)),
+                    Err(error) => Err(error.to_string()),
+                };
+                store_result(&mut heap, &mut registers, &frames, rd, result);
+                *pc += 1;
+            }
+            IRStatement::Fail { rs1 } => {
+                let error = format_value(&heap, get_value(&registers, rs1)?);
+                return Err(RuntimeErrorKind::UnhandledError(error));
+            }
+            IRStatement::Assert { rs1 } => {
+                if get_integer(&registers, rs1)? == 0 {
+                    return Err(RuntimeErrorKind::AssertionFailed);
+                }
+                *pc += 1;
+            }
+            IRStatement::AssertEqual { rs1, rs2 } => {
+                let rs1_val = get_value(&registers, rs1)?;
+                let rs2_val = get_value(&registers, rs2)?;
+                if !values_equal(&heap, rs1_val, rs2_val) {
+                    return Err(RuntimeErrorKind::AssertionEqualFailed {
+                        left: format_value(&heap, rs1_val),
+                        right: format_value(&heap, rs2_val),
+                    });
+                }
+                *pc += 1;
+            }
+            IRStatement::Panic { rs1 } => {
+                let message = match get_object(&heap, &registers, rs1)? {
+                    Object::String(message) => message.clone(),
+                    _ => panic!("expected a string in {}", rs1),
+                };
+                return Err(RuntimeErrorKind::Panic(message));
+            }
+            // The exit code is an `i32`, so it always fits.
+            IRStatement::Exit { rs1 } => {
+                exit_code = Some(get_integer(&registers, rs1)? as i32);
+                break;
+            }
+            IRStatement::Length { rd, rs1 } => {
+                let rd_val = match get_object(&heap, &registers, rs1)? {
+                    Object::Map(map) => map.len() as i128,
+                    Object::String(string) => string.chars().count() as i128,
+                    _ => panic!("len expects a map or a string"),
+                };
+                registers.insert(rd.clone(), Value::Integer(rd_val));
+                *pc += 1;
+                last_rd_val = rd_val;
             }
         }
     }
 
-    last_rd_val
+    if options.gc_stats {
+        eprintln!(
+            "gc: {} collections, {} objects freed, {} bytes freed",
+            heap.stats.collections, heap.stats.objects_freed, heap.stats.bytes_freed
+        );
+    }
+
+    Ok(match exit_code {
+        Some(code) => Exit::Code(code),
+        None => Exit::Finished(last_rd_val),
+    })
+}
+
+// Does arithmetic on integers of type `ty`. The exact result is worked out
+// first, and `overflow` decides what happens if it does not fit.
+pub(crate) fn arithmetic(
+    operation_type: BinaryOperationType,
+    rs1_val: i128,
+    rs2_val: i128,
+    ty: IntegerType,
+    overflow: Overflow,
+    mode: OverflowMode,
+) -> Result<i128, RuntimeErrorKind> {
+    // Operands are at most 64 bits wide, so only `*` and `**` can leave the
+    // range of i128, and saturating keeps their sign.
+    let (exact, wrapped) = match operation_type {
+        BinaryOperationType::Add => (rs1_val + rs2_val, rs1_val + rs2_val),
+        BinaryOperationType::Subtract => (rs1_val - rs2_val, rs1_val - rs2_val),
+        BinaryOperationType::Multiply => (
+            rs1_val.saturating_mul(rs2_val),
+            rs1_val.wrapping_mul(rs2_val),
+        ),
+        BinaryOperationType::Divide => {
+            if rs2_val == 0 {
+                return Err(RuntimeErrorKind::DivisionByZero);
+            }
+            (rs1_val / rs2_val, rs1_val / rs2_val)
+        }
+        BinaryOperationType::Exponentiate => (
+            rs1_val.saturating_pow(rs2_val as u32),
+            rs1_val.wrapping_pow(rs2_val as u32),
+        ),
+        _ => unreachable!(),
+    };
+
+    if (ty.min()..=ty.max()).contains(&exact) {
+        return Ok(exact);
+    }
+
+    match (overflow, mode) {
+        // Only the low bits are kept, which i128 wrapping preserves.
+        (Overflow::Wrap, _) | (Overflow::Default, OverflowMode::Wrap) => Ok(ty.wrap(wrapped)),
+        (Overflow::Saturate, _) => Ok(exact.clamp(ty.min(), ty.max())),
+        _ => Err(RuntimeErrorKind::Overflow { operation_type, ty }),
+    }
+}
+
+// Stores the result of arithmetic in `rd` and returns it. Checked arithmetic
+// stores a `Result` instead of failing, and returns None.
+fn store_arithmetic<'a>(
+    heap: &mut Heap<'a>,
+    registers: &mut HashMap<Register, Value>,
+    frames: &[Frame],
+    rd: &Register,
+    result: Result<i128, RuntimeErrorKind>,
+    overflow: Overflow,
+) -> Result<Option<i128>, RuntimeErrorKind> {
+    if overflow != Overflow::Checked {
+        let rd_val = result?;
+        registers.insert(rd.clone(), Value::Integer(rd_val));
+        return Ok(Some(rd_val));
+    }
+
+    let result = result.map(Value::Integer).map_err(|kind| kind.to_string());
+    store_result(heap, registers, frames, rd, result);
+    Ok(None)
+}
+
+// Stores `Result::Ok(value)` or `Result::Err(message)` in `rd`.
+fn store_result<'a>(
+    heap: &mut Heap<'a>,
+    registers: &mut HashMap<Register, Value>,
+    frames: &[Frame],
+    rd: &Register,
+    result: Result<Value, String>,
+) {
+    // The tags are in the order of the variants of `Result`.
+    let (variant_name, tag, field) = match result {
+        Ok(value) => ("Ok", 0, value),
+        Err(message) => {
+            // `rd` keeps the message alive while the variant is allocated.
+            let message = allocate(heap, registers, frames, Object::String(message));
+            registers.insert(rd.clone(), message);
+            ("Err", 1, message)
+        }
+    };
+
+    let rd_val = allocate(
+        heap,
+        registers,
+        frames,
+        Object::Variant {
+            enum_name: "Result",
+            variant_name,
+            tag,
+            fields: vec![field],
+        },
+    );
+    registers.insert(rd.clone(), rd_val);
+}
+
+fn allocate<'a>(
+    heap: &mut Heap<'a>,
+    registers: &HashMap<Register, Value>,
+    frames: &[Frame],
+    object: Object<'a>,
+) -> Value {
+    collect_garbage(heap, registers, frames);
+    Value::Reference(heap.allocate(object))
+}
+
+// The roots are the registers of the running closure and of every caller.
+fn collect_garbage(heap: &mut Heap, registers: &HashMap<Register, Value>, frames: &[Frame]) {
+    if heap.should_collect() {
+        let roots = registers
+            .values()
+            .chain(frames.iter().flat_map(|frame| frame.registers.values()));
+        heap.collect(roots.copied());
+    }
+}
+
+fn get_value(
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+) -> Result<Value, RuntimeErrorKind> {
+    registers
+        .get(register)
+        .copied()
+        .ok_or_else(|| RuntimeErrorKind::UnsetRegister(register.clone()))
+}
+
+fn get_label(
+    label_locations: &HashMap<Label, usize>,
+    label: &Label,
+) -> Result<usize, RuntimeErrorKind> {
+    label_locations
+        .get(label)
+        .copied()
+        .ok_or_else(|| RuntimeErrorKind::MissingLabel(label.clone()))
+}
+
+fn get_integer(
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+) -> Result<i128, RuntimeErrorKind> {
+    match get_value(registers, register)? {
+        Value::Integer(integer) => Ok(integer),
+        Value::Reference(_) => panic!("expected an integer in {}", register),
+    }
+}
+
+// Shifting by the width of the type or more is an error.
+fn get_shift_amount(
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+    ty: IntegerType,
+) -> Result<u32, RuntimeErrorKind> {
+    shift_amount(get_integer(registers, register)?, ty)
+}
+
+pub(crate) fn shift_amount(amount: i128, ty: IntegerType) -> Result<u32, RuntimeErrorKind> {
+    if amount < 0 || amount >= ty.bits() as i128 {
+        return Err(RuntimeErrorKind::ShiftOverflow { ty, amount });
+    }
+    Ok(amount as u32)
+}
+
+fn get_object<'a, 'b>(
+    heap: &'b Heap<'a>,
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+) -> Result<&'b Object<'a>, RuntimeErrorKind> {
+    match get_value(registers, register)? {
+        Value::Integer(_) => panic!("expected an object in {}", register),
+        Value::Reference(reference) => Ok(&heap[reference]),
+    }
+}
+
+fn get_map<'a, 'b>(
+    heap: &'b Heap<'a>,
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+) -> Result<&'b Map, RuntimeErrorKind> {
+    match get_object(heap, registers, register)? {
+        Object::Map(map) => Ok(map),
+        _ => panic!("expected a map in {}", register),
+    }
+}
+
+fn get_map_mut<'a, 'b>(
+    heap: &'b mut Heap<'a>,
+    registers: &HashMap<Register, Value>,
+    register: &Register,
+) -> Result<&'b mut Map, RuntimeErrorKind> {
+    let reference = match get_value(registers, register)? {
+        Value::Integer(_) => panic!("expected an object in {}", register),
+        Value::Reference(reference) => reference,
+    };
+
+    match &mut heap[reference] {
+        Object::Map(map) => Ok(map),
+        _ => panic!("expected a map in {}", register),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpret, Exit, Options, OverflowMode, RuntimeError, RuntimeErrorKind};
+    use crate::{
+        ast::expression::BinaryOperationType,
+        ir::{testing::get_checked_ir, IRStatement, Label, Register, IR},
+        typeck::types::IntegerType,
+    };
+
+    fn run_source(source: &str, overflow: OverflowMode) -> Result<Exit, RuntimeError> {
+        let ir = get_checked_ir(source);
+        let options = Options {
+            overflow,
+            ..Options::default()
+        };
+        interpret(&ir, &options)
+    }
+
+    fn run(source: &str, overflow: OverflowMode) -> Result<Exit, RuntimeErrorKind> {
+        run_source(source, overflow).map_err(|error| error.kind)
+    }
+
+    #[test]
+    fn test_overflow() {
+        let source = "let a: i8 = 100; let b = a + a;";
+        assert_eq!(
+            run(source, OverflowMode::Trap),
+            Err(RuntimeErrorKind::Overflow {
+                operation_type: BinaryOperationType::Add,
+                ty: IntegerType::I8,
+            })
+        );
+        assert_eq!(run(source, OverflowMode::Wrap), Ok(Exit::Finished(-56)));
+
+        let source = "let a: i8 = -127 - 1; let b = -a;";
+        assert_eq!(
+            run(source, OverflowMode::Trap),
+            Err(RuntimeErrorKind::NegateOverflow {
+                ty: IntegerType::I8
+            })
+        );
+        assert_eq!(run(source, OverflowMode::Wrap), Ok(Exit::Finished(-128)));
+
+        let source = "let a: u16 = 1000; let b = checked_mul(a, a)?;";
+        assert_eq!(
+            run(source, OverflowMode::Wrap),
+            Err(RuntimeErrorKind::UnhandledError(
+                "attempt to multiply with overflow".to_string()
+            ))
+        );
+        let source = "let a: u16 = 100; let b = checked_mul(a, a)? + 1;";
+        assert_eq!(run(source, OverflowMode::Trap), Ok(Exit::Finished(10001)));
+        let source = "let a: u16 = 1000; let b = saturating_mul(a, a);";
+        assert_eq!(run(source, OverflowMode::Trap), Ok(Exit::Finished(65535)));
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let run = |source| run(source, OverflowMode::Trap);
+        assert_eq!(
+            run("let a = 0; let b = 1 / a;"),
+            Err(RuntimeErrorKind::DivisionByZero)
+        );
+        assert_eq!(
+            run("let a = 0; let b = 1 % a;"),
+            Err(RuntimeErrorKind::RemainderByZero)
+        );
+        assert_eq!(
+            run("let m = {\"a\": 1}; print(m[\"z\"]);"),
+            Err(RuntimeErrorKind::KeyNotFound("\"z\"".to_string()))
+        );
+        assert_eq!(
+            run("let mut m = {1: 1}; m[2] += 1;"),
+            Err(RuntimeErrorKind::KeyNotFound("2".to_string()))
+        );
+        assert_eq!(
+            run("let a: i16 = 1; let b = a << 16;"),
+            Err(RuntimeErrorKind::ShiftOverflow {
+                ty: IntegerType::I16,
+                amount: 16,
+            })
+        );
+
+        // The error points at the read of the missing key.
+        let source = "let m = {\"a\": 1}; print(m[\"z\"]);";
+        let error = run_source(source, OverflowMode::Trap).unwrap_err();
+        let start = source.find("m[").unwrap();
+        assert_eq!(
+            error.span.map(|span| (span.start, span.end)),
+            Some((start, start + 6))
+        );
+
+        let ir = IR {
+            statements: vec![
+                IRStatement::Print { rs1: Register(1) },
+                IRStatement::Branch { label: Label(0) },
+            ],
+            spans: vec![],
+        };
+        let error = interpret(&ir, &Options::default()).unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::UnsetRegister(Register(1)));
+        assert_eq!((error.pc, error.span), (0, None));
+
+        let ir = IR {
+            statements: vec![IRStatement::Branch { label: Label(0) }],
+            spans: vec![],
+        };
+        let error = interpret(&ir, &Options::default()).unwrap_err();
+        assert_eq!(error.kind, RuntimeErrorKind::MissingLabel(Label(0)));
+    }
+
+    #[test]
+    fn test_assertions() {
+        let run = |source| run(source, OverflowMode::Trap);
+        assert_eq!(
+            run("let a = 2; assert(a > 1); assert_eq(\"a\", \"a\"); let b = a + 1;"),
+            Ok(Exit::Finished(3))
+        );
+        assert_eq!(
+            run("let a = 2; assert(a > 3);"),
+            Err(RuntimeErrorKind::AssertionFailed)
+        );
+        assert_eq!(
+            run("assert_eq({1: \"a\"}, {1: \"b\"});"),
+            Err(RuntimeErrorKind::AssertionEqualFailed {
+                left: "{1: \"a\"}".to_string(),
+                right: "{1: \"b\"}".to_string(),
+            })
+        );
+        assert_eq!(
+            run("panic(\"stop\"); exit(1);"),
+            Err(RuntimeErrorKind::Panic("stop".to_string()))
+        );
+        assert_eq!(run("exit(3); panic(\"stop\");"), Ok(Exit::Code(3)));
+    }
 }
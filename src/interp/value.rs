@@ -0,0 +1,269 @@
+use std::{collections::HashMap, mem::size_of};
+
+use super::heap::Heap;
+use crate::ir::Label;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    // Integers of every type, which the instructions keep in its range.
+    Integer(i128),
+    // Index of an object on the heap.
+    Reference(usize),
+}
+
+#[derive(Debug)]
+pub enum Object<'a> {
+    Variant {
+        enum_name: &'a str,
+        variant_name: &'a str,
+        tag: u32,
+        fields: Vec<Value>,
+    },
+    String(String),
+    Map(Map),
+    Tuple(Vec<Value>),
+    Closure {
+        label: &'a Label,
+        captures: Vec<Value>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Integer(i128),
+    String(String),
+    Tuple(Vec<MapKey>),
+}
+
+// A hash map that iterates in insertion order, so that printing a map or
+// walking its keys gives the same result on every run.
+#[derive(Debug, Default, Clone)]
+pub struct Map {
+    // Removed entries leave a hole so that the indices stay valid.
+    entries: Vec<Option<(Value, Value)>>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn get(&self, key: &MapKey) -> Option<Value> {
+        let index = self.indices.get(key)?;
+        self.entries[*index].map(|(_, value)| value)
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: MapKey, key_value: Value, value: Value) {
+        match self.indices.get(&key) {
+            Some(index) => self.entries[*index] = Some((key_value, value)),
+            None => {
+                self.indices.insert(key, self.entries.len());
+                self.entries.push(Some((key_value, value)));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> bool {
+        match self.indices.remove(key) {
+            Some(index) => {
+                self.entries[index] = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn heap_size(&self) -> usize {
+        self.entries.capacity() * size_of::<Option<(Value, Value)>>()
+            + self.indices.capacity() * size_of::<(MapKey, usize)>()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.entries.iter_mut().flatten().map(|(_, value)| value)
+    }
+}
+
+pub fn to_map_key(heap: &Heap, value: Value) -> MapKey {
+    match value {
+        Value::Integer(integer) => MapKey::Integer(integer),
+        Value::Reference(reference) => match &heap[reference] {
+            Object::String(string) => MapKey::String(string.clone()),
+            Object::Tuple(elements) => MapKey::Tuple(
+                elements
+                    .iter()
+                    .map(|element| to_map_key(heap, *element))
+                    .collect(),
+            ),
+            _ => panic!("map keys must be integers, strings or tuples"),
+        },
+    }
+}
+
+// Copies the maps in `value`, and the tuples and variants that hold them.
+// Everything else cannot change, so it is shared. Nothing is collected while
+// copying, so the new objects need no roots until they are stored.
+pub fn copy_maps(heap: &mut Heap, value: Value) -> Value {
+    let reference = match value {
+        Value::Integer(_) => return value,
+        Value::Reference(reference) => reference,
+    };
+
+    let mut object = match &heap[reference] {
+        Object::Map(map) => Object::Map(map.clone()),
+        Object::Tuple(elements) => Object::Tuple(elements.clone()),
+        Object::Variant {
+            enum_name,
+            variant_name,
+            tag,
+            fields,
+        } => Object::Variant {
+            enum_name,
+            variant_name,
+            tag: *tag,
+            fields: fields.clone(),
+        },
+        Object::String(_) | Object::Closure { .. } => return value,
+    };
+
+    let copied = match &mut object {
+        Object::Map(map) => {
+            for value in map.values_mut() {
+                *value = copy_maps(heap, *value);
+            }
+            true
+        }
+        Object::Tuple(values) | Object::Variant { fields: values, .. } => {
+            let mut copied = false;
+            for value in values {
+                let copy = copy_maps(heap, *value);
+                copied |= copy != *value;
+                *value = copy;
+            }
+            copied
+        }
+        Object::String(_) | Object::Closure { .. } => unreachable!(),
+    };
+
+    if !copied {
+        return value;
+    }
+    Value::Reference(heap.allocate(object))
+}
+
+pub fn values_equal(heap: &Heap, a: Value, b: Value) -> bool {
+    let (a, b) = match (a, b) {
+        (Value::Reference(a), Value::Reference(b)) => (a, b),
+        _ => return a == b,
+    };
+
+    if a == b {
+        return true;
+    }
+
+    match (&heap[a], &heap[b]) {
+        (Object::String(a), Object::String(b)) => a == b,
+        (
+            Object::Variant {
+                tag: a_tag,
+                fields: a_fields,
+                ..
+            },
+            Object::Variant {
+                tag: b_tag,
+                fields: b_fields,
+                ..
+            },
+        ) => {
+            a_tag == b_tag
+                && a_fields
+                    .iter()
+                    .zip(b_fields)
+                    .all(|(a, b)| values_equal(heap, *a, *b))
+        }
+        (Object::Tuple(a), Object::Tuple(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(heap, *a, *b))
+        }
+        // Maps are equal when they have the same entries, in any order.
+        (Object::Map(a), Object::Map(b)) => {
+            a.len() == b.len()
+                && a.entries().all(|(key, value)| {
+                    b.get(&to_map_key(heap, *key))
+                        .is_some_and(|other| values_equal(heap, *value, other))
+                })
+        }
+        _ => false,
+    }
+}
+
+// Strings are printed as-is at the top level and quoted inside other values.
+pub fn format_value(heap: &Heap, value: Value) -> String {
+    match value {
+        Value::Reference(reference) => match &heap[reference] {
+            Object::String(string) => string.clone(),
+            _ => format_nested_value(heap, value),
+        },
+        Value::Integer(_) => format_nested_value(heap, value),
+    }
+}
+
+// Strings are quoted, as they are inside other values and in error messages.
+pub fn format_nested_value(heap: &Heap, value: Value) -> String {
+    let reference = match value {
+        Value::Integer(integer) => return integer.to_string(),
+        Value::Reference(reference) => reference,
+    };
+
+    match &heap[reference] {
+        Object::Variant {
+            enum_name,
+            variant_name,
+            tag: _,
+            fields,
+        } => {
+            let mut formatted = format!("{}::{}", enum_name, variant_name);
+            if !fields.is_empty() {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|field| format_nested_value(heap, *field))
+                    .collect();
+                formatted.push_str(&format!("({})", fields.join(", ")));
+            }
+            formatted
+        }
+        Object::String(string) => format!("{:?}", string),
+        Object::Map(map) => {
+            let entries: Vec<String> = map
+                .entries()
+                .map(|(key, value)| {
+                    format!(
+                        "{}: {}",
+                        format_nested_value(heap, *key),
+                        format_nested_value(heap, *value)
+                    )
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        Object::Tuple(elements) => {
+            let elements: Vec<String> = elements
+                .iter()
+                .map(|element| format_nested_value(heap, *element))
+                .collect();
+            if elements.len() == 1 {
+                format!("({},)", elements[0])
+            } else {
+                format!("({})", elements.join(", "))
+            }
+        }
+        Object::Closure { label, .. } => format!("<closure {}>", label),
+    }
+}
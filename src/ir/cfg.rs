@@ -0,0 +1,1003 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{IRStatement, Label, Register, IR};
+use crate::diagnostic::Span;
+
+// A run of instructions that is only entered at the top and only left at the
+// bottom.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BasicBlock {
+    // The label the block starts with, if anything branches to it.
+    pub label: Option<(Label, Span)>,
+    // Only in SSA form, where they come before every other instruction.
+    pub phis: Vec<Phi>,
+    // The instructions after the label, none of which branch.
+    pub statements: Vec<(IRStatement, Span)>,
+    // The branch, return or other instruction that ends the block, or None if
+    // it falls through to the next block.
+    pub terminator: Option<(IRStatement, Span)>,
+}
+
+// Defines `rd` as the value of the source from the predecessor that control
+// came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi {
+    pub rd: Register,
+    // The predecessors are blocks, and those on which the value is undefined
+    // are left out.
+    pub sources: Vec<(usize, Register)>,
+}
+
+// The control flow graph of a program. The blocks are kept in the order of
+// the flat IR, so that falling through goes to the next block.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    pub successors: Vec<Vec<usize>>,
+    pub predecessors: Vec<Vec<usize>>,
+    // The first block of the program and of each closure body. Closure bodies
+    // are only entered by `call`, which is not an edge, so each entry starts a
+    // graph of its own.
+    pub entries: Vec<usize>,
+    labels: HashMap<Label, usize>,
+}
+
+impl IRStatement {
+    // Returns true iff the instruction ends a basic block.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            IRStatement::Branch { .. }
+                | IRStatement::BranchZero { .. }
+                | IRStatement::BranchNotZero { .. }
+                | IRStatement::Return { .. }
+                | IRStatement::Fail { .. }
+                | IRStatement::Panic { .. }
+                | IRStatement::Exit { .. }
+        )
+    }
+
+    // The registers the instruction writes and the registers it reads.
+    pub fn registers_mut(&mut self) -> (Vec<&mut Register>, Vec<&mut Register>) {
+        match self {
+            IRStatement::LoadImmediate { rd, .. } | IRStatement::LoadString { rd, .. } => {
+                (vec![rd], vec![])
+            }
+            IRStatement::Add { rd, rs1, rs2, .. }
+            | IRStatement::Subtract { rd, rs1, rs2, .. }
+            | IRStatement::Multiply { rd, rs1, rs2, .. }
+            | IRStatement::Divide { rd, rs1, rs2, .. }
+            | IRStatement::Modulus { rd, rs1, rs2 }
+            | IRStatement::Exponentiate { rd, rs1, rs2, .. }
+            | IRStatement::Equal { rd, rs1, rs2 }
+            | IRStatement::NotEqual { rd, rs1, rs2 }
+            | IRStatement::GreaterEqual { rd, rs1, rs2 }
+            | IRStatement::LessEqual { rd, rs1, rs2 }
+            | IRStatement::Greater { rd, rs1, rs2 }
+            | IRStatement::Less { rd, rs1, rs2 }
+            | IRStatement::LogicalAnd { rd, rs1, rs2 }
+            | IRStatement::LogicalOr { rd, rs1, rs2 }
+            | IRStatement::BitwiseAnd { rd, rs1, rs2 }
+            | IRStatement::BitwiseOr { rd, rs1, rs2 }
+            | IRStatement::BitwiseXor { rd, rs1, rs2 }
+            | IRStatement::LeftShift { rd, rs1, rs2, .. }
+            | IRStatement::RightShift { rd, rs1, rs2, .. }
+            | IRStatement::MapGet { rd, rs1, rs2 }
+            | IRStatement::MapContains { rd, rs1, rs2 }
+            | IRStatement::MapRemove { rd, rs1, rs2 } => (vec![rd], vec![rs1, rs2]),
+            IRStatement::Move { rd, rs1 }
+            | IRStatement::Clone { rd, rs1 }
+            | IRStatement::LogicalNot { rd, rs1 }
+            | IRStatement::Negate { rd, rs1, .. }
+            | IRStatement::BitwiseNot { rd, rs1, .. }
+            | IRStatement::Cast { rd, rs1, .. }
+            | IRStatement::LoadTag { rd, rs1 }
+            | IRStatement::LoadField { rd, rs1, .. }
+            | IRStatement::MapKeys { rd, rs1 }
+            | IRStatement::Length { rd, rs1 }
+            | IRStatement::ParseInteger { rd, rs1, .. } => (vec![rd], vec![rs1]),
+            IRStatement::Branch { .. } | IRStatement::Label { .. } => (vec![], vec![]),
+            IRStatement::BranchNotZero { rs1, .. }
+            | IRStatement::BranchZero { rs1, .. }
+            | IRStatement::Print { rs1 }
+            | IRStatement::Return { rs1 }
+            | IRStatement::Fail { rs1 }
+            | IRStatement::Assert { rs1 }
+            | IRStatement::Panic { rs1 }
+            | IRStatement::Exit { rs1 } => (vec![], vec![rs1]),
+            IRStatement::AssertEqual { rs1, rs2 } => (vec![], vec![rs1, rs2]),
+            IRStatement::MapInsert { rs1, rs2, rs3 } => (vec![], vec![rs1, rs2, rs3]),
+            IRStatement::MakeVariant { rd, fields, .. } => (vec![rd], fields.iter_mut().collect()),
+            IRStatement::MakeTuple { rd, elements } => (vec![rd], elements.iter_mut().collect()),
+            IRStatement::MakeMap { rd, entries } => (
+                vec![rd],
+                entries
+                    .iter_mut()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect(),
+            ),
+            IRStatement::MakeClosure { rd, captures, .. } => {
+                (vec![rd], captures.iter_mut().collect())
+            }
+            IRStatement::Enter {
+                parameters,
+                captures,
+            } => (parameters.iter_mut().chain(captures).collect(), vec![]),
+            IRStatement::Call { rd, rs1, arguments } => {
+                (vec![rd], std::iter::once(rs1).chain(arguments).collect())
+            }
+        }
+    }
+
+    pub fn defs(&self) -> Vec<Register> {
+        let mut statement = self.clone();
+        let (defs, _) = statement.registers_mut();
+        defs.into_iter().map(|register| register.clone()).collect()
+    }
+
+    pub fn uses(&self) -> Vec<Register> {
+        let mut statement = self.clone();
+        let (_, uses) = statement.registers_mut();
+        uses.into_iter().map(|register| register.clone()).collect()
+    }
+}
+
+impl BasicBlock {
+    // Returns true iff the block falls through to the next one when it does
+    // not branch.
+    pub fn falls_through(&self) -> bool {
+        matches!(
+            self.terminator,
+            None | Some((IRStatement::BranchZero { .. }, _))
+                | Some((IRStatement::BranchNotZero { .. }, _))
+        )
+    }
+
+    // The label the terminator branches to, if it does.
+    pub fn branch_target(&self) -> Option<&Label> {
+        match &self.terminator {
+            Some((IRStatement::Branch { label }, _))
+            | Some((IRStatement::BranchZero { label, .. }, _))
+            | Some((IRStatement::BranchNotZero { label, .. }, _)) => Some(label),
+            _ => None,
+        }
+    }
+
+    pub fn branch_target_mut(&mut self) -> Option<&mut Label> {
+        match &mut self.terminator {
+            Some((IRStatement::Branch { label }, _))
+            | Some((IRStatement::BranchZero { label, .. }, _))
+            | Some((IRStatement::BranchNotZero { label, .. }, _)) => Some(label),
+            _ => None,
+        }
+    }
+
+    // The statements and then the terminator, but not the phis.
+    pub fn instructions(&self) -> impl Iterator<Item = &IRStatement> {
+        self.statements
+            .iter()
+            .chain(&self.terminator)
+            .map(|(statement, _)| statement)
+    }
+
+    pub fn instructions_mut(&mut self) -> impl Iterator<Item = &mut IRStatement> {
+        self.statements
+            .iter_mut()
+            .chain(&mut self.terminator)
+            .map(|(statement, _)| statement)
+    }
+}
+
+impl Cfg {
+    pub fn new(ir: &IR) -> Cfg {
+        let mut blocks = vec![];
+        let mut current = BasicBlock::default();
+
+        for (i, statement) in ir.statements.iter().enumerate() {
+            // Statements built by hand may have no spans.
+            let span = ir.spans.get(i).copied().unwrap_or_default();
+
+            match statement {
+                IRStatement::Label { label } => {
+                    if current.label.is_some() || !current.statements.is_empty() {
+                        blocks.push(std::mem::take(&mut current));
+                    }
+                    current.label = Some((label.clone(), span));
+                }
+                statement if statement.is_terminator() => {
+                    current.terminator = Some((statement.clone(), span));
+                    blocks.push(std::mem::take(&mut current));
+                }
+                statement => current.statements.push((statement.clone(), span)),
+            }
+        }
+
+        if current.label.is_some() || !current.statements.is_empty() || blocks.is_empty() {
+            blocks.push(current);
+        }
+
+        let mut cfg = Cfg {
+            blocks,
+            successors: vec![],
+            predecessors: vec![],
+            entries: vec![],
+            labels: HashMap::new(),
+        };
+        cfg.compute_edges();
+        cfg
+    }
+
+    // The flat IR, with a label wherever a block has one. Falling through
+    // needs no instruction, so this gives back the IR the graph was built from.
+    pub fn to_ir(&self) -> IR {
+        let mut ir = IR {
+            statements: vec![],
+            spans: vec![],
+        };
+
+        for block in &self.blocks {
+            assert!(block.phis.is_empty(), "phis are left in the graph");
+            if let Some((label, span)) = &block.label {
+                ir.statements.push(IRStatement::Label {
+                    label: label.clone(),
+                });
+                ir.spans.push(*span);
+            }
+            for (statement, span) in block.statements.iter().chain(&block.terminator) {
+                ir.statements.push(statement.clone());
+                ir.spans.push(*span);
+            }
+        }
+
+        ir
+    }
+
+    // Works out the edges and entries again after the blocks have changed.
+    pub fn compute_edges(&mut self) {
+        self.labels = self
+            .blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, block)| Some((block.label.as_ref()?.0.clone(), i)))
+            .collect();
+
+        self.successors = (0..self.blocks.len())
+            .map(|i| {
+                let block = &self.blocks[i];
+                let mut successors = vec![];
+                if let Some(label) = block.branch_target() {
+                    successors.push(self.block_of(label));
+                }
+                if block.falls_through()
+                    && i + 1 < self.blocks.len()
+                    && !successors.contains(&(i + 1))
+                {
+                    successors.push(i + 1);
+                }
+                successors
+            })
+            .collect();
+
+        self.predecessors = vec![vec![]; self.blocks.len()];
+        for (i, successors) in self.successors.iter().enumerate() {
+            for successor in successors {
+                self.predecessors[*successor].push(i);
+            }
+        }
+
+        let closure_labels: HashSet<&Label> = self
+            .blocks
+            .iter()
+            .flat_map(|block| &block.statements)
+            .filter_map(|(statement, _)| match statement {
+                IRStatement::MakeClosure { label, .. } => Some(label),
+                _ => None,
+            })
+            .collect();
+        self.entries = std::iter::once(0)
+            .chain(self.blocks.iter().enumerate().filter_map(|(i, block)| {
+                let (label, _) = block.label.as_ref()?;
+                closure_labels.contains(label).then_some(i)
+            }))
+            .collect();
+    }
+
+    pub fn block_of(&self, label: &Label) -> usize {
+        *self
+            .labels
+            .get(label)
+            .unwrap_or_else(|| panic!("branch to missing label {}", label))
+    }
+
+    // A label that is not used anywhere in the graph.
+    pub fn new_label(&self) -> Label {
+        let highest = self
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                let closures =
+                    block
+                        .statements
+                        .iter()
+                        .filter_map(|(statement, _)| match statement {
+                            IRStatement::MakeClosure { label, .. } => Some(label.0),
+                            _ => None,
+                        });
+                block
+                    .label
+                    .iter()
+                    .map(|(label, _)| label.0)
+                    .chain(block.branch_target().map(|label| label.0))
+                    .chain(closures)
+            })
+            .max();
+        Label(highest.map_or(0, |label| label + 1))
+    }
+
+    // The number after the highest register in the graph.
+    pub fn next_register(&self) -> u32 {
+        self.blocks
+            .iter()
+            .flat_map(|block| {
+                let phis = block.phis.iter().flat_map(|phi| {
+                    std::iter::once(phi.rd.0).chain(phi.sources.iter().map(|(_, source)| source.0))
+                });
+                let instructions = block.instructions().flat_map(|statement| {
+                    let (defs, uses) = (statement.defs(), statement.uses());
+                    defs.into_iter().chain(uses).map(|register| register.0)
+                });
+                phis.chain(instructions).collect::<Vec<_>>()
+            })
+            .max()
+            .map_or(1, |register| register + 1)
+    }
+
+    // Puts `block` before the block at `index`, keeping the predecessors of
+    // phis pointing at the same blocks.
+    pub fn insert_block(&mut self, index: usize, block: BasicBlock) {
+        self.blocks.insert(index, block);
+        for phi in self.blocks.iter_mut().flat_map(|block| &mut block.phis) {
+            for (predecessor, _) in &mut phi.sources {
+                if *predecessor >= index {
+                    *predecessor += 1;
+                }
+            }
+        }
+        self.compute_edges();
+    }
+
+    // Removes the blocks that `keep` is false for, and the phi sources from
+    // them. Nothing may branch or fall through to a removed block.
+    pub fn retain_blocks(&mut self, keep: &[bool]) {
+        let mut indices = vec![None; self.blocks.len()];
+        let mut next = 0;
+        for (index, keep) in indices.iter_mut().zip(keep) {
+            if *keep {
+                *index = Some(next);
+                next += 1;
+            }
+        }
+
+        let mut kept = keep.iter();
+        self.blocks.retain(|_| *kept.next().unwrap());
+        for phi in self.blocks.iter_mut().flat_map(|block| &mut block.phis) {
+            phi.sources
+                .retain_mut(|(predecessor, _)| match indices[*predecessor] {
+                    Some(index) => {
+                        *predecessor = index;
+                        true
+                    }
+                    None => false,
+                });
+        }
+        self.compute_edges();
+    }
+
+    // Puts a block on every edge from a block with several successors to a
+    // block with several predecessors, so that there is somewhere to put code
+    // that should only run when that edge is taken.
+    pub fn split_critical_edges(&mut self) {
+        loop {
+            let critical_edge = (0..self.blocks.len()).find_map(|block| {
+                if self.successors[block].len() < 2 {
+                    return None;
+                }
+                let successor = self.successors[block]
+                    .iter()
+                    .find(|successor| self.predecessors[**successor].len() > 1)?;
+                Some((block, *successor))
+            });
+
+            match critical_edge {
+                Some((block, successor)) => self.split_edge(block, successor),
+                None => return,
+            }
+        }
+    }
+
+    fn split_edge(&mut self, block: usize, successor: usize) {
+        let index = if self.blocks[block].branch_target()
+            != self.blocks[successor]
+                .label
+                .as_ref()
+                .map(|(label, _)| label)
+        {
+            // The edge falls through, so the new block can too.
+            self.insert_block(block + 1, BasicBlock::default());
+            block + 1
+        } else {
+            let index = self.detached_index(block);
+            let label = self.new_label();
+            let target = std::mem::replace(
+                self.blocks[block].branch_target_mut().unwrap(),
+                label.clone(),
+            );
+            self.insert_block(
+                index,
+                BasicBlock {
+                    label: Some((label, Span::default())),
+                    terminator: Some((IRStatement::Branch { label: target }, Span::default())),
+                    ..BasicBlock::default()
+                },
+            );
+            index
+        };
+
+        let successor = if successor >= index {
+            successor + 1
+        } else {
+            successor
+        };
+        for phi in &mut self.blocks[successor].phis {
+            for (predecessor, _) in &mut phi.sources {
+                if *predecessor == block {
+                    *predecessor = index;
+                }
+            }
+        }
+    }
+
+    // An index after `block` where a block can be inserted without anything
+    // falling through into it.
+    fn detached_index(&mut self, block: usize) -> usize {
+        if let Some(index) =
+            (block + 1..self.blocks.len()).find(|index| !self.blocks[*index].falls_through())
+        {
+            return index + 1;
+        }
+
+        // Make the end of the program a jump to an empty block, and insert
+        // before that.
+        let label = self.new_label();
+        if self.blocks.last().unwrap().terminator.is_some() {
+            self.blocks.push(BasicBlock::default());
+        }
+        self.blocks.last_mut().unwrap().terminator = Some((
+            IRStatement::Branch {
+                label: label.clone(),
+            },
+            Span::default(),
+        ));
+        self.blocks.push(BasicBlock {
+            label: Some((label, Span::default())),
+            ..BasicBlock::default()
+        });
+        self.compute_edges();
+        self.blocks.len() - 1
+    }
+
+    // The registers that may be read after each block before they are
+    // written. Phi sources are read at the end of their predecessor.
+    pub fn live_out(&self) -> Vec<HashSet<Register>> {
+        let mut read_first = vec![HashSet::new(); self.blocks.len()];
+        let mut written = vec![HashSet::new(); self.blocks.len()];
+        let mut phi_reads = vec![HashSet::new(); self.blocks.len()];
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            for phi in &block.phis {
+                written[i].insert(phi.rd.clone());
+                for (predecessor, source) in &phi.sources {
+                    phi_reads[*predecessor].insert(source.clone());
+                }
+            }
+            for statement in block.instructions() {
+                for register in statement.uses() {
+                    if !written[i].contains(&register) {
+                        read_first[i].insert(register);
+                    }
+                }
+                written[i].extend(statement.defs());
+            }
+        }
+
+        let mut live_in: Vec<HashSet<Register>> = vec![HashSet::new(); self.blocks.len()];
+        let mut live_out = phi_reads;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in (0..self.blocks.len()).rev() {
+                for successor in &self.successors[block] {
+                    for register in &live_in[*successor] {
+                        changed |= live_out[block].insert(register.clone());
+                    }
+                }
+                let new_live_in: HashSet<Register> = live_out[block]
+                    .difference(&written[block])
+                    .chain(&read_first[block])
+                    .cloned()
+                    .collect();
+                live_in[block] = new_live_in;
+            }
+        }
+
+        live_out
+    }
+
+    // The blocks reachable from `entry`, each one before its successors
+    // except along back edges.
+    pub fn reverse_postorder(&self, entry: usize) -> Vec<usize> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = vec![];
+        // Blocks with the index of the next successor to visit.
+        let mut stack = vec![(entry, 0)];
+        visited[entry] = true;
+
+        while let Some((block, next)) = stack.pop() {
+            match self.successors[block].get(next) {
+                Some(&successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    pub fn dominators(&self) -> Dominators {
+        let mut immediate_dominators = vec![None; self.blocks.len()];
+        let mut order = vec![usize::MAX; self.blocks.len()];
+
+        // The algorithm from "A Simple, Fast Dominance Algorithm" by Cooper,
+        // Harvey and Kennedy, once for each entry.
+        for &entry in &self.entries {
+            let reverse_postorder = self.reverse_postorder(entry);
+            for (i, block) in reverse_postorder.iter().enumerate() {
+                order[*block] = i;
+            }
+
+            let mut idom: HashMap<usize, usize> = HashMap::from([(entry, entry)]);
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for &block in &reverse_postorder[1..] {
+                    let mut processed = self.predecessors[block]
+                        .iter()
+                        .filter(|predecessor| idom.contains_key(predecessor));
+                    let mut new_idom = *processed.next().unwrap();
+                    for &predecessor in processed {
+                        new_idom = intersect(&idom, &order, predecessor, new_idom);
+                    }
+
+                    if idom.get(&block) != Some(&new_idom) {
+                        idom.insert(block, new_idom);
+                        changed = true;
+                    }
+                }
+            }
+
+            for &block in &reverse_postorder[1..] {
+                immediate_dominators[block] = Some(idom[&block]);
+            }
+        }
+
+        let mut children = vec![vec![]; self.blocks.len()];
+        for (block, idom) in immediate_dominators.iter().enumerate() {
+            if let Some(idom) = idom {
+                children[*idom].push(block);
+            }
+        }
+
+        Dominators {
+            immediate_dominators,
+            children,
+            reachable: order.iter().map(|i| *i != usize::MAX).collect(),
+        }
+    }
+
+    // The natural loops of the graph, outermost first. Back edges to the same
+    // header make up a single loop.
+    pub fn loops(&self, dominators: &Dominators) -> LoopNest {
+        let mut loops: Vec<Loop> = vec![];
+
+        for (block, successors) in self.successors.iter().enumerate() {
+            for &header in successors {
+                if !dominators.dominates(header, block) {
+                    continue;
+                }
+
+                let index = match loops.iter().position(|l| l.header == header) {
+                    Some(index) => index,
+                    None => {
+                        loops.push(Loop {
+                            header,
+                            latches: vec![],
+                            blocks: vec![header],
+                            parent: None,
+                            depth: 1,
+                        });
+                        loops.len() - 1
+                    }
+                };
+                let l = &mut loops[index];
+                l.latches.push(block);
+
+                // Everything that reaches the latch without going through the
+                // header is in the loop.
+                let mut stack = vec![block];
+                while let Some(block) = stack.pop() {
+                    if l.blocks.contains(&block) {
+                        continue;
+                    }
+                    l.blocks.push(block);
+                    stack.extend(
+                        self.predecessors[block]
+                            .iter()
+                            .filter(|predecessor| dominators.is_reachable(**predecessor)),
+                    );
+                }
+            }
+        }
+
+        for l in &mut loops {
+            l.blocks.sort_unstable();
+        }
+        loops.sort_by_key(|l| std::cmp::Reverse(l.blocks.len()));
+
+        // A loop's parent is the smallest loop that contains it, which comes
+        // last of the loops before it that do.
+        for i in 0..loops.len() {
+            let parent = (0..i)
+                .rev()
+                .find(|j| loops[*j].blocks.binary_search(&loops[i].header).is_ok());
+            loops[i].parent = parent;
+            loops[i].depth = parent.map_or(1, |parent| loops[parent].depth + 1);
+        }
+
+        LoopNest { loops }
+    }
+
+    // The block that code before the loop can be put in: the only way into
+    // the loop from outside it, which goes nowhere else.
+    pub fn preheader(&self, l: &Loop) -> Option<usize> {
+        if self.entries.contains(&l.header) {
+            return None;
+        }
+        let mut outside = self.predecessors[l.header]
+            .iter()
+            .filter(|predecessor| l.blocks.binary_search(predecessor).is_err());
+        match (outside.next(), outside.next()) {
+            (Some(&preheader), None) if self.successors[preheader] == [l.header] => Some(preheader),
+            _ => None,
+        }
+    }
+
+    // Puts an empty block before the header of every loop that has no
+    // preheader, for the edges from outside the loop to go through. Phis in
+    // the header get their values from outside through it.
+    pub fn insert_preheaders(&mut self) {
+        // Loops whose latch falls through to the header from a branch are
+        // left alone, as nothing can come between them.
+        let can_insert = |cfg: &Cfg, l: &Loop| {
+            let header = l.header;
+            cfg.preheader(l).is_none()
+                && (header == 0 || !cfg.entries.contains(&header))
+                && (header == 0
+                    || l.blocks.binary_search(&(header - 1)).is_err()
+                    || cfg.blocks[header - 1].terminator.is_none()
+                    || !cfg.blocks[header - 1].falls_through())
+        };
+
+        loop {
+            let dominators = self.dominators();
+            let nest = self.loops(&dominators);
+            let Some(l) = nest.loops.iter().find(|l| can_insert(self, l)) else {
+                return;
+            };
+            let header = l.header;
+
+            let header_label = match &self.blocks[header].label {
+                Some((label, _)) => label.clone(),
+                None => {
+                    let label = self.new_label();
+                    self.blocks[header].label = Some((label.clone(), Span::default()));
+                    label
+                }
+            };
+            if header > 0
+                && l.blocks.binary_search(&(header - 1)).is_ok()
+                && self.blocks[header - 1].terminator.is_none()
+            {
+                self.blocks[header - 1].terminator = Some((
+                    IRStatement::Branch {
+                        label: header_label.clone(),
+                    },
+                    Span::default(),
+                ));
+            }
+
+            let label = self.new_label();
+            for predecessor in self.predecessors[header].clone() {
+                if l.blocks.binary_search(&predecessor).is_err()
+                    && self.blocks[predecessor].branch_target() == Some(&header_label)
+                {
+                    *self.blocks[predecessor].branch_target_mut().unwrap() = label.clone();
+                }
+            }
+            self.insert_block(
+                header,
+                BasicBlock {
+                    label: Some((label, Span::default())),
+                    ..BasicBlock::default()
+                },
+            );
+
+            // Sources from blocks that now go to the preheader come through
+            // it, merged by a phi there if they differ.
+            let (preheader, header) = (header, header + 1);
+            let mut next_register = self.next_register();
+            let mut phis = vec![];
+            for phi in &mut self.blocks[header].phis {
+                let (outside, inside): (Vec<_>, Vec<_>) = phi
+                    .sources
+                    .drain(..)
+                    .partition(|(predecessor, _)| !self.predecessors[header].contains(predecessor));
+                phi.sources = inside;
+                let Some((_, first)) = outside.first() else {
+                    continue;
+                };
+                if outside.iter().all(|(_, source)| source == first) {
+                    phi.sources.push((preheader, first.clone()));
+                } else {
+                    let rd = Register(next_register);
+                    next_register += 1;
+                    phi.sources.push((preheader, rd.clone()));
+                    phis.push(Phi {
+                        rd,
+                        sources: outside,
+                    });
+                }
+            }
+            self.blocks[preheader].phis = phis;
+        }
+    }
+}
+
+// Walks up the dominator tree from `a` and `b` to where they meet.
+fn intersect(idom: &HashMap<usize, usize>, order: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while order[a] > order[b] {
+            a = idom[&a];
+        }
+        while order[b] > order[a] {
+            b = idom[&b];
+        }
+    }
+    a
+}
+
+#[derive(Debug)]
+pub struct Dominators {
+    // None for entries and for blocks that cannot be reached.
+    pub immediate_dominators: Vec<Option<usize>>,
+    // The blocks each block immediately dominates.
+    pub children: Vec<Vec<usize>>,
+    reachable: Vec<bool>,
+}
+
+impl Dominators {
+    // Returns true iff every path from the entry to `b` goes through `a`.
+    // Blocks dominate themselves.
+    pub fn dominates(&self, a: usize, mut b: usize) -> bool {
+        if !self.reachable[b] {
+            return false;
+        }
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.immediate_dominators[b] {
+                Some(idom) => b = idom,
+                None => return false,
+            }
+        }
+    }
+
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.reachable[block]
+    }
+}
+
+#[derive(Debug)]
+pub struct Loop {
+    pub header: usize,
+    // The blocks that branch back to the header.
+    pub latches: Vec<usize>,
+    // Every block in the loop, including those of nested loops, in order.
+    pub blocks: Vec<usize>,
+    // The index of the innermost loop this one is nested in.
+    pub parent: Option<usize>,
+    // 1 for loops that are not nested in another.
+    pub depth: usize,
+}
+
+#[derive(Debug)]
+pub struct LoopNest {
+    // Every loop before the loops nested in it.
+    pub loops: Vec<Loop>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cfg;
+    use crate::{
+        interp::{interpret, Exit, Options},
+        ir::{testing::get_checked_ir, IRStatement, Label, Overflow, Register, IR},
+        typeck::types::IntegerType,
+    };
+
+    fn get_cfg(source: &str) -> (crate::ir::IR, Cfg) {
+        let ir = get_checked_ir(source);
+        let cfg = Cfg::new(&ir);
+        (ir, cfg)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (ir, cfg) = get_cfg(
+            "let mut i = 0;
+            while i < 10 {
+                if i == 3 { break; } else if i == 4 { i += 2; };
+                i += 1;
+            };
+            let f = |x| { let y = x; };
+            f(i);",
+        );
+        let round_trip = cfg.to_ir();
+        assert_eq!(round_trip, ir);
+        let spans = |ir: &crate::ir::IR| -> Vec<(usize, usize)> {
+            ir.spans.iter().map(|span| (span.start, span.end)).collect()
+        };
+        assert_eq!(spans(&round_trip), spans(&ir));
+    }
+
+    #[test]
+    fn test_dominators_and_loops() {
+        let (_, cfg) = get_cfg(
+            "let mut i = 0;
+            while i < 10 {
+                let mut j = 0;
+                while j < i { j += 1; };
+                i += 1;
+            };
+            let f = |x| x;",
+        );
+        let dominators = cfg.dominators();
+        let nest = cfg.loops(&dominators);
+
+        // The closure body is a graph of its own.
+        assert_eq!(cfg.entries.len(), 2);
+        assert_eq!(dominators.immediate_dominators[cfg.entries[1]], None);
+
+        assert_eq!(nest.loops.len(), 2);
+        let (outer, inner) = (&nest.loops[0], &nest.loops[1]);
+        assert_eq!(inner.parent, Some(0));
+        assert_eq!((outer.depth, inner.depth), (1, 2));
+        assert!(inner
+            .blocks
+            .iter()
+            .all(|block| outer.blocks.contains(block)));
+        assert!(dominators.dominates(outer.header, inner.header));
+        assert!(!dominators.dominates(inner.header, outer.header));
+        assert!(nest.loops.iter().all(|l| !l.blocks.contains(&0)));
+    }
+
+    #[test]
+    fn test_split_critical_edges() {
+        // Both edges out of the first block go to blocks that are also
+        // reached from elsewhere, and the program ends by falling through.
+        let ir = IR {
+            statements: vec![
+                IRStatement::LoadImmediate {
+                    rd: Register(1),
+                    imm: 0,
+                },
+                IRStatement::BranchZero {
+                    rs1: Register(1),
+                    label: Label(1),
+                },
+                IRStatement::Label { label: Label(0) },
+                IRStatement::LoadImmediate {
+                    rd: Register(1),
+                    imm: 1,
+                },
+                IRStatement::Label { label: Label(1) },
+                IRStatement::BranchZero {
+                    rs1: Register(1),
+                    label: Label(0),
+                },
+            ],
+            spans: vec![],
+        };
+        let mut cfg = Cfg::new(&ir);
+        cfg.split_critical_edges();
+
+        for (block, successors) in cfg.successors.iter().enumerate() {
+            if successors.len() > 1 {
+                for successor in successors {
+                    assert_eq!(
+                        cfg.predecessors[*successor].len(),
+                        1,
+                        "{} -> {}",
+                        block,
+                        successor
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            interpret(&cfg.to_ir(), &Options::default()).unwrap(),
+            Exit::Finished(1)
+        );
+    }
+
+    #[test]
+    fn test_insert_preheaders() {
+        // The loop is entered by a branch and by falling through, and the
+        // header is its own latch.
+        let li = |rd, imm| IRStatement::LoadImmediate {
+            rd: Register(rd),
+            imm,
+        };
+        let ir = IR {
+            statements: vec![
+                li(1, 3),
+                IRStatement::BranchZero {
+                    rs1: Register(1),
+                    label: Label(0),
+                },
+                li(1, 2),
+                IRStatement::Label { label: Label(0) },
+                li(2, 1),
+                IRStatement::Subtract {
+                    rd: Register(1),
+                    rs1: Register(1),
+                    rs2: Register(2),
+                    ty: IntegerType::I64,
+                    overflow: Overflow::Default,
+                },
+                IRStatement::BranchNotZero {
+                    rs1: Register(1),
+                    label: Label(0),
+                },
+            ],
+            spans: vec![],
+        };
+        let mut cfg = Cfg::new(&ir);
+        cfg.insert_preheaders();
+
+        let nest = cfg.loops(&cfg.dominators());
+        assert_eq!(nest.loops.len(), 1);
+        let preheader = cfg.preheader(&nest.loops[0]).unwrap();
+        assert!(cfg.blocks[preheader].statements.is_empty());
+        assert_eq!(
+            interpret(&cfg.to_ir(), &Options::default()).unwrap(),
+            interpret(&ir, &Options::default()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use super::{get_enum, Scope};
+use crate::{
+    ast::{
+        pattern::{Pattern, PatternKind},
+        statement::EnumDeclaration,
+    },
+    diagnostic::Span,
+    syntax_error::SyntaxError,
+};
+
+static WILDCARD: Pattern = Pattern {
+    kind: PatternKind::Wildcard,
+    span: Span { start: 0, end: 0 },
+};
+
+// What the first column of a pattern matrix is matched against.
+enum Column<'a> {
+    Enum(&'a EnumDeclaration),
+    Tuple(usize),
+    // Integers, or only wildcards and bindings.
+    Open,
+}
+
+// Returns true iff every value is matched by at least one row of `patterns`.
+pub fn is_exhaustive<'a>(
+    scope: &Option<Scope<'a>>,
+    patterns: &[&'a Pattern],
+) -> Result<bool, SyntaxError> {
+    let matrix: Vec<Vec<&Pattern>> = patterns.iter().map(|pattern| vec![*pattern]).collect();
+    Ok(!is_useful(scope, &matrix, 1)?)
+}
+
+// A vector of `width` wildcards is useful if some value matches it but none
+// of the rows of `matrix`. This is the usefulness check from "Warnings for
+// pattern matching" (Maranget, 2007) specialised to an all-wildcard query.
+fn is_useful<'a>(
+    scope: &Option<Scope<'a>>,
+    matrix: &[Vec<&'a Pattern>],
+    width: usize,
+) -> Result<bool, SyntaxError> {
+    if width == 0 {
+        return Ok(matrix.is_empty());
+    }
+
+    let declaration = match get_column(scope, matrix)? {
+        Column::Open => return is_useful(scope, &default_matrix(matrix), width - 1),
+        Column::Tuple(arity) => {
+            let specialized = specialize_matrix(matrix, arity, |pattern| match &pattern.kind {
+                PatternKind::Tuple(elements) => Some(elements),
+                _ => None,
+            });
+            return is_useful(scope, &specialized, arity + width - 1);
+        }
+        Column::Enum(declaration) => declaration,
+    };
+
+    let is_complete = declaration.variants.iter().all(|variant| {
+        matrix.iter().any(|row| match &row[0].kind {
+            PatternKind::Variant(pattern) => pattern.variant_name == variant.identifier,
+            _ => false,
+        })
+    });
+
+    if !is_complete {
+        return is_useful(scope, &default_matrix(matrix), width - 1);
+    }
+
+    for variant in &declaration.variants {
+        let arity = variant.fields.len();
+        let specialized = specialize_matrix(matrix, arity, |pattern| match &pattern.kind {
+            PatternKind::Variant(pattern) if pattern.variant_name == variant.identifier => {
+                Some(&pattern.fields)
+            }
+            _ => None,
+        });
+        if is_useful(scope, &specialized, arity + width - 1)? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+// Works out what the first column of `matrix` is matched against, and rejects
+// columns that mix enums, tuples of different lengths and integers.
+fn get_column<'a>(
+    scope: &Option<Scope<'a>>,
+    matrix: &[Vec<&'a Pattern>],
+) -> Result<Column<'a>, SyntaxError> {
+    let mut enum_name: Option<&str> = None;
+    let mut tuple_arity: Option<usize> = None;
+    let mut has_integer = false;
+
+    for row in matrix {
+        match &row[0].kind {
+            PatternKind::Variant(pattern) => match enum_name {
+                None => enum_name = Some(&pattern.enum_name),
+                Some(name) => {
+                    if name != pattern.enum_name {
+                        return Err(SyntaxError::MismatchedPatterns);
+                    }
+                }
+            },
+            PatternKind::Tuple(elements) => match tuple_arity {
+                None => tuple_arity = Some(elements.len()),
+                Some(arity) => {
+                    if arity != elements.len() {
+                        return Err(SyntaxError::MismatchedPatterns);
+                    }
+                }
+            },
+            PatternKind::IntegerLiteral(_) => has_integer = true,
+            PatternKind::Wildcard | PatternKind::Binding(_) => {}
+        }
+    }
+
+    match (enum_name, tuple_arity, has_integer) {
+        (None, None, _) => Ok(Column::Open),
+        (None, Some(arity), false) => Ok(Column::Tuple(arity)),
+        (Some(name), None, false) => match get_enum(scope, name) {
+            None => Err(SyntaxError::UndefinedEnum),
+            Some(declaration) => Ok(Column::Enum(declaration)),
+        },
+        _ => Err(SyntaxError::MismatchedPatterns),
+    }
+}
+
+// Keeps the rows whose first pattern can match the constructor that
+// `get_fields` accepts, replacing that pattern with the constructor's fields.
+fn specialize_matrix<'a>(
+    matrix: &[Vec<&'a Pattern>],
+    arity: usize,
+    get_fields: impl Fn(&'a Pattern) -> Option<&'a Vec<Pattern>>,
+) -> Vec<Vec<&'a Pattern>> {
+    let mut specialized: Vec<Vec<&Pattern>> = vec![];
+
+    for row in matrix {
+        let mut fields: Vec<&Pattern> = match &row[0].kind {
+            PatternKind::Wildcard | PatternKind::Binding(_) => vec![&WILDCARD; arity],
+            _ => match get_fields(row[0]) {
+                None => continue,
+                Some(fields) => fields.iter().collect(),
+            },
+        };
+
+        fields.extend_from_slice(&row[1..]);
+        specialized.push(fields);
+    }
+
+    specialized
+}
+
+fn default_matrix<'a>(matrix: &[Vec<&'a Pattern>]) -> Vec<Vec<&'a Pattern>> {
+    matrix
+        .iter()
+        .filter(|row| matches!(row[0].kind, PatternKind::Wildcard | PatternKind::Binding(_)))
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
@@ -0,0 +1,96 @@
+use crate::{
+    ast::{
+        expression::{Match, MatchArmBody},
+        pattern::Pattern,
+    },
+    ir::{
+        exhaustiveness,
+        pattern::{check_pattern, walk_pattern},
+        IRState, IRStatement, IRWalkable, Label, Register,
+    },
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for Match {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        for arm in &self.arms {
+            check_pattern(ir, &arm.pattern)?;
+        }
+
+        // Guarded arms may fail to match, so they do not count towards exhaustiveness.
+        let unguarded_patterns: Vec<&Pattern> = self
+            .arms
+            .iter()
+            .filter(|arm| arm.guard.is_none())
+            .map(|arm| &arm.pattern)
+            .collect();
+
+        if !exhaustiveness::is_exhaustive(&ir.scope, &unguarded_patterns)? {
+            return Err(SyntaxError::NonExhaustiveMatch);
+        }
+
+        let expression_register = self.expression.walk_ir(ir)?;
+
+        ir.current_register += 1;
+        let rd = ir.current_register;
+
+        //
+        // Allocate labels
+        //
+        let first_arm_label = ir.current_label + 1;
+        let done_label = first_arm_label + self.arms.len();
+        ir.current_label = done_label;
+
+        for (i, arm) in self.arms.iter().enumerate() {
+            // Falling through an arm's tests continues with the next arm.
+            let next_arm_label = first_arm_label + i + 1;
+
+            ir.statements.push(IRStatement::Label {
+                label: Label(first_arm_label + i),
+            });
+
+            ir.enter_scope();
+
+            walk_pattern(ir, &arm.pattern, expression_register, next_arm_label)?;
+
+            if let Some(guard) = &arm.guard {
+                let guard_register = guard.walk_ir(ir)?;
+                ir.statements.push(IRStatement::BranchZero {
+                    rs1: Register(guard_register),
+                    label: Label(next_arm_label),
+                });
+            }
+
+            let result_register = match &arm.body {
+                MatchArmBody::Expression(expression) => expression.walk_ir(ir)?,
+                MatchArmBody::Block(block) => {
+                    block.walk_ir(ir)?;
+
+                    ir.current_register += 1;
+                    ir.statements.push(IRStatement::LoadImmediate {
+                        rd: Register(ir.current_register),
+                        imm: 0,
+                    });
+                    ir.current_register
+                }
+            };
+
+            ir.walk_move(rd, result_register);
+
+            ir.exit_scope();
+
+            ir.statements.push(IRStatement::Branch {
+                label: Label(done_label),
+            });
+        }
+
+        // Done label
+        ir.statements.push(IRStatement::Label {
+            label: Label(done_label),
+        });
+
+        Ok(rd)
+    }
+}
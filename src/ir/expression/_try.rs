@@ -0,0 +1,47 @@
+use crate::{
+    ast::expression::Try,
+    ir::{pattern::walk_load_field, IRState, IRStatement, IRWalkable, Label, Register},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for Try {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let result = self.expression.walk_ir(ir)?;
+
+        ir.current_label += 1;
+        let ok_label = ir.current_label;
+
+        // `Result::Ok` has tag 0.
+        ir.current_register += 1;
+        let tag = ir.current_register;
+        ir.statements.push(IRStatement::LoadTag {
+            rd: Register(tag),
+            rs1: Register(result),
+        });
+        ir.statements.push(IRStatement::BranchZero {
+            rs1: Register(tag),
+            label: Label(ok_label),
+        });
+
+        // The error is returned as it is, since the closure returns a
+        // `Result` with the same error type.
+        if ir.in_closure() {
+            ir.statements.push(IRStatement::Return {
+                rs1: Register(result),
+            });
+        } else {
+            let error = walk_load_field(ir, result, 0);
+            ir.statements.push(IRStatement::Fail {
+                rs1: Register(error),
+            });
+        }
+
+        ir.statements.push(IRStatement::Label {
+            label: Label(ok_label),
+        });
+
+        Ok(walk_load_field(ir, result, 0))
+    }
+}
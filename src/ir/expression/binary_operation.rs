@@ -1,7 +1,8 @@
 use crate::{
     ast::expression::{BinaryOperation, BinaryOperationType},
-    ir::{IRState, IRStatement, IRWalkable, Register},
+    ir::{get_integer_type, IRState, IRStatement, IRWalkable, Overflow, Register},
     syntax_error::SyntaxError,
+    typeck::types::IntegerType,
 };
 
 impl IRWalkable for BinaryOperation {
@@ -10,44 +11,22 @@ impl IRWalkable for BinaryOperation {
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
         let left = self.left_expression.walk_ir(ir)?;
         let right = self.right_expression.walk_ir(ir)?;
+        // Arithmetic is done at the width of the left operand.
+        let ty = get_integer_type(&self.left_expression.ty);
 
         match self.operation_type {
-            BinaryOperationType::Add => {
-                ir.current_register += 1;
-                ir.statements.push(IRStatement::Add {
-                    rd: Register(ir.current_register),
-                    rs1: Register(left),
-                    rs2: Register(right),
-                });
-                Ok(ir.current_register)
-            }
-            BinaryOperationType::Subtract => {
-                ir.current_register += 1;
-                ir.statements.push(IRStatement::Subtract {
-                    rd: Register(ir.current_register),
-                    rs1: Register(left),
-                    rs2: Register(right),
-                });
-                Ok(ir.current_register)
-            }
-            BinaryOperationType::Multiply => {
-                ir.current_register += 1;
-                ir.statements.push(IRStatement::Multiply {
-                    rd: Register(ir.current_register),
-                    rs1: Register(left),
-                    rs2: Register(right),
-                });
-                Ok(ir.current_register)
-            }
-            BinaryOperationType::Divide => {
-                ir.current_register += 1;
-                ir.statements.push(IRStatement::Divide {
-                    rd: Register(ir.current_register),
-                    rs1: Register(left),
-                    rs2: Register(right),
-                });
-                Ok(ir.current_register)
-            }
+            BinaryOperationType::Add
+            | BinaryOperationType::Subtract
+            | BinaryOperationType::Multiply
+            | BinaryOperationType::Divide
+            | BinaryOperationType::Exponentiate => Ok(walk_arithmetic(
+                ir,
+                self.operation_type,
+                left,
+                right,
+                ty,
+                Overflow::Default,
+            )),
             BinaryOperationType::Modulus => {
                 ir.current_register += 1;
                 ir.statements.push(IRStatement::Modulus {
@@ -57,15 +36,6 @@ impl IRWalkable for BinaryOperation {
                 });
                 Ok(ir.current_register)
             }
-            BinaryOperationType::Exponentiate => {
-                ir.current_register += 1;
-                ir.statements.push(IRStatement::Exponentiate {
-                    rd: Register(ir.current_register),
-                    rs1: Register(left),
-                    rs2: Register(right),
-                });
-                Ok(ir.current_register)
-            }
             BinaryOperationType::Equal => {
                 ir.current_register += 1;
                 ir.statements.push(IRStatement::Equal {
@@ -171,6 +141,7 @@ impl IRWalkable for BinaryOperation {
                     rd: Register(ir.current_register),
                     rs1: Register(left),
                     rs2: Register(right),
+                    ty,
                 });
                 Ok(ir.current_register)
             }
@@ -180,9 +151,66 @@ impl IRWalkable for BinaryOperation {
                     rd: Register(ir.current_register),
                     rs1: Register(left),
                     rs2: Register(right),
+                    ty,
                 });
                 Ok(ir.current_register)
             }
         }
     }
 }
+
+// Emits `+`, `-`, `*`, `/` or `**`, the operations that can overflow.
+pub fn walk_arithmetic(
+    ir: &mut IRState,
+    operation_type: BinaryOperationType,
+    left: u32,
+    right: u32,
+    ty: IntegerType,
+    overflow: Overflow,
+) -> u32 {
+    ir.current_register += 1;
+    let rd = Register(ir.current_register);
+    let rs1 = Register(left);
+    let rs2 = Register(right);
+
+    ir.statements.push(match operation_type {
+        BinaryOperationType::Add => IRStatement::Add {
+            rd,
+            rs1,
+            rs2,
+            ty,
+            overflow,
+        },
+        BinaryOperationType::Subtract => IRStatement::Subtract {
+            rd,
+            rs1,
+            rs2,
+            ty,
+            overflow,
+        },
+        BinaryOperationType::Multiply => IRStatement::Multiply {
+            rd,
+            rs1,
+            rs2,
+            ty,
+            overflow,
+        },
+        BinaryOperationType::Divide => IRStatement::Divide {
+            rd,
+            rs1,
+            rs2,
+            ty,
+            overflow,
+        },
+        BinaryOperationType::Exponentiate => IRStatement::Exponentiate {
+            rd,
+            rs1,
+            rs2,
+            ty,
+            overflow,
+        },
+        _ => unreachable!(),
+    });
+
+    ir.current_register
+}
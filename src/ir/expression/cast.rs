@@ -0,0 +1,32 @@
+use crate::{
+    ast::{expression::Cast, type_annotation::TypeAnnotationKind},
+    ir::{IRState, IRStatement, IRWalkable, Register},
+    syntax_error::SyntaxError,
+    typeck::types::IntegerType,
+};
+
+impl IRWalkable for Cast {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let ty = match &self.type_annotation.kind {
+            TypeAnnotationKind::Named(name) => IntegerType::from_name(name),
+            _ => None,
+        };
+        let ty = match ty {
+            None => return Err(SyntaxError::NonIntegerCast),
+            Some(ty) => ty,
+        };
+
+        let expression_register = self.expression.walk_ir(ir)?;
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::Cast {
+            rd: Register(ir.current_register),
+            rs1: Register(expression_register),
+            ty,
+        });
+
+        Ok(ir.current_register)
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    ast::expression::{Closure, ClosureBody},
+    ir::{IRState, IRStatement, IRWalkable, Label, Register},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for Closure {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        //
+        // Allocate labels
+        //
+        let function_label = ir.current_label + 1;
+        let end_label = ir.current_label + 2;
+        ir.current_label += 2;
+
+        // The body is emitted in place and skipped over until it is called.
+        ir.statements.push(IRStatement::Branch {
+            label: Label(end_label),
+        });
+        ir.statements.push(IRStatement::Label {
+            label: Label(function_label),
+        });
+
+        // The captures are only known once the body has been walked.
+        let enter_index = ir.statements.len();
+        ir.statements.push(IRStatement::Enter {
+            parameters: vec![],
+            captures: vec![],
+        });
+
+        // `break` and `continue` cannot reach loops outside of the closure.
+        let continue_label = ir.current_loop_continue_label.take();
+        let break_label = ir.current_loop_break_label.take();
+
+        ir.enter_scope();
+        let scope = ir.scope.as_mut().unwrap();
+        scope.captures = Some(vec![]);
+
+        let mut parameters: Vec<Register> = vec![];
+        for parameter in &self.parameters {
+            ir.current_register += 1;
+            let scope = ir.scope.as_mut().unwrap();
+            if scope
+                .symbols
+                .insert(parameter, ir.current_register)
+                .is_some()
+            {
+                return Err(SyntaxError::DuplicateParameter);
+            }
+            parameters.push(Register(ir.current_register));
+        }
+
+        let result_register = match &self.body {
+            ClosureBody::Expression(expression) => expression.walk_ir(ir)?,
+            ClosureBody::Block(block) => {
+                block.walk_ir(ir)?;
+
+                ir.current_register += 1;
+                ir.statements.push(IRStatement::LoadImmediate {
+                    rd: Register(ir.current_register),
+                    imm: 0,
+                });
+                ir.current_register
+            }
+        };
+
+        ir.statements.push(IRStatement::Return {
+            rs1: Register(result_register),
+        });
+
+        let captures = ir.scope.as_mut().unwrap().captures.take().unwrap();
+        ir.exit_scope();
+
+        ir.current_loop_continue_label = continue_label;
+        ir.current_loop_break_label = break_label;
+
+        ir.statements[enter_index] = IRStatement::Enter {
+            parameters,
+            captures: captures.iter().map(|(_, inner)| Register(*inner)).collect(),
+        };
+
+        ir.statements.push(IRStatement::Label {
+            label: Label(end_label),
+        });
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::MakeClosure {
+            rd: Register(ir.current_register),
+            label: Label(function_label),
+            captures: captures.iter().map(|(outer, _)| Register(*outer)).collect(),
+        });
+
+        Ok(ir.current_register)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    ast::expression::EnumVariant,
+    ir::{get_enum, IRState, IRStatement, IRWalkable, Register},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for EnumVariant {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let declaration = match get_enum(&ir.scope, &self.enum_name) {
+            None => return Err(SyntaxError::UndefinedEnum),
+            Some(declaration) => declaration,
+        };
+
+        let tag = match declaration
+            .variants
+            .iter()
+            .position(|variant| variant.identifier == self.variant_name)
+        {
+            None => return Err(SyntaxError::UndefinedEnumVariant),
+            Some(tag) => tag,
+        };
+
+        if declaration.variants[tag].fields.len() != self.arguments.len() {
+            return Err(SyntaxError::WrongNumberOfVariantFields);
+        }
+
+        let mut fields: Vec<Register> = vec![];
+        for argument in &self.arguments {
+            fields.push(Register(ir.walk_value(argument)?));
+        }
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::MakeVariant {
+            rd: Register(ir.current_register),
+            enum_name: self.enum_name.clone(),
+            variant_name: self.variant_name.clone(),
+            tag: tag as u32,
+            fields,
+        });
+
+        Ok(ir.current_register)
+    }
+}
@@ -1,22 +1,198 @@
+use super::binary_operation::walk_arithmetic;
 use crate::{
-    ast::expression::FunctionCall,
-    ir::{IRState, IRStatement, IRWalkable, Register},
+    ast::expression::{BinaryOperationType, Call, Expression, FunctionCall},
+    ir::{get_integer_type, IRState, IRStatement, IRWalkable, Overflow, Register},
     syntax_error::SyntaxError,
+    typeck::types::Type,
 };
 
-impl IRWalkable for FunctionCall {
-    type Output = u32;
+// Builtins like `parse_int` need `ty`, the type of the call, to know what they
+// return.
+pub fn walk_function_call<'a>(
+    ir: &mut IRState<'a>,
+    call: &'a FunctionCall,
+    ty: &Option<Type>,
+) -> Result<u32, SyntaxError> {
+    // Variables shadow builtins of the same name.
+    if let Some(function) = ir.get_identifier_register(&call.function_name) {
+        return walk_call(ir, function, &call.arguments);
+    }
 
-    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
-        // TODO: Temporarily hardcode print
-        if self.function_name == "print" {
-            let argument = self.argument.walk_ir(ir)?;
-            ir.statements.push(IRStatement::Print {
-                rs1: Register(argument),
+    let arithmetic = get_arithmetic_builtin(&call.function_name);
+
+    let arity = match call.function_name.as_str() {
+        "print" | "keys" | "len" | "ok" | "err" | "parse_int" => 1,
+        "assert" | "panic" | "exit" => 1,
+        "contains" | "remove" | "assert_eq" => 2,
+        _ if arithmetic.is_some() => 2,
+        _ => return Err(SyntaxError::UndefinedFunction),
+    };
+
+    if call.arguments.len() != arity {
+        return Err(SyntaxError::WrongNumberOfArguments);
+    }
+
+    // `remove` changes the map, and `ok` and `err` store their argument.
+    let mut arguments: Vec<Register> = vec![];
+    for (i, argument) in call.arguments.iter().enumerate() {
+        let register = match call.function_name.as_str() {
+            "remove" if i == 0 => ir.walk_changed_map(argument)?,
+            "ok" | "err" => ir.walk_value(argument)?,
+            _ => argument.walk_ir(ir)?,
+        };
+        arguments.push(Register(register));
+    }
+
+    if let Some((operation_type, overflow)) = arithmetic {
+        let ty = get_integer_type(&call.arguments[0].ty);
+        return Ok(walk_arithmetic(
+            ir,
+            operation_type,
+            arguments[0].0,
+            arguments[1].0,
+            ty,
+            overflow,
+        ));
+    }
+
+    // These are only run for what they do, and have no value.
+    let statement = match call.function_name.as_str() {
+        "print" => Some(IRStatement::Print {
+            rs1: arguments[0].clone(),
+        }),
+        "assert_eq" => Some(IRStatement::AssertEqual {
+            rs1: arguments[0].clone(),
+            rs2: arguments[1].clone(),
+        }),
+        "panic" => Some(IRStatement::Panic {
+            rs1: arguments[0].clone(),
+        }),
+        "exit" => Some(IRStatement::Exit {
+            rs1: arguments[0].clone(),
+        }),
+        _ => None,
+    };
+    if let Some(statement) = statement {
+        ir.statements.push(statement);
+        return Ok(1);
+    }
+
+    // A failed assertion points at the condition, so that its text can be
+    // shown.
+    if call.function_name == "assert" {
+        ir.with_span(call.arguments[0].span, |ir| {
+            ir.statements.push(IRStatement::Assert {
+                rs1: arguments[0].clone(),
             });
-            Ok(1)
-        } else {
-            unimplemented!()
+            Ok(())
+        })?;
+        return Ok(1);
+    }
+
+    ir.current_register += 1;
+    let rd = Register(ir.current_register);
+
+    ir.statements.push(match call.function_name.as_str() {
+        "keys" => IRStatement::MapKeys {
+            rd,
+            rs1: arguments[0].clone(),
+        },
+        "len" => IRStatement::Length {
+            rd,
+            rs1: arguments[0].clone(),
+        },
+        "contains" => IRStatement::MapContains {
+            rd,
+            rs1: arguments[0].clone(),
+            rs2: arguments[1].clone(),
+        },
+        "remove" => IRStatement::MapRemove {
+            rd,
+            rs1: arguments[0].clone(),
+            rs2: arguments[1].clone(),
+        },
+        // In the order of the variants of `Result`.
+        "ok" | "err" => IRStatement::MakeVariant {
+            rd,
+            enum_name: "Result".to_string(),
+            variant_name: if call.function_name == "ok" {
+                "Ok"
+            } else {
+                "Err"
+            }
+            .to_string(),
+            tag: if call.function_name == "ok" { 0 } else { 1 },
+            fields: arguments,
+        },
+        "parse_int" => {
+            let ok = match ty {
+                Some(Type::Result(ok, _)) => Some((**ok).clone()),
+                _ => None,
+            };
+            IRStatement::ParseInteger {
+                rd,
+                rs1: arguments[0].clone(),
+                ty: get_integer_type(&ok),
+            }
         }
+        _ => unreachable!(),
+    });
+
+    Ok(ir.current_register)
+}
+
+// `wrapping_add`, `saturating_mul`, `checked_pow` and the like, which are
+// arithmetic that does something other than the default on overflow.
+fn get_arithmetic_builtin(function_name: &str) -> Option<(BinaryOperationType, Overflow)> {
+    let (overflow, operation) = function_name.split_once('_')?;
+
+    let overflow = match overflow {
+        "wrapping" => Overflow::Wrap,
+        "saturating" => Overflow::Saturate,
+        "checked" => Overflow::Checked,
+        _ => return None,
+    };
+    let operation_type = match operation {
+        "add" => BinaryOperationType::Add,
+        "sub" => BinaryOperationType::Subtract,
+        "mul" => BinaryOperationType::Multiply,
+        // Dividing can only overflow with `i8::MIN / -1` and the like, but
+        // `checked_div` also catches division by zero.
+        "div" if overflow == Overflow::Checked => BinaryOperationType::Divide,
+        "pow" => BinaryOperationType::Exponentiate,
+        _ => return None,
+    };
+
+    Some((operation_type, overflow))
+}
+
+impl IRWalkable for Call {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let function = self.callee.walk_ir(ir)?;
+        walk_call(ir, function, &self.arguments)
     }
 }
+
+// The number of arguments is checked when the closure is entered, since the
+// function value is only known at runtime.
+fn walk_call<'a>(
+    ir: &mut IRState<'a>,
+    function: u32,
+    arguments: &'a [Expression],
+) -> Result<u32, SyntaxError> {
+    let mut argument_registers: Vec<Register> = vec![];
+    for argument in arguments {
+        argument_registers.push(Register(argument.walk_ir(ir)?));
+    }
+
+    ir.current_register += 1;
+    ir.statements.push(IRStatement::Call {
+        rd: Register(ir.current_register),
+        rs1: Register(function),
+        arguments: argument_registers,
+    });
+
+    Ok(ir.current_register)
+}
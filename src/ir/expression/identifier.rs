@@ -1,10 +1,7 @@
-use crate::{
-    ir::{get_identifier_register, IRState},
-    syntax_error::SyntaxError,
-};
+use crate::{ir::IRState, syntax_error::SyntaxError};
 
 pub fn ir_walk<'a>(ir: &mut IRState<'a>, identifier: &'a str) -> Result<u32, SyntaxError> {
-    match get_identifier_register(ir.scope.clone(), identifier) {
+    match ir.get_identifier_register(identifier) {
         None => Err(SyntaxError::UndefinedReference),
         Some(register) => Ok(register),
     }
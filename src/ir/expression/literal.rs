@@ -9,18 +9,25 @@ impl IRWalkable for Literal {
 
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
         match self {
-            Literal::StringLiteral(_) => unimplemented!(),
+            Literal::StringLiteral(string) => {
+                ir.current_register += 1;
+                ir.statements.push(IRStatement::LoadString {
+                    rd: Register(ir.current_register),
+                    value: string.clone(),
+                });
+                Ok(ir.current_register)
+            }
             Literal::IntegerLiteral(int) => walk_integer_literal(ir, *int),
         }
     }
 }
 
-fn walk_integer_literal<'a>(ir: &'a mut IRState, integer_literal: u32) -> Result<u32, SyntaxError> {
+fn walk_integer_literal(ir: &mut IRState, integer_literal: u64) -> Result<u32, SyntaxError> {
     ir.current_register += 1;
 
     ir.statements.push(IRStatement::LoadImmediate {
         rd: Register(ir.current_register),
-        imm: integer_literal,
+        imm: integer_literal as i128,
     });
 
     Ok(ir.current_register)
@@ -0,0 +1,44 @@
+use crate::{
+    ast::expression::{Index, MapLiteral},
+    ir::{IRState, IRStatement, IRWalkable, Register},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for MapLiteral {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let mut entries: Vec<(Register, Register)> = vec![];
+        for (key, value) in &self.entries {
+            let key = key.walk_ir(ir)?;
+            let value = ir.walk_value(value)?;
+            entries.push((Register(key), Register(value)));
+        }
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::MakeMap {
+            rd: Register(ir.current_register),
+            entries,
+        });
+
+        Ok(ir.current_register)
+    }
+}
+
+impl IRWalkable for Index {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let map = self.expression.walk_ir(ir)?;
+        let key = self.index.walk_ir(ir)?;
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::MapGet {
+            rd: Register(ir.current_register),
+            rs1: Register(map),
+            rs2: Register(key),
+        });
+
+        Ok(ir.current_register)
+    }
+}
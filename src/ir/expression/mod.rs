@@ -1,22 +1,44 @@
+mod _match;
+mod _try;
 mod binary_operation;
+mod cast;
+mod closure;
+mod enum_variant;
 mod function_call;
 mod identifier;
 mod literal;
+mod map_literal;
+mod tuple;
 mod unary_operation;
 
 use super::{IRState, IRWalkable};
-use crate::{ast::expression::Expression, syntax_error::SyntaxError};
+use crate::{
+    ast::expression::{Expression, ExpressionKind},
+    syntax_error::SyntaxError,
+};
+use function_call::walk_function_call;
 
 impl IRWalkable for Expression {
     type Output = u32;
 
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
-        match self {
-            Expression::FunctionCall(function_call) => function_call.walk_ir(ir),
-            Expression::BinaryOperation(binary_op) => binary_op.walk_ir(ir),
-            Expression::UnaryOperation(unary_op) => unary_op.walk_ir(ir),
-            Expression::Literal(literal) => literal.walk_ir(ir),
-            Expression::Identifier(identifier) => identifier::ir_walk(ir, &identifier),
-        }
+        ir.with_span(self.span, |ir| match &self.kind {
+            ExpressionKind::FunctionCall(function_call) => {
+                walk_function_call(ir, function_call, &self.ty)
+            }
+            ExpressionKind::BinaryOperation(binary_op) => binary_op.walk_ir(ir),
+            ExpressionKind::UnaryOperation(unary_op) => unary_op.walk_ir(ir),
+            ExpressionKind::Literal(literal) => literal.walk_ir(ir),
+            ExpressionKind::Identifier(identifier) => identifier::ir_walk(ir, identifier),
+            ExpressionKind::EnumVariant(enum_variant) => enum_variant.walk_ir(ir),
+            ExpressionKind::Match(_match) => _match.walk_ir(ir),
+            ExpressionKind::MapLiteral(map_literal) => map_literal.walk_ir(ir),
+            ExpressionKind::Index(index) => index.walk_ir(ir),
+            ExpressionKind::Tuple(tuple) => tuple.walk_ir(ir),
+            ExpressionKind::Closure(closure) => closure.walk_ir(ir),
+            ExpressionKind::Call(call) => call.walk_ir(ir),
+            ExpressionKind::Cast(cast) => cast.walk_ir(ir),
+            ExpressionKind::Try(_try) => _try.walk_ir(ir),
+        })
     }
 }
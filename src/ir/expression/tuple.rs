@@ -0,0 +1,24 @@
+use crate::{
+    ast::expression::Tuple,
+    ir::{IRState, IRStatement, IRWalkable, Register},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for Tuple {
+    type Output = u32;
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        let mut elements: Vec<Register> = vec![];
+        for element in &self.elements {
+            elements.push(Register(ir.walk_value(element)?));
+        }
+
+        ir.current_register += 1;
+        ir.statements.push(IRStatement::MakeTuple {
+            rd: Register(ir.current_register),
+            elements,
+        });
+
+        Ok(ir.current_register)
+    }
+}
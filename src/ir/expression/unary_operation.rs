@@ -1,6 +1,6 @@
 use crate::{
     ast::expression::{UnaryOperation, UnaryOperationType},
-    ir::{IRState, IRStatement, IRWalkable, Register},
+    ir::{get_integer_type, IRState, IRStatement, IRWalkable, Register},
     syntax_error::SyntaxError,
 };
 
@@ -9,44 +9,29 @@ impl IRWalkable for UnaryOperation {
 
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
         let expression_register = self.expression.walk_ir(ir)?;
+        let ty = get_integer_type(&self.expression.ty);
 
         match self.operation_type {
             UnaryOperationType::Plus => Ok(expression_register),
             UnaryOperationType::Minus => {
-                let tmp_register = ir.current_register + 1;
-                let rd = tmp_register + 1;
-                ir.current_register = rd;
-
-                ir.statements.push(IRStatement::LoadImmediate {
-                    rd: Register(tmp_register),
-                    imm: 0,
-                });
-
-                ir.statements.push(IRStatement::Subtract {
-                    rd: Register(rd),
-                    rs1: Register(tmp_register),
-                    rs2: Register(expression_register),
+                ir.current_register += 1;
+                ir.statements.push(IRStatement::Negate {
+                    rd: Register(ir.current_register),
+                    rs1: Register(expression_register),
+                    ty,
                 });
 
-                Ok(rd)
+                Ok(ir.current_register)
             }
             UnaryOperationType::BitwiseNot => {
-                let tmp_register = ir.current_register + 1;
-                let rd = tmp_register + 1;
-                ir.current_register = rd;
-
-                ir.statements.push(IRStatement::LoadImmediate {
-                    rd: Register(tmp_register),
-                    imm: 0xFFFFFFFF,
-                });
-
-                ir.statements.push(IRStatement::BitwiseXor {
-                    rd: Register(rd),
+                ir.current_register += 1;
+                ir.statements.push(IRStatement::BitwiseNot {
+                    rd: Register(ir.current_register),
                     rs1: Register(expression_register),
-                    rs2: Register(tmp_register),
+                    ty,
                 });
 
-                Ok(rd)
+                Ok(ir.current_register)
             }
             UnaryOperationType::LogicalNot => {
                 ir.current_register += 1;
@@ -1,14 +1,29 @@
 mod block;
+pub mod cfg;
+mod exhaustiveness;
 mod expression;
+mod pattern;
+pub mod ssa;
 mod statement;
 
-use crate::{ast::block::Block, syntax_error::SyntaxError};
+use crate::{
+    ast::{
+        block::Block,
+        expression::{Expression, ExpressionKind},
+        statement::{EnumDeclaration, EnumVariantDeclaration},
+    },
+    diagnostic::Span,
+    syntax_error::SyntaxError,
+    typeck::types::{IntegerType, Type},
+};
 use core::fmt;
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, sync::LazyLock};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IR {
     pub statements: Vec<IRStatement>,
+    // The code each statement was generated from, for runtime errors.
+    pub spans: Vec<Span>,
 }
 impl fmt::Display for IR {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -35,31 +50,62 @@ impl fmt::Display for Label {
     }
 }
 
-#[derive(Debug, PartialEq)]
+// What an arithmetic instruction does when the result does not fit its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    // Whatever the interpreter was told to do, which is to trap by default.
+    Default,
+    Wrap,
+    Saturate,
+    // Gives a `Result`, which is an error instead of the overflowed value.
+    Checked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum IRStatement {
     LoadImmediate {
         rd: Register,
-        imm: u32,
+        imm: i128,
+    },
+    // Copies the value in `rs1` to `rd`, including references, which then
+    // point at the same object.
+    Move {
+        rd: Register,
+        rs1: Register,
+    },
+    // Copies the value in `rs1` to `rd` with copies of the maps in it, so
+    // that changing one leaves the other alone.
+    Clone {
+        rd: Register,
+        rs1: Register,
     },
     Add {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
+        overflow: Overflow,
     },
     Subtract {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
+        overflow: Overflow,
     },
     Multiply {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
+        overflow: Overflow,
     },
     Divide {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
+        overflow: Overflow,
     },
     Modulus {
         rd: Register,
@@ -70,6 +116,8 @@ pub enum IRStatement {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
+        overflow: Overflow,
     },
     Equal {
         rd: Register,
@@ -115,6 +163,25 @@ pub enum IRStatement {
         rd: Register,
         rs1: Register,
     },
+    // Subtracts `rs1` from zero, which overflows for the smallest value of a
+    // signed `ty`.
+    Negate {
+        rd: Register,
+        rs1: Register,
+        ty: IntegerType,
+    },
+    // Flips every bit of `rs1` as a `ty`.
+    BitwiseNot {
+        rd: Register,
+        rs1: Register,
+        ty: IntegerType,
+    },
+    // Converts an integer to `ty`, truncating and sign-extending it.
+    Cast {
+        rd: Register,
+        rs1: Register,
+        ty: IntegerType,
+    },
     BitwiseAnd {
         rd: Register,
         rs1: Register,
@@ -134,11 +201,13 @@ pub enum IRStatement {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
     },
     RightShift {
         rd: Register,
         rs1: Register,
         rs2: Register,
+        ty: IntegerType,
     },
     Branch {
         label: Label,
@@ -157,18 +226,152 @@ pub enum IRStatement {
     Print {
         rs1: Register,
     },
+    MakeVariant {
+        rd: Register,
+        enum_name: String,
+        variant_name: String,
+        tag: u32,
+        fields: Vec<Register>,
+    },
+    LoadTag {
+        rd: Register,
+        rs1: Register,
+    },
+    LoadField {
+        rd: Register,
+        rs1: Register,
+        index: u32,
+    },
+    LoadString {
+        rd: Register,
+        value: String,
+    },
+    MakeTuple {
+        rd: Register,
+        elements: Vec<Register>,
+    },
+    MakeMap {
+        rd: Register,
+        entries: Vec<(Register, Register)>,
+    },
+    MapGet {
+        rd: Register,
+        rs1: Register,
+        rs2: Register,
+    },
+    MapInsert {
+        rs1: Register,
+        rs2: Register,
+        rs3: Register,
+    },
+    MapContains {
+        rd: Register,
+        rs1: Register,
+        rs2: Register,
+    },
+    MapRemove {
+        rd: Register,
+        rs1: Register,
+        rs2: Register,
+    },
+    MapKeys {
+        rd: Register,
+        rs1: Register,
+    },
+    Length {
+        rd: Register,
+        rs1: Register,
+    },
+    // Parses the string in `rs1` as a `ty` into a `Result`.
+    ParseInteger {
+        rd: Register,
+        rs1: Register,
+        ty: IntegerType,
+    },
+    // Captures copies of the maps in `captures`, like `Clone`.
+    MakeClosure {
+        rd: Register,
+        label: Label,
+        captures: Vec<Register>,
+    },
+    // First instruction of a closure body: binds the arguments and captured
+    // values passed by `Call` to registers of the new frame.
+    Enter {
+        parameters: Vec<Register>,
+        captures: Vec<Register>,
+    },
+    Call {
+        rd: Register,
+        rs1: Register,
+        arguments: Vec<Register>,
+    },
+    Return {
+        rs1: Register,
+    },
+    // Ends the program with the error in `rs1`, for `?` outside of closures.
+    Fail {
+        rs1: Register,
+    },
+    // Ends the program with an error if `rs1` is zero.
+    Assert {
+        rs1: Register,
+    },
+    AssertEqual {
+        rs1: Register,
+        rs2: Register,
+    },
+    // Ends the program with the message in `rs1`.
+    Panic {
+        rs1: Register,
+    },
+    // Ends the program successfully with the exit code in `rs1`.
+    Exit {
+        rs1: Register,
+    },
 }
 
 impl Display for IRStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             IRStatement::LoadImmediate { rd, imm } => write!(f, "li {}, {}", rd, imm),
-            IRStatement::Add { rd, rs1, rs2 } => write!(f, "add {}, {}, {}", rd, rs1, rs2),
-            IRStatement::Subtract { rd, rs1, rs2 } => write!(f, "sub {}, {}, {}", rd, rs1, rs2),
-            IRStatement::Multiply { rd, rs1, rs2 } => write!(f, "mul {}, {}, {}", rd, rs1, rs2),
-            IRStatement::Divide { rd, rs1, rs2 } => write!(f, "div {}, {}, {}", rd, rs1, rs2),
+            IRStatement::Move { rd, rs1 } => write!(f, "mov {}, {}", rd, rs1),
+            IRStatement::Clone { rd, rs1 } => write!(f, "clone {}, {}", rd, rs1),
+            IRStatement::Add {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => write!(f, "add{}{} {}, {}, {}", Width(*ty), overflow, rd, rs1, rs2),
+            IRStatement::Subtract {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => write!(f, "sub{}{} {}, {}, {}", Width(*ty), overflow, rd, rs1, rs2),
+            IRStatement::Multiply {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => write!(f, "mul{}{} {}, {}, {}", Width(*ty), overflow, rd, rs1, rs2),
+            IRStatement::Divide {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => write!(f, "div{}{} {}, {}, {}", Width(*ty), overflow, rd, rs1, rs2),
             IRStatement::Modulus { rd, rs1, rs2 } => write!(f, "mod {}, {}, {}", rd, rs1, rs2),
-            IRStatement::Exponentiate { rd, rs1, rs2 } => write!(f, "exp {}, {}, {}", rd, rs1, rs2),
+            IRStatement::Exponentiate {
+                rd,
+                rs1,
+                rs2,
+                ty,
+                overflow,
+            } => write!(f, "exp{}{} {}, {}, {}", Width(*ty), overflow, rd, rs1, rs2),
             IRStatement::Equal { rd, rs1, rs2 } => write!(f, "eq {}, {}, {}", rd, rs1, rs2),
             IRStatement::NotEqual { rd, rs1, rs2 } => write!(f, "ne {}, {}, {}", rd, rs1, rs2),
             IRStatement::GreaterEqual { rd, rs1, rs2 } => write!(f, "ge {}, {}, {}", rd, rs1, rs2),
@@ -178,20 +381,142 @@ impl Display for IRStatement {
             IRStatement::LogicalAnd { rd, rs1, rs2 } => write!(f, "land {}, {}, {}", rd, rs1, rs2),
             IRStatement::LogicalOr { rd, rs1, rs2 } => write!(f, "lor {}, {}, {}", rd, rs1, rs2),
             IRStatement::LogicalNot { rd, rs1 } => write!(f, "lnot {}, {}", rd, rs1),
+            IRStatement::Negate { rd, rs1, ty } => write!(f, "neg{} {}, {}", Width(*ty), rd, rs1),
+            IRStatement::BitwiseNot { rd, rs1, ty } => {
+                write!(f, "not{} {}, {}", Width(*ty), rd, rs1)
+            }
+            IRStatement::Cast { rd, rs1, ty } => write!(f, "cast{} {}, {}", Width(*ty), rd, rs1),
             IRStatement::BitwiseAnd { rd, rs1, rs2 } => write!(f, "and {}, {}, {}", rd, rs1, rs2),
             IRStatement::BitwiseOr { rd, rs1, rs2 } => write!(f, "or {}, {}, {}", rd, rs1, rs2),
             IRStatement::BitwiseXor { rd, rs1, rs2 } => write!(f, "xor {}, {}, {}", rd, rs1, rs2),
-            IRStatement::LeftShift { rd, rs1, rs2 } => write!(f, "sll {}, {}, {}", rd, rs1, rs2),
-            IRStatement::RightShift { rd, rs1, rs2 } => write!(f, "srl {}, {}, {}", rd, rs1, rs2),
+            IRStatement::LeftShift { rd, rs1, rs2, ty } => {
+                write!(f, "sll{} {}, {}, {}", Width(*ty), rd, rs1, rs2)
+            }
+            IRStatement::RightShift { rd, rs1, rs2, ty } => {
+                write!(f, "srl{} {}, {}, {}", Width(*ty), rd, rs1, rs2)
+            }
             IRStatement::Branch { label } => write!(f, "j {}", label),
             IRStatement::BranchNotZero { rs1, label } => write!(f, "bnz {}, {}", rs1, label),
             IRStatement::BranchZero { rs1, label } => write!(f, "bz {}, {}", rs1, label),
             IRStatement::Label { label } => write!(f, "{}:", label),
             IRStatement::Print { rs1 } => write!(f, "print {}", rs1),
+            IRStatement::MakeVariant {
+                rd,
+                enum_name,
+                variant_name,
+                tag,
+                fields,
+            } => {
+                write!(
+                    f,
+                    "variant {}, {}, {}::{}(",
+                    rd, tag, enum_name, variant_name
+                )?;
+                write_registers(f, fields)?;
+                write!(f, ")")
+            }
+            IRStatement::LoadTag { rd, rs1 } => write!(f, "tag {}, {}", rd, rs1),
+            IRStatement::LoadField { rd, rs1, index } => {
+                write!(f, "field {}, {}, {}", rd, rs1, index)
+            }
+            IRStatement::LoadString { rd, value } => write!(f, "ls {}, {:?}", rd, value),
+            IRStatement::MakeTuple { rd, elements } => {
+                write!(f, "tuple {}, (", rd)?;
+                write_registers(f, elements)?;
+                write!(f, ")")
+            }
+            IRStatement::MakeMap { rd, entries } => {
+                write!(f, "map {}, {{", rd)?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            IRStatement::MapGet { rd, rs1, rs2 } => write!(f, "mget {}, {}, {}", rd, rs1, rs2),
+            IRStatement::MapInsert { rs1, rs2, rs3 } => {
+                write!(f, "mset {}, {}, {}", rs1, rs2, rs3)
+            }
+            IRStatement::MapContains { rd, rs1, rs2 } => {
+                write!(f, "mhas {}, {}, {}", rd, rs1, rs2)
+            }
+            IRStatement::MapRemove { rd, rs1, rs2 } => write!(f, "mdel {}, {}, {}", rd, rs1, rs2),
+            IRStatement::MapKeys { rd, rs1 } => write!(f, "mkeys {}, {}", rd, rs1),
+            IRStatement::Length { rd, rs1 } => write!(f, "len {}, {}", rd, rs1),
+            IRStatement::ParseInteger { rd, rs1, ty } => {
+                write!(f, "parse{} {}, {}", Width(*ty), rd, rs1)
+            }
+            IRStatement::MakeClosure {
+                rd,
+                label,
+                captures,
+            } => {
+                write!(f, "closure {}, {}, [", rd, label)?;
+                write_registers(f, captures)?;
+                write!(f, "]")
+            }
+            IRStatement::Enter {
+                parameters,
+                captures,
+            } => {
+                write!(f, "enter (")?;
+                write_registers(f, parameters)?;
+                write!(f, "), [")?;
+                write_registers(f, captures)?;
+                write!(f, "]")
+            }
+            IRStatement::Call { rd, rs1, arguments } => {
+                write!(f, "call {}, {}, (", rd, rs1)?;
+                write_registers(f, arguments)?;
+                write!(f, ")")
+            }
+            IRStatement::Return { rs1 } => write!(f, "ret {}", rs1),
+            IRStatement::Fail { rs1 } => write!(f, "fail {}", rs1),
+            IRStatement::Assert { rs1 } => write!(f, "assert {}", rs1),
+            IRStatement::AssertEqual { rs1, rs2 } => write!(f, "assert_eq {}, {}", rs1, rs2),
+            IRStatement::Panic { rs1 } => write!(f, "panic {}", rs1),
+            IRStatement::Exit { rs1 } => write!(f, "exit {}", rs1),
         }
     }
 }
 
+// The suffix of arithmetic that does not do the default on overflow, like
+// `add.sat`.
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overflow::Default => Ok(()),
+            Overflow::Wrap => write!(f, ".wrap"),
+            Overflow::Saturate => write!(f, ".sat"),
+            Overflow::Checked => write!(f, ".checked"),
+        }
+    }
+}
+
+// The suffix of instructions that depend on the integer type, like `add.i8`.
+// `u32` has none.
+struct Width(IntegerType);
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            IntegerType::U32 => Ok(()),
+            ty => write!(f, ".{}", ty),
+        }
+    }
+}
+
+fn write_registers(f: &mut fmt::Formatter<'_>, registers: &[Register]) -> fmt::Result {
+    for (i, register) in registers.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", register)?;
+    }
+    Ok(())
+}
+
 trait IRWalkable {
     type Output;
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError>;
@@ -205,14 +530,199 @@ struct IRState<'a> {
     current_label: usize,
     current_loop_continue_label: Option<usize>,
     current_loop_break_label: Option<usize>,
+    // The innermost statement or expression being lowered, which errors are
+    // reported at.
+    span: Span,
+    // The span of each statement in `statements` that has been attributed.
+    spans: Vec<Span>,
 }
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct Scope<'a> {
     previous_scope: Option<Box<Scope<'a>>>,
     symbols: HashMap<&'a str, u32>,
+    enums: HashMap<&'a str, &'a EnumDeclaration>,
+    // Set on the outermost scope of a closure body, whose registers live in
+    // their own frame. Holds the (enclosing register, closure register) pairs
+    // of the variables the closure captures from enclosing scopes.
+    captures: Option<Vec<(u32, u32)>>,
 }
 
-pub fn get_ir<'a>(program: &'a Block) -> Result<IR, SyntaxError> {
+impl<'a> IRState<'a> {
+    // Opens a new innermost scope.
+    fn enter_scope(&mut self) {
+        self.scope = Some(Scope {
+            previous_scope: self.scope.take().map(Box::new),
+            symbols: HashMap::new(),
+            enums: HashMap::new(),
+            captures: None,
+        });
+    }
+
+    // Closes the innermost scope, returning to the one that was open before it.
+    fn exit_scope(&mut self) {
+        self.scope = self
+            .scope
+            .take()
+            .unwrap()
+            .previous_scope
+            .map(|scope| *scope);
+    }
+
+    fn get_identifier_register(&mut self, identifier: &'a str) -> Option<u32> {
+        self.scope
+            .as_mut()?
+            .get_identifier_register(identifier, &mut self.current_register)
+    }
+
+    // Returns true iff `register` holds a variable that is in scope.
+    fn is_bound(&self, register: u32) -> bool {
+        let mut current_scope_option = self.scope.as_ref();
+        while let Some(current_scope) = current_scope_option {
+            if current_scope
+                .symbols
+                .values()
+                .any(|symbol| *symbol == register)
+            {
+                return true;
+            }
+
+            current_scope_option = current_scope.previous_scope.as_deref();
+        }
+
+        false
+    }
+
+    // Lowers `walk` as code from `span`. The instructions emitted before the
+    // call belong to the enclosing code, and those emitted by `walk` to
+    // `span` unless it attributes them to something nested more deeply.
+    fn with_span<T>(
+        &mut self,
+        span: Span,
+        walk: impl FnOnce(&mut Self) -> Result<T, SyntaxError>,
+    ) -> Result<T, SyntaxError> {
+        let outer_span = self.span;
+        self.spans.resize(self.statements.len(), outer_span);
+
+        self.span = span;
+        let output = walk(self)?;
+        self.spans.resize(self.statements.len(), span);
+
+        self.span = outer_span;
+        Ok(output)
+    }
+
+    // Copies the value in `rs` to `rd`.
+    fn walk_move(&mut self, rd: u32, rs: u32) {
+        self.statements.push(IRStatement::Move {
+            rd: Register(rd),
+            rs1: Register(rs),
+        });
+    }
+
+    // Walks `expression` for a value that is stored in a variable or in
+    // another value. Maps are values, so one that is already stored somewhere
+    // is copied rather than shared. New tuples, variants and maps copy their
+    // elements instead.
+    fn walk_value(&mut self, expression: &'a Expression) -> Result<u32, SyntaxError> {
+        let register = expression.walk_ir(self)?;
+        let new = matches!(
+            expression.kind,
+            ExpressionKind::MapLiteral(_)
+                | ExpressionKind::Tuple(_)
+                | ExpressionKind::EnumVariant(_)
+        );
+        if new || !expression.ty.as_ref().is_some_and(may_hold_map) {
+            return Ok(register);
+        }
+
+        self.current_register += 1;
+        self.statements.push(IRStatement::Clone {
+            rd: Register(self.current_register),
+            rs1: Register(register),
+        });
+        Ok(self.current_register)
+    }
+
+    // Walks `expression`, a map that is about to be changed. Closures only
+    // have copies of the variables they capture, so they cannot change them.
+    fn walk_changed_map(&mut self, expression: &'a Expression) -> Result<u32, SyntaxError> {
+        let mut root = expression;
+        while let ExpressionKind::Index(index) = &root.kind {
+            root = &index.expression;
+        }
+        if let ExpressionKind::Identifier(identifier) = &root.kind {
+            if let Some(register) = self.get_identifier_register(identifier) {
+                if self.is_captured(register) {
+                    return Err(SyntaxError::AssignedCapturedVariable);
+                }
+            }
+        }
+
+        expression.walk_ir(self)
+    }
+
+    // Returns true iff code is being lowered into the body of a closure.
+    fn in_closure(&self) -> bool {
+        let mut current_scope_option = self.scope.as_ref();
+        while let Some(current_scope) = current_scope_option {
+            if current_scope.captures.is_some() {
+                return true;
+            }
+
+            current_scope_option = current_scope.previous_scope.as_deref();
+        }
+
+        false
+    }
+
+    // Returns true iff `register` is a copy of a variable captured by the innermost closure.
+    fn is_captured(&self, register: u32) -> bool {
+        let mut current_scope_option = self.scope.as_ref();
+        while let Some(current_scope) = current_scope_option {
+            if let Some(captures) = &current_scope.captures {
+                return captures.iter().any(|(_, inner)| *inner == register);
+            }
+
+            current_scope_option = current_scope.previous_scope.as_deref();
+        }
+
+        false
+    }
+}
+
+impl<'a> Scope<'a> {
+    // Looks `identifier` up from this scope outwards. A variable found past the
+    // start of a closure body is captured, so it gets a register of its own in
+    // the closure and every closure in between captures it as well.
+    fn get_identifier_register(
+        &mut self,
+        identifier: &'a str,
+        current_register: &mut u32,
+    ) -> Option<u32> {
+        if let Some(symbol) = self.symbols.get(identifier) {
+            return Some(*symbol);
+        }
+
+        let outer_register = self
+            .previous_scope
+            .as_mut()?
+            .get_identifier_register(identifier, current_register)?;
+
+        let captures = match &mut self.captures {
+            None => return Some(outer_register),
+            Some(captures) => captures,
+        };
+
+        *current_register += 1;
+        captures.push((outer_register, *current_register));
+        self.symbols.insert(identifier, *current_register);
+
+        Some(*current_register)
+    }
+}
+
+// Errors come with the span of the statement they were found in.
+pub fn get_ir(program: &Block) -> Result<IR, (SyntaxError, Span)> {
     let mut ir = IRState {
         statements: vec![],
         scope: None,
@@ -220,35 +730,86 @@ pub fn get_ir<'a>(program: &'a Block) -> Result<IR, SyntaxError> {
         current_label: 0,
         current_loop_continue_label: None,
         current_loop_break_label: None,
+        span: Span::default(),
+        spans: vec![],
     };
 
-    program.walk_ir(&mut ir)?;
+    program.walk_ir(&mut ir).map_err(|error| (error, ir.span))?;
+    ir.spans.resize(ir.statements.len(), Span::default());
     Ok(IR {
         statements: ir.statements,
+        spans: ir.spans,
     })
 }
 
-fn get_identifier_register<'a>(scope: Option<Scope>, identifier: &'a str) -> Option<u32> {
-    let mut current_scope_option = scope;
-    loop {
-        if current_scope_option.is_none() {
-            break;
-        }
+// Enums are inferred with fields of any type, so any of them may hold a map.
+fn may_hold_map(ty: &Type) -> bool {
+    match ty {
+        Type::Map(..) | Type::Enum(_) => true,
+        Type::Tuple(elements) => elements.iter().any(may_hold_map),
+        Type::Result(ok, err) => may_hold_map(ok) || may_hold_map(err),
+        Type::Integer(_)
+        | Type::String
+        | Type::Function(..)
+        | Type::Variable(_)
+        | Type::IntegerVariable(_) => false,
+    }
+}
 
-        let current_scope = current_scope_option.unwrap();
+// The integer type of an expression that has been type checked, or `u32` for
+// one that has not.
+fn get_integer_type(ty: &Option<Type>) -> IntegerType {
+    match ty {
+        Some(Type::Integer(ty)) => *ty,
+        _ => IntegerType::U32,
+    }
+}
 
-        match current_scope.symbols.get(identifier) {
-            Some(symbol) => return Some(*symbol),
-            None => {}
+fn get_enum<'a>(scope: &Option<Scope<'a>>, identifier: &str) -> Option<&'a EnumDeclaration> {
+    let mut current_scope_option = scope.as_ref();
+    while let Some(current_scope) = current_scope_option {
+        if let Some(declaration) = current_scope.enums.get(identifier) {
+            return Some(declaration);
         }
 
-        current_scope_option = match current_scope.previous_scope {
-            Some(scope) => Some(*scope),
-            None => None,
-        };
+        current_scope_option = current_scope.previous_scope.as_deref();
+    }
+
+    // `Result` is built in, unless a program declares its own.
+    (identifier == "Result").then(|| &*RESULT_DECLARATION)
+}
+
+// `Result::Ok(value)` and `Result::Err(error)`, which are made by `ok` and
+// `err`, and by the builtins that can fail.
+static RESULT_DECLARATION: LazyLock<EnumDeclaration> = LazyLock::new(|| EnumDeclaration {
+    identifier: "Result".to_string(),
+    variants: ["Ok", "Err"]
+        .iter()
+        .map(|variant| EnumVariantDeclaration {
+            identifier: variant.to_string(),
+            fields: vec!["value".to_string()],
+        })
+        .collect(),
+    span: Span::default(),
+});
+
+// What the tests that start from source code share.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{get_ir, IR};
+    use crate::{ast::block::Block, parser::Parser, typeck};
+
+    // Parses and checks `source`, which has to be a valid program.
+    pub fn get_checked_program(source: &str) -> Block {
+        let mut program = Parser::new(source).get_ast().unwrap().unwrap();
+        assert!(typeck::check(&mut program).is_empty());
+        program
     }
 
-    None
+    // Lowers `source`, which has to be a valid program.
+    pub fn get_checked_ir(source: &str) -> IR {
+        get_ir(&get_checked_program(source)).unwrap()
+    }
 }
 
 #[cfg(test)]
@@ -256,25 +817,39 @@ mod tests {
     use crate::{
         ast::{
             block::Block,
-            expression::{BinaryOperation, BinaryOperationType, Expression, Literal},
+            expression::{BinaryOperation, BinaryOperationType, ExpressionKind, Literal},
+            pattern::PatternKind,
             statement::{LetStatement, Statement},
         },
-        ir::{IRStatement, Register, IR},
+        diagnostic::Span,
+        ir::{IRStatement, Overflow, Register, IR},
         parser::Parser,
+        syntax_error::SyntaxError,
+        typeck::types::IntegerType,
     };
 
-    use super::get_ir;
+    use super::{get_ir, testing::get_checked_ir};
 
     #[test]
     fn test_get_ir() {
         let stmt = Statement::LetStatement(LetStatement {
-            identifier: "x".to_string(),
-            expression: Some(Expression::BinaryOperation(BinaryOperation {
-                operation_type: BinaryOperationType::Add,
-                left_expression: Box::new(Expression::Literal(Literal::IntegerLiteral(1))),
-                right_expression: Box::new(Expression::Literal(Literal::IntegerLiteral(2))),
-            })),
-            _mutable: true,
+            pattern: PatternKind::Binding("x".to_string()).into(),
+            type_annotation: None,
+            expression: Some(
+                ExpressionKind::BinaryOperation(BinaryOperation {
+                    operation_type: BinaryOperationType::Add,
+                    left_expression: Box::new(
+                        ExpressionKind::Literal(Literal::IntegerLiteral(1)).into(),
+                    ),
+                    right_expression: Box::new(
+                        ExpressionKind::Literal(Literal::IntegerLiteral(2)).into(),
+                    ),
+                })
+                .into(),
+            ),
+            mutable: true,
+            span: Span::default(),
+            ty: None,
         });
 
         let program = Block {
@@ -299,8 +874,11 @@ mod tests {
                         rd: Register(3),
                         rs1: Register(1),
                         rs2: Register(2),
+                        ty: IntegerType::U32,
+                        overflow: Overflow::Default,
                     },
-                ]
+                ],
+                spans: vec![Span::default(); 3],
             },
         );
     }
@@ -337,4 +915,142 @@ mod tests {
             println!("{}", stmt);
         }
     }
+
+    #[test]
+    fn test_get_ir_exhaustive_match() {
+        let mut parser = Parser::new(
+            "enum Shape { Circle(r), Rect(w, h) };
+            let s = Shape::Rect(1, 2);
+            match s { Shape::Circle(r) => r, Shape::Rect(w, 0) => w, Shape::Rect(_, h) => h };",
+        );
+        let program = parser.get_ast().unwrap().unwrap();
+        assert!(get_ir(&program).is_ok());
+    }
+
+    #[test]
+    fn test_get_ir_non_exhaustive_match() {
+        let mut parser = Parser::new(
+            "enum Shape { Circle(r), Rect(w, h) };
+            let s = Shape::Rect(1, 2);
+            match s { Shape::Circle(r) => r, Shape::Rect(w, h) if w == h => w };",
+        );
+        let program = parser.get_ast().unwrap().unwrap();
+        assert!(matches!(
+            get_ir(&program),
+            Err((SyntaxError::NonExhaustiveMatch, _))
+        ));
+
+        let mut parser = Parser::new("match 3 { 1 => 2, 3 => 4 };");
+        let program = parser.get_ast().unwrap().unwrap();
+        assert!(matches!(
+            get_ir(&program),
+            Err((SyntaxError::NonExhaustiveMatch, _))
+        ));
+    }
+
+    #[test]
+    fn test_get_ir_parallel_assignment() {
+        let mut parser = Parser::new("let a = 1; let b = 2; (a, b) = (b, a + b);");
+        let program = parser.get_ast().unwrap().unwrap();
+        let ir = get_ir(&program).unwrap();
+
+        let statements: Vec<String> = ir.statements.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            statements,
+            vec![
+                "li r1, 1",
+                "li r2, 2",
+                "add r3, r1, r2",
+                "tuple r4, (r2, r3)",
+                "field r5, r4, 0",
+                "field r6, r4, 1",
+                "mov r1, r5",
+                "mov r2, r6",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_ir_refutable_let() {
+        let mut parser = Parser::new("let (a, 1) = (1, 1);");
+        let program = parser.get_ast().unwrap().unwrap();
+        assert!(matches!(
+            get_ir(&program),
+            Err((SyntaxError::RefutablePatternInLet, _))
+        ));
+
+        let mut parser = Parser::new("let (a, b) = (1, 2, 3);");
+        let program = parser.get_ast().unwrap().unwrap();
+        assert!(matches!(
+            get_ir(&program),
+            Err((SyntaxError::WrongNumberOfTupleElements, _))
+        ));
+    }
+
+    #[test]
+    fn test_get_ir_closure_captures() {
+        let mut parser = Parser::new("let offset = 10; let add = |x| x + offset; add(1);");
+        let program = parser.get_ast().unwrap().unwrap();
+        let ir = get_ir(&program).unwrap();
+
+        let statements: Vec<String> = ir.statements.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            statements,
+            vec![
+                "li r1, 10",
+                "j L2",
+                "L1:",
+                "enter (r2), [r3]",
+                "add r4, r2, r3",
+                "ret r4",
+                "L2:",
+                "closure r5, L1, [r1]",
+                "li r6, 1",
+                "call r7, r5, (r6)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_ir_nested_closure_captures() {
+        // `a` is captured by the outer closure so that the inner one can capture it.
+        let mut parser = Parser::new("let a = 1; let f = || || a;");
+        let program = parser.get_ast().unwrap().unwrap();
+        let ir = get_ir(&program).unwrap();
+
+        let statements: Vec<String> = ir.statements.iter().map(|s| s.to_string()).collect();
+        assert!(statements.contains(&"closure r5, L1, [r1]".to_string()));
+        assert!(statements.contains(&"closure r4, L3, [r2]".to_string()));
+
+        for source in [
+            "let a = 1; let f = || { a = 2; };",
+            "let mut m = {}; let f = || { m[1] = 2; };",
+            "let mut m = {}; let f = || remove(m, 1);",
+        ] {
+            let program = Parser::new(source).get_ast().unwrap().unwrap();
+            assert!(matches!(
+                get_ir(&program),
+                Err((SyntaxError::AssignedCapturedVariable, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_get_ir_integer_widths() {
+        let ir = get_checked_ir("let a: i8 = 100; let b = ~(a + a) as u16; print(b >> 2);");
+
+        let statements: Vec<String> = ir.statements.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            statements,
+            vec![
+                "li r1, 100",
+                "add.i8 r2, r1, r1",
+                "not.i8 r3, r2",
+                "cast.u16 r4, r3",
+                "li r5, 2",
+                "srl.u16 r6, r4, r5",
+                "print r6",
+            ]
+        );
+    }
 }
@@ -0,0 +1,155 @@
+use crate::{
+    ast::pattern::{Pattern, PatternKind},
+    ir::{get_enum, IRState, IRStatement, Label, Register},
+    syntax_error::SyntaxError,
+};
+
+// Rejects patterns that name unknown enums or variants, or have the wrong number of fields.
+pub fn check_pattern(ir: &IRState, pattern: &Pattern) -> Result<(), SyntaxError> {
+    let variant_pattern = match &pattern.kind {
+        PatternKind::Variant(variant_pattern) => variant_pattern,
+        PatternKind::Tuple(elements) => {
+            for element in elements {
+                check_pattern(ir, element)?;
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    let declaration = match get_enum(&ir.scope, &variant_pattern.enum_name) {
+        None => return Err(SyntaxError::UndefinedEnum),
+        Some(declaration) => declaration,
+    };
+
+    let variant = match declaration
+        .variants
+        .iter()
+        .find(|variant| variant.identifier == variant_pattern.variant_name)
+    {
+        None => return Err(SyntaxError::UndefinedEnumVariant),
+        Some(variant) => variant,
+    };
+
+    if variant.fields.len() != variant_pattern.fields.len() {
+        return Err(SyntaxError::WrongNumberOfVariantFields);
+    }
+
+    for field in &variant_pattern.fields {
+        check_pattern(ir, field)?;
+    }
+
+    Ok(())
+}
+
+// Emits the tests for `pattern` against the value in `register`, branching to
+// `fail_label` on a mismatch, and binds the pattern's identifiers in the current scope.
+pub fn walk_pattern<'a>(
+    ir: &mut IRState<'a>,
+    pattern: &'a Pattern,
+    register: u32,
+    fail_label: usize,
+) -> Result<(), SyntaxError> {
+    match &pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Binding(identifier) => {
+            ir.scope
+                .as_mut()
+                .unwrap()
+                .symbols
+                .insert(identifier, register);
+        }
+        PatternKind::IntegerLiteral(literal) => {
+            walk_equality_test(ir, register, *literal, fail_label);
+        }
+        PatternKind::Variant(variant_pattern) => {
+            let declaration = get_enum(&ir.scope, &variant_pattern.enum_name).unwrap();
+            let tag = declaration
+                .variants
+                .iter()
+                .position(|variant| variant.identifier == variant_pattern.variant_name)
+                .unwrap();
+
+            ir.current_register += 1;
+            let tag_register = ir.current_register;
+            ir.statements.push(IRStatement::LoadTag {
+                rd: Register(tag_register),
+                rs1: Register(register),
+            });
+            walk_equality_test(ir, tag_register, tag as i128, fail_label);
+
+            for (i, field) in variant_pattern.fields.iter().enumerate() {
+                let field_register = walk_load_field(ir, register, i);
+                walk_pattern(ir, field, field_register, fail_label)?;
+            }
+        }
+        PatternKind::Tuple(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                let element_register = walk_load_field(ir, register, i);
+                walk_pattern(ir, element, element_register, fail_label)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Patterns that match every value they are applied to, which are the only ones allowed in `let`.
+pub fn is_irrefutable(pattern: &Pattern) -> bool {
+    match &pattern.kind {
+        PatternKind::Wildcard | PatternKind::Binding(_) => true,
+        PatternKind::Tuple(elements) => elements.iter().all(is_irrefutable),
+        PatternKind::IntegerLiteral(_) | PatternKind::Variant(_) => false,
+    }
+}
+
+// Binds the identifiers of an irrefutable pattern to the parts of the value in `register`.
+pub fn bind_pattern<'a>(ir: &mut IRState<'a>, pattern: &'a Pattern, register: u32) {
+    match &pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Binding(identifier) => {
+            ir.scope
+                .as_mut()
+                .unwrap()
+                .symbols
+                .insert(identifier, register);
+        }
+        PatternKind::Tuple(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                let element_register = walk_load_field(ir, register, i);
+                bind_pattern(ir, element, element_register);
+            }
+        }
+        PatternKind::IntegerLiteral(_) | PatternKind::Variant(_) => unreachable!(),
+    }
+}
+
+pub fn walk_load_field(ir: &mut IRState, register: u32, index: usize) -> u32 {
+    ir.current_register += 1;
+    ir.statements.push(IRStatement::LoadField {
+        rd: Register(ir.current_register),
+        rs1: Register(register),
+        index: index as u32,
+    });
+    ir.current_register
+}
+
+fn walk_equality_test(ir: &mut IRState, register: u32, imm: i128, fail_label: usize) {
+    let imm_register = ir.current_register + 1;
+    let condition_register = imm_register + 1;
+    ir.current_register = condition_register;
+
+    ir.statements.push(IRStatement::LoadImmediate {
+        rd: Register(imm_register),
+        imm,
+    });
+    ir.statements.push(IRStatement::Equal {
+        rd: Register(condition_register),
+        rs1: Register(register),
+        rs2: Register(imm_register),
+    });
+    ir.statements.push(IRStatement::BranchZero {
+        rs1: Register(condition_register),
+        label: Label(fail_label),
+    });
+}
@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    cfg::{Cfg, Dominators, Phi},
+    IRStatement, Register,
+};
+use crate::diagnostic::Span;
+
+// Renames the registers of the graph so that each one is written by a single
+// instruction, with phis where the values of a register from different paths
+// meet. This is the algorithm of Cytron et al., with phis only for registers
+// that are read in a different block than the one they were written in.
+pub fn to_ssa(cfg: &mut Cfg) {
+    // Out of SSA, the copies for a phi go at the end of its predecessors,
+    // which must not lead anywhere else.
+    cfg.split_critical_edges();
+    // Renaming walks down from the entries, so it would leave the registers
+    // of blocks that nothing reaches written more than once.
+    let dominators = cfg.dominators();
+    let reachable: Vec<bool> = (0..cfg.blocks.len())
+        .map(|block| dominators.is_reachable(block))
+        .collect();
+    cfg.retain_blocks(&reachable);
+
+    let dominators = cfg.dominators();
+    let frontiers = dominance_frontiers(cfg, &dominators);
+
+    let mut definitions: HashMap<Register, Vec<usize>> = HashMap::new();
+    let mut non_local = HashSet::new();
+    for (i, block) in cfg.blocks.iter().enumerate() {
+        let mut written = HashSet::new();
+        for statement in block.instructions() {
+            for register in statement.uses() {
+                if !written.contains(&register) {
+                    non_local.insert(register);
+                }
+            }
+            for register in statement.defs() {
+                written.insert(register.clone());
+                definitions.entry(register).or_default().push(i);
+            }
+        }
+    }
+
+    // The register each phi is for, before it is renamed.
+    let mut phi_registers: Vec<Vec<Register>> = vec![vec![]; cfg.blocks.len()];
+    let mut registers: Vec<&Register> = definitions
+        .keys()
+        .filter(|register| non_local.contains(*register))
+        .collect();
+    registers.sort_by_key(|register| register.0);
+    for register in registers {
+        let mut worklist = definitions[register].clone();
+        let mut has_phi = HashSet::new();
+        while let Some(block) = worklist.pop() {
+            for &frontier in &frontiers[block] {
+                if has_phi.insert(frontier) {
+                    phi_registers[frontier].push(register.clone());
+                    worklist.push(frontier);
+                }
+            }
+        }
+    }
+    for (block, registers) in cfg.blocks.iter_mut().zip(&phi_registers) {
+        block.phis = registers
+            .iter()
+            .map(|register| Phi {
+                rd: register.clone(),
+                sources: vec![],
+            })
+            .collect();
+    }
+
+    rename(cfg, &dominators, &phi_registers);
+    remove_dead_phis(cfg);
+    debug_assert!(has_single_definitions(cfg), "a register is written twice");
+}
+
+// Returns true iff no register is written by more than one phi or
+// instruction.
+fn has_single_definitions(cfg: &Cfg) -> bool {
+    let mut written = HashSet::new();
+    cfg.blocks.iter().all(|block| {
+        let phis = block.phis.iter().map(|phi| phi.rd.clone());
+        phis.chain(block.instructions().flat_map(IRStatement::defs))
+            .all(|register| written.insert(register))
+    })
+}
+
+enum Visit {
+    Block(usize),
+    // Ends the renaming of the subtree of a block, which wrote these
+    // registers.
+    Done(Vec<Register>),
+}
+
+// Walks down the dominator tree, giving each write a new register and each
+// read the newest register of the value it reads.
+fn rename(cfg: &mut Cfg, dominators: &Dominators, phi_registers: &[Vec<Register>]) {
+    let mut next_register = cfg.next_register();
+    let mut names: HashMap<Register, Vec<Register>> = HashMap::new();
+
+    for entry in cfg.entries.clone() {
+        let mut visits = vec![Visit::Block(entry)];
+        while let Some(visit) = visits.pop() {
+            let block = match visit {
+                Visit::Block(block) => block,
+                Visit::Done(written) => {
+                    for register in written {
+                        names.get_mut(&register).unwrap().pop();
+                    }
+                    continue;
+                }
+            };
+
+            let mut written = vec![];
+            let mut write = |register: &mut Register, names: &mut HashMap<_, Vec<_>>| {
+                let name = Register(next_register);
+                next_register += 1;
+                names
+                    .entry(register.clone())
+                    .or_default()
+                    .push(name.clone());
+                written.push(std::mem::replace(register, name));
+            };
+
+            for phi in &mut cfg.blocks[block].phis {
+                write(&mut phi.rd, &mut names);
+            }
+            for statement in cfg.blocks[block].instructions_mut() {
+                let (defs, uses) = statement.registers_mut();
+                for register in uses {
+                    // Reads of registers that are never written stay as they
+                    // are.
+                    if let Some(name) = names.get(register).and_then(|names| names.last()) {
+                        *register = name.clone();
+                    }
+                }
+                for register in defs {
+                    write(register, &mut names);
+                }
+            }
+
+            for &successor in &cfg.successors[block] {
+                let phis = cfg.blocks[successor].phis.iter_mut();
+                for (phi, register) in phis.zip(&phi_registers[successor]) {
+                    if let Some(name) = names.get(register).and_then(|names| names.last()) {
+                        phi.sources.push((block, name.clone()));
+                    }
+                }
+            }
+
+            visits.push(Visit::Done(written));
+            for &child in dominators.children[block].iter().rev() {
+                visits.push(Visit::Block(child));
+            }
+        }
+    }
+}
+
+// The blocks where the paths from each block meet paths that do not go
+// through it, which is where the values written in the block need phis.
+fn dominance_frontiers(cfg: &Cfg, dominators: &Dominators) -> Vec<Vec<usize>> {
+    let mut frontiers = vec![vec![]; cfg.blocks.len()];
+
+    for block in 0..cfg.blocks.len() {
+        let predecessors: Vec<usize> = cfg.predecessors[block]
+            .iter()
+            .copied()
+            .filter(|predecessor| dominators.is_reachable(*predecessor))
+            .collect();
+        // Entries are also reached from outside of the graph.
+        let joins =
+            predecessors.len() > 1 || (!predecessors.is_empty() && cfg.entries.contains(&block));
+        if !joins {
+            continue;
+        }
+
+        let idom = dominators.immediate_dominators[block];
+        for mut runner in predecessors {
+            while Some(runner) != idom {
+                if !frontiers[runner].contains(&block) {
+                    frontiers[runner].push(block);
+                }
+                match dominators.immediate_dominators[runner] {
+                    Some(parent) => runner = parent,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    frontiers
+}
+
+// Removes the phis whose values are only read by other phis that are not
+// needed either.
+fn remove_dead_phis(cfg: &mut Cfg) {
+    let mut needed: HashSet<Register> = cfg
+        .blocks
+        .iter()
+        .flat_map(|block| block.instructions().flat_map(IRStatement::uses))
+        .collect();
+
+    let phis: Vec<&Phi> = cfg.blocks.iter().flat_map(|block| &block.phis).collect();
+    let mut worklist: Vec<Register> = needed.iter().cloned().collect();
+    while let Some(register) = worklist.pop() {
+        for phi in phis.iter().filter(|phi| phi.rd == register) {
+            for (_, source) in &phi.sources {
+                if needed.insert(source.clone()) {
+                    worklist.push(source.clone());
+                }
+            }
+        }
+    }
+
+    for block in &mut cfg.blocks {
+        block.phis.retain(|phi| needed.contains(&phi.rd));
+    }
+}
+
+// Replaces the phis with copies at the end of their predecessors. The
+// registers of a phi and its sources are merged where their values are never
+// needed at the same time, so that most phis need no copies at all.
+pub fn from_ssa(cfg: &mut Cfg) {
+    cfg.split_critical_edges();
+
+    let dominators = cfg.dominators();
+    let names = coalesce(cfg, &dominators);
+    let name = |register: &Register| names.get(register).unwrap_or(register).clone();
+
+    for block in &mut cfg.blocks {
+        for phi in &mut block.phis {
+            phi.rd = name(&phi.rd);
+            for (_, source) in &mut phi.sources {
+                *source = name(source);
+            }
+        }
+        for statement in block.instructions_mut() {
+            let (defs, uses) = statement.registers_mut();
+            for register in defs.into_iter().chain(uses) {
+                *register = name(register);
+            }
+        }
+    }
+
+    let mut next_register = cfg.next_register();
+    for block in 0..cfg.blocks.len() {
+        let phis = std::mem::take(&mut cfg.blocks[block].phis);
+
+        for &predecessor in &cfg.predecessors[block] {
+            let copies: Vec<(Register, Register)> = phis
+                .iter()
+                .filter_map(|phi| {
+                    let (_, source) = phi.sources.iter().find(|(p, _)| *p == predecessor)?;
+                    (*source != phi.rd).then(|| (phi.rd.clone(), source.clone()))
+                })
+                .collect();
+            if copies.is_empty() {
+                continue;
+            }
+
+            // With no critical edges, either the predecessor only leads here
+            // or this block is only reached from the predecessor.
+            if cfg.successors[predecessor].len() > 1 {
+                let statements = sequentialize(copies, &mut next_register);
+                cfg.blocks[block].statements.splice(0..0, statements);
+                continue;
+            }
+
+            let mut statements = sequentialize(copies.clone(), &mut next_register);
+            // A conditional branch to this block on both paths reads its
+            // condition after the copies.
+            let predecessor = &mut cfg.blocks[predecessor];
+            if let Some((terminator, _)) = &mut predecessor.terminator {
+                let (_, uses) = terminator.registers_mut();
+                for register in uses {
+                    if copies.iter().any(|(rd, _)| rd == register) {
+                        let saved = Register(next_register);
+                        next_register += 1;
+                        let save = IRStatement::Move {
+                            rd: saved.clone(),
+                            rs1: register.clone(),
+                        };
+                        statements.insert(0, (save, Span::default()));
+                        *register = saved;
+                    }
+                }
+            }
+            predecessor.statements.extend(statements);
+        }
+    }
+
+    renumber(cfg);
+}
+
+// Gives each register a name, which is the same for a phi and its sources
+// unless their values interfere.
+fn coalesce(cfg: &Cfg, dominators: &Dominators) -> HashMap<Register, Register> {
+    let live_out = cfg.live_out();
+
+    // Where each register is written: its block, and 0 for phis or the
+    // position of the instruction after them.
+    let mut definitions: HashMap<Register, (usize, usize)> = HashMap::new();
+    for (i, block) in cfg.blocks.iter().enumerate() {
+        for phi in &block.phis {
+            definitions.insert(phi.rd.clone(), (i, 0));
+        }
+        for (j, statement) in block.instructions().enumerate() {
+            for register in statement.defs() {
+                definitions.insert(register, (i, j + 1));
+            }
+        }
+    }
+
+    // Returns true iff `register` is still needed after `position`.
+    let live_after = |register: &Register, (block, position): (usize, usize)| {
+        live_out[block].contains(register)
+            || cfg.blocks[block]
+                .instructions()
+                .skip(position)
+                .any(|statement| statement.uses().contains(register))
+    };
+    let interfere = |a: &Register, b: &Register| {
+        let (Some(&a_definition), Some(&b_definition)) = (definitions.get(a), definitions.get(b))
+        else {
+            return true;
+        };
+        let before = |(a_block, a_position): (usize, usize), (b_block, b_position)| {
+            if a_block == b_block {
+                a_position < b_position
+            } else {
+                dominators.dominates(a_block, b_block)
+            }
+        };
+
+        // Values that are needed at the same time are written by
+        // instructions that dominate each other.
+        if a_definition == b_definition {
+            true
+        } else if before(a_definition, b_definition) {
+            live_after(a, b_definition)
+        } else if before(b_definition, a_definition) {
+            live_after(b, a_definition)
+        } else {
+            false
+        }
+    };
+
+    let mut names: HashMap<Register, Register> = HashMap::new();
+    let mut members: HashMap<Register, Vec<Register>> = HashMap::new();
+    for phi in cfg.blocks.iter().flat_map(|block| &block.phis) {
+        for (_, source) in &phi.sources {
+            let a = names.get(&phi.rd).unwrap_or(&phi.rd).clone();
+            let b = names.get(source).unwrap_or(source).clone();
+            if a == b {
+                continue;
+            }
+
+            let a_members = members.get(&a).cloned().unwrap_or_else(|| vec![a.clone()]);
+            let b_members = members.remove(&b).unwrap_or_else(|| vec![b.clone()]);
+            let merge = !a_members
+                .iter()
+                .any(|a| b_members.iter().any(|b| interfere(a, b)));
+            if !merge {
+                members.insert(b, b_members);
+                continue;
+            }
+
+            for member in &b_members {
+                names.insert(member.clone(), a.clone());
+            }
+            let a_members = members.entry(a.clone()).or_insert(a_members);
+            a_members.extend(b_members);
+        }
+    }
+
+    names
+}
+
+// Orders copies that happen at the same time so that none overwrites a
+// register that another still has to read, using a new register to break
+// cycles like swaps.
+fn sequentialize(
+    mut copies: Vec<(Register, Register)>,
+    next_register: &mut u32,
+) -> Vec<(IRStatement, Span)> {
+    let mut statements = vec![];
+
+    while !copies.is_empty() {
+        let ready = copies
+            .iter()
+            .position(|(rd, _)| !copies.iter().any(|(_, rs)| rs == rd));
+        match ready {
+            Some(i) => {
+                let (rd, rs) = copies.remove(i);
+                statements.push(IRStatement::Move { rd, rs1: rs });
+            }
+            None => {
+                let (rd, _) = copies[0].clone();
+                let saved = Register(*next_register);
+                *next_register += 1;
+                statements.push(IRStatement::Move {
+                    rd: saved.clone(),
+                    rs1: rd.clone(),
+                });
+                for (_, rs) in &mut copies {
+                    if *rs == rd {
+                        *rs = saved.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    statements
+        .into_iter()
+        .map(|statement| (statement, Span::default()))
+        .collect()
+}
+
+// Numbers the registers from 1 in the order they first appear, so that the IR
+// stays readable after renaming.
+fn renumber(cfg: &mut Cfg) {
+    let mut numbers: HashMap<Register, Register> = HashMap::new();
+
+    for statement in cfg
+        .blocks
+        .iter_mut()
+        .flat_map(|block| block.instructions_mut())
+    {
+        let (defs, uses) = statement.registers_mut();
+        for register in defs.into_iter().chain(uses) {
+            let next = Register(numbers.len() as u32 + 1);
+            *register = numbers.entry(register.clone()).or_insert(next).clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_ssa, has_single_definitions, to_ssa};
+    use crate::{
+        interp::{interpret, Exit, Options},
+        ir::{cfg::Cfg, testing::get_checked_ir},
+    };
+
+    fn get_cfg(source: &str) -> Cfg {
+        Cfg::new(&get_checked_ir(source))
+    }
+
+    #[test]
+    fn test_to_ssa() {
+        let mut cfg = get_cfg(
+            "let mut i = 0;
+            let mut total = 0;
+            while i < 10 {
+                if i % 2 == 0 { total += i; } else { total += 1; };
+                i += 1;
+            };
+            print(total);",
+        );
+        to_ssa(&mut cfg);
+        assert!(has_single_definitions(&cfg));
+
+        // `i` and `total` meet at the top of the loop, and `total` after the
+        // `if`.
+        let dominators = cfg.dominators();
+        let nest = cfg.loops(&dominators);
+        let header = nest.loops[0].header;
+        assert_eq!(cfg.blocks[header].phis.len(), 2);
+        assert!(cfg.blocks[header]
+            .phis
+            .iter()
+            .all(|phi| phi.sources.len() == 2));
+        let joins = cfg
+            .blocks
+            .iter()
+            .enumerate()
+            .filter(|(i, block)| *i != header && !block.phis.is_empty())
+            .count();
+        assert_eq!(joins, 1);
+
+        // The code after `break` never runs, and goes rather than keep `d`
+        // written twice.
+        let mut cfg = get_cfg(
+            "let mut d = 7;
+            let c = 1;
+            while c {
+                break;
+                d = match d { 0 => 1, _ => d };
+            };
+            print(d);",
+        );
+        let blocks = cfg.blocks.len();
+        to_ssa(&mut cfg);
+        assert!(has_single_definitions(&cfg));
+        assert!(cfg.blocks.len() < blocks);
+    }
+
+    #[test]
+    fn test_from_ssa() {
+        let programs = [
+            "let mut i = 0;
+            let mut total = 0;
+            while i < 10 {
+                if i % 2 == 0 { total += i; } else { total += 1; };
+                i += 1;
+            };
+            let result = total * 2;",
+            "let mut a = 1;
+            let mut b = 2;
+            let mut n = 0;
+            while n < 5 {
+                (a, b) = (b, a + b);
+                n += 1;
+            };
+            let result = a * 100 + b;",
+            "let mut outer = 0;
+            let mut count = 0;
+            loop {
+                outer += 1;
+                if outer > 6 { break; };
+                let mut inner = 0;
+                while inner < outer {
+                    inner += 1;
+                    if inner == 2 { continue; };
+                    count += inner;
+                };
+            };
+            let result = count + 0;",
+            "let x = 3;
+            let scale = |y| y * x;
+            let mut z = 1;
+            while z < 100 { z = scale(z) + 1; };
+            let result = z * 1;",
+        ];
+
+        for source in programs {
+            let cfg = get_cfg(source);
+            let expected = interpret(&cfg.to_ir(), &Options::default()).unwrap();
+            assert!(matches!(expected, Exit::Finished(_)));
+
+            let mut ssa = cfg.clone();
+            to_ssa(&mut ssa);
+            from_ssa(&mut ssa);
+            let ir = ssa.to_ir();
+            assert_eq!(
+                interpret(&ir, &Options::default()).unwrap(),
+                expected,
+                "{}",
+                source
+            );
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
-    ast::statement::AssignmentStatement,
-    ir::{get_identifier_register, IRState, IRStatement, IRWalkable, Register},
+    ast::{
+        expression::ExpressionKind,
+        statement::{AssignmentStatement, AssignmentTarget},
+    },
+    ir::{pattern::walk_load_field, IRState, IRStatement, IRWalkable, Register},
     syntax_error::SyntaxError,
 };
 
@@ -8,26 +11,61 @@ impl IRWalkable for AssignmentStatement {
     type Output = ();
 
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
-        let rd = match get_identifier_register(ir.scope.clone(), &self.identifier) {
-            None => return Err(SyntaxError::AssignedUndeclaredVariable),
-            Some(symbol) => symbol,
-        };
+        if let (AssignmentTarget::Tuple(targets), ExpressionKind::Tuple(tuple)) =
+            (&self.target, &self.expression.kind)
+        {
+            if targets.len() != tuple.elements.len() {
+                return Err(SyntaxError::WrongNumberOfTupleElements);
+            }
+        }
 
-        let rs1 = self.expression.walk_ir(ir)?;
+        // The right-hand side is evaluated in full before any target is written,
+        // so `(a, b) = (b, a + b);` sees the old values of both `a` and `b`.
+        let value = ir.walk_value(&self.expression)?;
+        walk_assignment(ir, &self.target, value)
+    }
+}
 
-        ir.current_register += 1;
+fn walk_assignment<'a>(
+    ir: &mut IRState<'a>,
+    target: &'a AssignmentTarget,
+    value: u32,
+) -> Result<(), SyntaxError> {
+    match target {
+        AssignmentTarget::Identifier(identifier) => {
+            let rd = match ir.get_identifier_register(identifier) {
+                None => return Err(SyntaxError::AssignedUndeclaredVariable),
+                Some(symbol) => symbol,
+            };
 
-        ir.statements.push(IRStatement::LoadImmediate {
-            rd: Register(ir.current_register),
-            imm: 0,
-        });
+            // Closures capture by value, so the assignment would only change their copy.
+            if ir.is_captured(rd) {
+                return Err(SyntaxError::AssignedCapturedVariable);
+            }
 
-        ir.statements.push(IRStatement::Add {
-            rd: Register(rd),
-            rs1: Register(rs1),
-            rs2: Register(ir.current_register),
-        });
+            ir.walk_move(rd, value);
+        }
+        AssignmentTarget::Index(index) => {
+            let map = ir.walk_changed_map(&index.expression)?;
+            let key = index.index.walk_ir(ir)?;
 
-        Ok(())
+            ir.statements.push(IRStatement::MapInsert {
+                rs1: Register(map),
+                rs2: Register(key),
+                rs3: Register(value),
+            });
+        }
+        AssignmentTarget::Tuple(targets) => {
+            // Read every element before writing any of the targets.
+            let elements: Vec<u32> = (0..targets.len())
+                .map(|i| walk_load_field(ir, value, i))
+                .collect();
+
+            for (target, element) in targets.iter().zip(elements) {
+                walk_assignment(ir, target, element)?;
+            }
+        }
     }
+
+    Ok(())
 }
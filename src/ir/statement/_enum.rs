@@ -0,0 +1,28 @@
+use crate::{
+    ast::statement::EnumDeclaration,
+    ir::{IRState, IRWalkable},
+    syntax_error::SyntaxError,
+};
+
+impl IRWalkable for EnumDeclaration {
+    type Output = ();
+
+    fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        for (i, variant) in self.variants.iter().enumerate() {
+            if self.variants[..i]
+                .iter()
+                .any(|other| other.identifier == variant.identifier)
+            {
+                return Err(SyntaxError::DuplicateEnumVariant);
+            }
+        }
+
+        ir.scope
+            .as_mut()
+            .unwrap()
+            .enums
+            .insert(&self.identifier, self);
+
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@ impl IRWalkable for IfStatement {
         //
         let if_label = ir.current_label + 1;
         let first_else_if_label = if_label + 1;
-        let else_label = first_else_if_label + usize::try_from(self.else_if.len()).unwrap();
+        let else_label = first_else_if_label + self.else_if.len();
         let done_label = else_label + 1;
         ir.current_label = done_label;
 
@@ -31,16 +31,13 @@ impl IRWalkable for IfStatement {
 
         // Else if
 
-        let mut current_else_if_label = first_else_if_label;
-        for else_if in &self.else_if {
+        for (i, else_if) in self.else_if.iter().enumerate() {
             let else_if_condition = else_if.condition.walk_ir(ir)?;
 
             ir.statements.push(IRStatement::BranchNotZero {
                 rs1: Register(else_if_condition),
-                label: Label(current_else_if_label),
+                label: Label(first_else_if_label + i),
             });
-
-            current_else_if_label += 1;
         }
 
         // Branch to else or done label
@@ -68,10 +65,9 @@ impl IRWalkable for IfStatement {
         });
 
         // Else if blocks
-        let mut current_else_if_label = first_else_if_label;
-        for else_if in &self.else_if {
+        for (i, else_if) in self.else_if.iter().enumerate() {
             ir.statements.push(IRStatement::Label {
-                label: Label(current_else_if_label),
+                label: Label(first_else_if_label + i),
             });
 
             else_if.block.walk_ir(ir)?;
@@ -79,8 +75,6 @@ impl IRWalkable for IfStatement {
             ir.statements.push(IRStatement::Branch {
                 label: Label(done_label),
             });
-
-            current_else_if_label += 1;
         }
 
         // Else block
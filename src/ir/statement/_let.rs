@@ -1,6 +1,9 @@
 use crate::{
-    ast::statement::LetStatement,
-    ir::{IRState, IRWalkable},
+    ast::{expression::ExpressionKind, pattern::PatternKind, statement::LetStatement},
+    ir::{
+        pattern::{bind_pattern, check_pattern, is_irrefutable},
+        IRState, IRWalkable,
+    },
     syntax_error::SyntaxError,
 };
 
@@ -8,19 +11,43 @@ impl IRWalkable for LetStatement {
     type Output = ();
 
     fn walk_ir<'a>(&'a self, ir: &mut IRState<'a>) -> Result<Self::Output, SyntaxError> {
+        if !is_irrefutable(&self.pattern) {
+            return Err(SyntaxError::RefutablePatternInLet);
+        }
+        check_pattern(ir, &self.pattern)?;
+
         let register = match &self.expression {
-            None => {
-                ir.current_register += 1;
-                ir.current_register
+            None => match &self.pattern.kind {
+                PatternKind::Binding(_) => {
+                    ir.current_register += 1;
+                    ir.current_register
+                }
+                _ => return Err(SyntaxError::NoExpressionInLetAssignmentStatement),
+            },
+            Some(expression) => {
+                if let (PatternKind::Tuple(elements), ExpressionKind::Tuple(tuple)) =
+                    (&self.pattern.kind, &expression.kind)
+                {
+                    if elements.len() != tuple.elements.len() {
+                        return Err(SyntaxError::WrongNumberOfTupleElements);
+                    }
+                }
+                let register = ir.walk_value(expression)?;
+
+                // Every binding gets its own register, so that assigning to
+                // `b` after `let b = a;` leaves `a` alone.
+                if ir.is_bound(register) {
+                    ir.current_register += 1;
+                    let copy = ir.current_register;
+                    ir.walk_move(copy, register);
+                    copy
+                } else {
+                    register
+                }
             }
-            Some(expression) => expression.walk_ir(ir)?,
         };
 
-        ir.scope
-            .as_mut()
-            .unwrap()
-            .symbols
-            .insert(&self.identifier, register);
+        bind_pattern(ir, &self.pattern, register);
 
         Ok(())
     }
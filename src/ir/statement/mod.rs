@@ -1,6 +1,7 @@
 mod _assignment;
 mod _break;
 mod _continue;
+mod _enum;
 mod _if;
 mod _let;
 mod _loop;
@@ -21,6 +22,7 @@ impl IRWalkable for Statement {
             Statement::ContinueStatement(stmt) => stmt.walk_ir(ir),
             Statement::LoopStatement(stmt) => stmt.walk_ir(ir),
             Statement::WhileStatement(stmt) => stmt.walk_ir(ir),
+            Statement::EnumDeclaration(stmt) => stmt.walk_ir(ir),
             Statement::Expression(stmt) => match stmt.walk_ir(ir) {
                 Ok(_) => Ok(()),
                 Err(err) => Err(err),
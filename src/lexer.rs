@@ -7,7 +7,7 @@ pub enum Token {
     SimpleToken(SimpleToken),
     Identifier(String),
     StringLiteral(String),
-    IntegerLiteral(u32),
+    IntegerLiteral(u64),
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -21,6 +21,10 @@ pub enum SimpleToken {
     Continue,
     Loop,
     While,
+    Enum,
+    Match,
+    As,
+    Import,
     // Unary Operators
     LogicalNot,
     BitwiseNot,
@@ -64,6 +68,13 @@ pub enum SimpleToken {
     LBrace,
     RBrace,
     Comma,
+    PathSeparator,
+    FatArrow,
+    Arrow,
+    LBracket,
+    RBracket,
+    Colon,
+    Question,
 }
 
 struct SimpleTokenMatcher {
@@ -75,9 +86,12 @@ struct SimpleTokenMatcher {
 #[derive(Clone)]
 pub struct Lexer<'a> {
     text: CurrentIterator<Chars<'a>>,
+    length: usize,
+    // The offset of the text in the source map, added to every position.
+    start: usize,
 }
 
-const MATCH_TOKENS: [SimpleTokenMatcher; 47] = [
+const MATCH_TOKENS: [SimpleTokenMatcher; 58] = [
     SimpleTokenMatcher {
         token: SimpleToken::Let,
         match_str: "let",
@@ -118,6 +132,26 @@ const MATCH_TOKENS: [SimpleTokenMatcher; 47] = [
         match_str: "while",
         is_word: true,
     },
+    SimpleTokenMatcher {
+        token: SimpleToken::Enum,
+        match_str: "enum",
+        is_word: true,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::Match,
+        match_str: "match",
+        is_word: true,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::As,
+        match_str: "as",
+        is_word: true,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::Import,
+        match_str: "import",
+        is_word: true,
+    },
     SimpleTokenMatcher {
         token: SimpleToken::ExponentiationAssignment,
         match_str: "**=",
@@ -198,6 +232,16 @@ const MATCH_TOKENS: [SimpleTokenMatcher; 47] = [
         match_str: "<=",
         is_word: false,
     },
+    SimpleTokenMatcher {
+        token: SimpleToken::FatArrow,
+        match_str: "=>",
+        is_word: false,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::Arrow,
+        match_str: "->",
+        is_word: false,
+    },
     SimpleTokenMatcher {
         token: SimpleToken::Equal,
         match_str: "==",
@@ -313,15 +357,58 @@ const MATCH_TOKENS: [SimpleTokenMatcher; 47] = [
         match_str: ",",
         is_word: false,
     },
+    SimpleTokenMatcher {
+        token: SimpleToken::PathSeparator,
+        match_str: "::",
+        is_word: false,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::LBracket,
+        match_str: "[",
+        is_word: false,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::RBracket,
+        match_str: "]",
+        is_word: false,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::Colon,
+        match_str: ":",
+        is_word: false,
+    },
+    SimpleTokenMatcher {
+        token: SimpleToken::Question,
+        match_str: "?",
+        is_word: false,
+    },
 ];
 
 impl Lexer<'_> {
     pub fn new<'a>(input_data: &'a str) -> Lexer<'a> {
+        Lexer::with_start(input_data, 0)
+    }
+
+    pub fn with_start<'a>(input_data: &'a str, start: usize) -> Lexer<'a> {
         Lexer {
             text: CurrentIterator::new(input_data.chars()),
+            length: input_data.len(),
+            start,
         }
     }
 
+    // Byte offset of the end of the last token.
+    pub fn position(&self) -> usize {
+        self.start + self.length - self.text.inner().as_str().len()
+    }
+
+    // Byte offset of the start of the next token.
+    pub fn get_next_token_start(&self) -> usize {
+        let mut lexer = self.clone();
+        lexer.consume_whitespace();
+        lexer.position()
+    }
+
     #[allow(dead_code)]
     pub fn get_tokens(&mut self) -> Result<Vec<Token>, SyntaxError> {
         let mut tokens: Vec<Token> = vec![];
@@ -363,7 +450,7 @@ impl Lexer<'_> {
             let prev = it.current();
 
             if token_matcher.is_word
-                && !prev.is_none()
+                && prev.is_some()
                 && (prev.unwrap().is_alphanumeric() || prev.unwrap() == '_')
             {
                 continue;
@@ -382,7 +469,7 @@ impl Lexer<'_> {
 
             let after = it.next();
             if token_matcher.is_word
-                && !after.is_none()
+                && after.is_some()
                 && (after.unwrap().is_alphanumeric() || after.unwrap() == '_')
             {
                 continue;
@@ -408,7 +495,7 @@ impl Lexer<'_> {
         let mut identifier = String::new();
         identifier.push(first.unwrap());
 
-        while let Some(ch) = it.next() {
+        for ch in it {
             if !ch.is_alphanumeric() && ch != '_' {
                 break;
             }
@@ -416,7 +503,7 @@ impl Lexer<'_> {
             identifier.push(ch);
         }
 
-        for _ in 0..identifier.len() {
+        for _ in 0..identifier.chars().count() {
             self.text.next();
         }
         Ok(Some(Token::Identifier(identifier)))
@@ -434,7 +521,7 @@ impl Lexer<'_> {
         let mut escape = false;
         let mut num_chars = 0;
 
-        while let Some(ch) = it.next() {
+        for ch in it {
             if escape {
                 if ch == '\\' {
                     str.push('\\')
@@ -487,16 +574,16 @@ impl Lexer<'_> {
         let mut it = self.text.clone();
         let mut num_chars = 0;
 
-        if radix_first.is_some() && radix_first.unwrap() == '0' && radix_second.is_some() {
-            if radix_second.unwrap() == 'x' {
+        if radix_first == Some('0') {
+            if radix_second == Some('x') {
                 radix = 16;
                 num_chars += 2;
                 it = it_radix;
-            } else if radix_second.unwrap() == 'b' {
+            } else if radix_second == Some('b') {
                 radix = 2;
                 num_chars += 2;
                 it = it_radix;
-            } else if radix_second.unwrap() == 'o' {
+            } else if radix_second == Some('o') {
                 radix = 8;
                 num_chars += 2;
                 it = it_radix;
@@ -505,7 +592,7 @@ impl Lexer<'_> {
 
         let mut num_str = String::new();
 
-        while let Some(ch) = it.next() {
+        for ch in it {
             if !ch.is_digit(radix) {
                 if ch.is_alphanumeric() || ch == '_' {
                     return Ok(None);
@@ -522,7 +609,7 @@ impl Lexer<'_> {
             return Ok(None);
         }
 
-        match u32::from_str_radix(&num_str, radix) {
+        match u64::from_str_radix(&num_str, radix) {
             Err(_err) => Err(SyntaxError::TooLargeIntegerLiteral),
             Ok(n) => {
                 for _ in 0..num_chars {
@@ -586,7 +673,7 @@ impl Lexer<'_> {
         for _ in 0..num_chars {
             self.text.next();
         }
-        Ok(Some(Token::IntegerLiteral(ch.unwrap() as u32)))
+        Ok(Some(Token::IntegerLiteral(ch.unwrap() as u64)))
     }
 
     pub fn get_next_token(&mut self) -> Result<Option<Token>, SyntaxError> {
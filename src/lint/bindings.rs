@@ -0,0 +1,547 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{LintContext, SAME_SCOPE_SHADOWING, UNUSED_ASSIGNMENTS, UNUSED_MUT, UNUSED_VARIABLES};
+use crate::{
+    ast::{
+        block::Block,
+        expression::{ClosureBody, Expression, ExpressionKind, MatchArmBody},
+        pattern::{Pattern, PatternKind},
+        statement::{AssignmentTarget, Statement},
+    },
+    diagnostic::{Diagnostic, Span},
+};
+
+// Finds unused variables, assignments and `mut`s, and bindings that shadow
+// another in the same scope. Which assignments are read is worked out with a
+// reaching definitions analysis: a read of a variable uses every assignment
+// to it that reaches the read along some path.
+pub fn check(program: &Block, context: &mut LintContext) {
+    let mut checker = BindingChecker::default();
+    checker.check_block(program, &mut State::default());
+    checker.report(context);
+}
+
+struct Binding {
+    identifier: String,
+    declaration: Span,
+    mutable: bool,
+    read: bool,
+    // Assigned to when it may already have a value, or changed through an
+    // index.
+    reassigned: bool,
+}
+
+// A `let` with a value or an assignment.
+struct Definition {
+    binding: usize,
+    span: Span,
+    read: bool,
+}
+
+// The definitions that reach a point in the program.
+#[derive(Clone, Default, PartialEq)]
+struct State {
+    definitions: BTreeSet<usize>,
+    unreachable: bool,
+}
+
+impl State {
+    fn join(self, other: State) -> State {
+        if self.unreachable {
+            return other;
+        }
+        if other.unreachable {
+            return self;
+        }
+
+        State {
+            definitions: self
+                .definitions
+                .union(&other.definitions)
+                .copied()
+                .collect(),
+            unreachable: false,
+        }
+    }
+
+    fn unreachable() -> State {
+        State {
+            definitions: BTreeSet::new(),
+            unreachable: true,
+        }
+    }
+}
+
+// The states at the `break`s and `continue`s of a loop.
+struct LoopStates {
+    breaks: State,
+    continues: State,
+}
+
+#[derive(Default)]
+struct BindingChecker {
+    bindings: Vec<Binding>,
+    definitions: Vec<Definition>,
+    // Loop bodies are walked until the analysis settles, so bindings and
+    // definitions are keyed by the address of the syntax that introduces
+    // them to find the same ones every time.
+    binding_ids: HashMap<(usize, String), usize>,
+    definition_ids: HashMap<(usize, usize), usize>,
+    scopes: Vec<HashMap<String, usize>>,
+    loops: Vec<LoopStates>,
+    // (new binding, shadowed binding) pairs.
+    shadowed: Vec<(usize, usize)>,
+}
+
+fn address<T>(node: &T) -> usize {
+    node as *const T as usize
+}
+
+impl BindingChecker {
+    fn declare(&mut self, node: usize, identifier: &str, span: Span, mutable: bool) -> usize {
+        let scope = self.scopes.last_mut().unwrap();
+        let key = (node, identifier.to_string());
+
+        let id = match self.binding_ids.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.bindings.len();
+                self.bindings.push(Binding {
+                    identifier: identifier.to_string(),
+                    declaration: span,
+                    mutable,
+                    read: false,
+                    reassigned: false,
+                });
+                self.binding_ids.insert(key, id);
+
+                if let Some(shadowed) = scope.get(identifier) {
+                    self.shadowed.push((id, *shadowed));
+                }
+                id
+            }
+        };
+
+        scope.insert(identifier.to_string(), id);
+        id
+    }
+
+    fn get_binding(&self, identifier: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(identifier))
+            .copied()
+    }
+
+    fn define(&mut self, node: usize, binding: usize, span: Span, state: &mut State) {
+        if state.unreachable {
+            return;
+        }
+
+        let id = *self
+            .definition_ids
+            .entry((node, binding))
+            .or_insert_with(|| {
+                self.definitions.push(Definition {
+                    binding,
+                    span,
+                    read: false,
+                });
+                self.definitions.len() - 1
+            });
+
+        let definitions = &self.definitions;
+        state
+            .definitions
+            .retain(|definition| definitions[*definition].binding != binding);
+        state.definitions.insert(id);
+    }
+
+    fn read(&mut self, identifier: &str, state: &State) {
+        let binding = match self.get_binding(identifier) {
+            Some(binding) => binding,
+            None => return,
+        };
+
+        self.bindings[binding].read = true;
+        for definition in &state.definitions {
+            if self.definitions[*definition].binding == binding {
+                self.definitions[*definition].read = true;
+            }
+        }
+    }
+
+    // Declares the bindings of a `let`, which are defined if it has a value.
+    fn bind_let_pattern(
+        &mut self,
+        node: usize,
+        pattern: &Pattern,
+        mutable: bool,
+        span: Span,
+        state: &mut State,
+        defined: bool,
+    ) {
+        match &pattern.kind {
+            PatternKind::Binding(identifier) => {
+                let binding = self.declare(node, identifier, span, mutable);
+                if defined {
+                    self.define(node, binding, span, state);
+                }
+            }
+            PatternKind::Tuple(elements) => {
+                for element in elements {
+                    self.bind_let_pattern(node, element, mutable, span, state, defined);
+                }
+            }
+            PatternKind::Variant(variant_pattern) => {
+                for field in &variant_pattern.fields {
+                    self.bind_let_pattern(node, field, mutable, span, state, defined);
+                }
+            }
+            PatternKind::Wildcard | PatternKind::IntegerLiteral(_) => {}
+        }
+    }
+
+    // Each binding in a match arm is declared by its own pattern.
+    fn bind_match_pattern(&mut self, node: usize, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Binding(identifier) => {
+                self.declare(node, identifier, pattern.span, false);
+            }
+            PatternKind::Tuple(elements) => {
+                for element in elements {
+                    self.bind_match_pattern(node, element);
+                }
+            }
+            PatternKind::Variant(variant_pattern) => {
+                for field in &variant_pattern.fields {
+                    self.bind_match_pattern(node, field);
+                }
+            }
+            PatternKind::Wildcard | PatternKind::IntegerLiteral(_) => {}
+        }
+    }
+
+    fn check_block(&mut self, block: &Block, state: &mut State) {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.check_statement(statement, state);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, statement: &Statement, state: &mut State) {
+        match statement {
+            Statement::LetStatement(statement) => {
+                if let Some(expression) = &statement.expression {
+                    self.check_expression(expression, state);
+                }
+                self.bind_let_pattern(
+                    address(statement),
+                    &statement.pattern,
+                    statement.mutable,
+                    statement.span,
+                    state,
+                    statement.expression.is_some(),
+                );
+            }
+            Statement::Assignment(assignment) => {
+                self.check_expression(&assignment.expression, state);
+                self.check_assignment_target(
+                    address(assignment),
+                    &assignment.target,
+                    assignment.span,
+                    state,
+                );
+            }
+            Statement::IfStatement(statement) => {
+                let mut otherwise = state.clone();
+                let mut after = State::unreachable();
+
+                for condition_with_block in
+                    std::iter::once(&statement._if).chain(&statement.else_if)
+                {
+                    self.check_expression(&condition_with_block.condition, &mut otherwise);
+                    let mut branch = otherwise.clone();
+                    self.check_block(&condition_with_block.block, &mut branch);
+                    after = after.join(branch);
+                }
+
+                if let Some(block) = &statement._else {
+                    self.check_block(block, &mut otherwise);
+                }
+
+                *state = after.join(otherwise);
+            }
+            Statement::BreakStatement(_) => {
+                if let Some(states) = self.loops.last_mut() {
+                    states.breaks = std::mem::take(&mut states.breaks).join(state.clone());
+                }
+                *state = State::unreachable();
+            }
+            Statement::ContinueStatement(_) => {
+                if let Some(states) = self.loops.last_mut() {
+                    states.continues = std::mem::take(&mut states.continues).join(state.clone());
+                }
+                *state = State::unreachable();
+            }
+            Statement::LoopStatement(statement) => self.check_loop(None, &statement.block, state),
+            Statement::WhileStatement(statement) => {
+                self.check_loop(Some(&statement.condition), &statement.block, state)
+            }
+            Statement::Expression(expression) => self.check_expression(expression, state),
+            Statement::EnumDeclaration(_) | Statement::EmptyStatement => {}
+        }
+    }
+
+    // Walks the loop until the definitions reaching its start stop changing.
+    fn check_loop(&mut self, condition: Option<&Expression>, block: &Block, state: &mut State) {
+        let entry = state.clone();
+        let mut head = entry.clone();
+
+        loop {
+            let mut after_condition = head.clone();
+            if let Some(condition) = condition {
+                self.check_expression(condition, &mut after_condition);
+            }
+
+            self.loops.push(LoopStates {
+                breaks: State::unreachable(),
+                continues: State::unreachable(),
+            });
+            let mut body = after_condition.clone();
+            self.check_block(block, &mut body);
+            let states = self.loops.pop().unwrap();
+
+            let next_head = entry.clone().join(body).join(states.continues);
+            if next_head == head {
+                *state = match condition {
+                    Some(_) => after_condition.join(states.breaks),
+                    None => states.breaks,
+                };
+                return;
+            }
+            head = next_head;
+        }
+    }
+
+    // Marks the binding holding the map `expression` is in as changed.
+    fn modify(&mut self, expression: &Expression) {
+        let mut map = expression;
+        while let ExpressionKind::Index(inner) = &map.kind {
+            map = &inner.expression;
+        }
+        if let ExpressionKind::Identifier(identifier) = &map.kind {
+            if let Some(binding) = self.get_binding(identifier) {
+                self.bindings[binding].reassigned = true;
+            }
+        }
+    }
+
+    fn check_assignment_target(
+        &mut self,
+        node: usize,
+        target: &AssignmentTarget,
+        span: Span,
+        state: &mut State,
+    ) {
+        match target {
+            AssignmentTarget::Identifier(identifier) => {
+                if let Some(binding) = self.get_binding(identifier) {
+                    // The first assignment to a binding declared without a
+                    // value initializes it.
+                    let definitions = &self.definitions;
+                    if state
+                        .definitions
+                        .iter()
+                        .any(|definition| definitions[*definition].binding == binding)
+                    {
+                        self.bindings[binding].reassigned = true;
+                    }
+                    self.define(node, binding, span, state);
+                }
+            }
+            AssignmentTarget::Index(index) => {
+                self.modify(&index.expression);
+                self.check_expression(&index.expression, state);
+                self.check_expression(&index.index, state);
+            }
+            AssignmentTarget::Tuple(targets) => {
+                for target in targets {
+                    self.check_assignment_target(node, target, span, state);
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression, state: &mut State) {
+        match &expression.kind {
+            ExpressionKind::Literal(_) => {}
+            ExpressionKind::Identifier(identifier) => self.read(identifier, state),
+            ExpressionKind::BinaryOperation(operation) => {
+                self.check_expression(&operation.left_expression, state);
+                self.check_expression(&operation.right_expression, state);
+            }
+            ExpressionKind::UnaryOperation(operation) => {
+                self.check_expression(&operation.expression, state)
+            }
+            ExpressionKind::Cast(cast) => self.check_expression(&cast.expression, state),
+            ExpressionKind::Try(_try) => self.check_expression(&_try.expression, state),
+            ExpressionKind::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    self.check_expression(argument, state);
+                }
+                if call.function_name == "remove" && self.get_binding("remove").is_none() {
+                    if let Some(map) = call.arguments.first() {
+                        self.modify(map);
+                    }
+                }
+                self.read(&call.function_name, state);
+            }
+            ExpressionKind::Call(call) => {
+                self.check_expression(&call.callee, state);
+                for argument in &call.arguments {
+                    self.check_expression(argument, state);
+                }
+            }
+            ExpressionKind::EnumVariant(variant) => {
+                for argument in &variant.arguments {
+                    self.check_expression(argument, state);
+                }
+            }
+            ExpressionKind::Match(_match) => {
+                self.check_expression(&_match.expression, state);
+
+                let mut after = State::unreachable();
+                for arm in &_match.arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_match_pattern(address(arm), &arm.pattern);
+
+                    let mut branch = state.clone();
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard, &mut branch);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(expression) => {
+                            self.check_expression(expression, &mut branch)
+                        }
+                        MatchArmBody::Block(block) => self.check_block(block, &mut branch),
+                    }
+
+                    self.scopes.pop();
+                    after = after.join(branch);
+                }
+
+                *state = after;
+            }
+            ExpressionKind::MapLiteral(map_literal) => {
+                for (key, value) in &map_literal.entries {
+                    self.check_expression(key, state);
+                    self.check_expression(value, state);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.check_expression(&index.expression, state);
+                self.check_expression(&index.index, state);
+            }
+            ExpressionKind::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.check_expression(element, state);
+                }
+            }
+            // The body reads the captured variables when the closure is
+            // created, since they are copied into it.
+            ExpressionKind::Closure(closure) => {
+                let loops = std::mem::take(&mut self.loops);
+                self.scopes.push(HashMap::new());
+
+                for parameter in &closure.parameters {
+                    self.declare(address(closure), parameter, expression.span, false);
+                }
+
+                let mut body = state.clone();
+                match &closure.body {
+                    ClosureBody::Expression(expression) => {
+                        self.check_expression(expression, &mut body)
+                    }
+                    ClosureBody::Block(block) => self.check_block(block, &mut body),
+                }
+
+                self.scopes.pop();
+                self.loops = loops;
+            }
+        }
+    }
+
+    fn report(&self, context: &mut LintContext) {
+        for binding in &self.bindings {
+            // The names of imported files are there to be used by other files.
+            if !binding.read
+                && !binding.identifier.starts_with('_')
+                && !binding.identifier.contains("::")
+            {
+                context.report(
+                    &UNUSED_VARIABLES,
+                    Diagnostic::warning(
+                        format!("unused variable: `{}`", binding.identifier),
+                        binding.declaration,
+                        "never read".to_string(),
+                    )
+                    .with_help(format!(
+                        "if this is intentional, prefix it with an underscore: `_{}`",
+                        binding.identifier
+                    )),
+                );
+            } else if binding.mutable && !binding.reassigned {
+                context.report(
+                    &UNUSED_MUT,
+                    Diagnostic::warning(
+                        format!(
+                            "variable `{}` does not need to be mutable",
+                            binding.identifier
+                        ),
+                        binding.declaration,
+                        "never reassigned".to_string(),
+                    )
+                    .with_help("remove the `mut`".to_string()),
+                );
+            }
+        }
+
+        // Variables that are never read are already reported above.
+        for definition in &self.definitions {
+            let binding = &self.bindings[definition.binding];
+            if !definition.read && binding.read {
+                context.report(
+                    &UNUSED_ASSIGNMENTS,
+                    Diagnostic::warning(
+                        format!("value assigned to `{}` is never read", binding.identifier),
+                        definition.span,
+                        "never read".to_string(),
+                    )
+                    .with_help("maybe it is overwritten before being read?".to_string()),
+                );
+            }
+        }
+
+        for (binding, shadowed) in &self.shadowed {
+            let binding = &self.bindings[*binding];
+            context.report(
+                &SAME_SCOPE_SHADOWING,
+                Diagnostic::warning(
+                    format!(
+                        "`{}` shadows a binding in the same scope",
+                        binding.identifier
+                    ),
+                    binding.declaration,
+                    "shadows the previous binding".to_string(),
+                )
+                .with_secondary_label(
+                    self.bindings[*shadowed].declaration,
+                    "previous binding".to_string(),
+                ),
+            );
+        }
+    }
+}
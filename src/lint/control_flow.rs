@@ -0,0 +1,298 @@
+use super::{LintContext, CONSTANT_CONDITION, LOOP_WITHOUT_BREAK, UNREACHABLE_CODE};
+use crate::{
+    ast::{
+        block::Block,
+        expression::{
+            BinaryOperationType, ClosureBody, Expression, ExpressionKind, Literal, MatchArmBody,
+            UnaryOperationType,
+        },
+        statement::Statement,
+    },
+    diagnostic::{Diagnostic, Span},
+};
+
+// Finds unreachable statements, `if` and `while` conditions that never
+// change, and `loop`s that can never be left.
+pub fn check(program: &Block, context: &mut LintContext) {
+    check_block(program, context);
+}
+
+fn check_block(block: &Block, context: &mut LintContext) {
+    let mut diverging_statement = None;
+
+    for (i, statement) in block.statements.iter().enumerate() {
+        check_statement(statement, context);
+
+        if diverging_statement.is_none() && diverges(statement) {
+            diverging_statement = Some((i, statement.span().unwrap()));
+        }
+    }
+
+    // Everything after the statement that diverges is reported together.
+    if let Some((i, diverging_span)) = diverging_statement {
+        let unreachable: Vec<Span> = block.statements[i + 1..]
+            .iter()
+            .filter_map(|statement| statement.span())
+            .collect();
+
+        if let (Some(first), Some(last)) = (unreachable.first(), unreachable.last()) {
+            context.report(
+                &UNREACHABLE_CODE,
+                Diagnostic::warning(
+                    "unreachable statement".to_string(),
+                    first.to(*last),
+                    "unreachable statement".to_string(),
+                )
+                .with_secondary_label(
+                    diverging_span,
+                    "any code following this statement is unreachable".to_string(),
+                ),
+            );
+        }
+    }
+}
+
+fn check_statement(statement: &Statement, context: &mut LintContext) {
+    match statement {
+        Statement::LetStatement(statement) => {
+            if let Some(expression) = &statement.expression {
+                check_expression(expression, context);
+            }
+        }
+        Statement::Assignment(statement) => check_expression(&statement.expression, context),
+        Statement::IfStatement(statement) => {
+            for condition_with_block in std::iter::once(&statement._if).chain(&statement.else_if) {
+                check_condition(&condition_with_block.condition, false, context);
+                check_block(&condition_with_block.block, context);
+            }
+
+            if let Some(block) = &statement._else {
+                check_block(block, context);
+            }
+        }
+        Statement::LoopStatement(statement) => {
+            if !has_break(&statement.block) {
+                context.report(
+                    &LOOP_WITHOUT_BREAK,
+                    Diagnostic::warning(
+                        "this `loop` has no `break`".to_string(),
+                        statement.span,
+                        "this loop never ends".to_string(),
+                    ),
+                );
+            }
+            check_block(&statement.block, context);
+        }
+        Statement::WhileStatement(statement) => {
+            check_condition(&statement.condition, true, context);
+            check_block(&statement.block, context);
+        }
+        Statement::Expression(expression) => check_expression(expression, context),
+        Statement::BreakStatement(_)
+        | Statement::ContinueStatement(_)
+        | Statement::EnumDeclaration(_)
+        | Statement::EmptyStatement => {}
+    }
+}
+
+fn check_condition(condition: &Expression, is_loop: bool, context: &mut LintContext) {
+    check_expression(condition, context);
+
+    let value = match evaluate_constant(condition) {
+        None => return,
+        Some(value) => value != 0,
+    };
+
+    let mut diagnostic = Diagnostic::warning(
+        format!("this condition is always {}", value),
+        condition.span,
+        "constant condition".to_string(),
+    );
+    if is_loop && value {
+        diagnostic =
+            diagnostic.with_help("use `loop` for loops that only end with `break`".to_string());
+    }
+    context.report(&CONSTANT_CONDITION, diagnostic);
+}
+
+// Only looks for blocks inside expressions, in `match` arms and closures.
+fn check_expression(expression: &Expression, context: &mut LintContext) {
+    match &expression.kind {
+        ExpressionKind::Literal(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::BinaryOperation(operation) => {
+            check_expression(&operation.left_expression, context);
+            check_expression(&operation.right_expression, context);
+        }
+        ExpressionKind::UnaryOperation(operation) => {
+            check_expression(&operation.expression, context)
+        }
+        ExpressionKind::Cast(cast) => check_expression(&cast.expression, context),
+        ExpressionKind::Try(_try) => check_expression(&_try.expression, context),
+        ExpressionKind::FunctionCall(call) => {
+            for argument in &call.arguments {
+                check_expression(argument, context);
+            }
+        }
+        ExpressionKind::Call(call) => {
+            check_expression(&call.callee, context);
+            for argument in &call.arguments {
+                check_expression(argument, context);
+            }
+        }
+        ExpressionKind::EnumVariant(variant) => {
+            for argument in &variant.arguments {
+                check_expression(argument, context);
+            }
+        }
+        ExpressionKind::Match(_match) => {
+            check_expression(&_match.expression, context);
+            for arm in &_match.arms {
+                if let Some(guard) = &arm.guard {
+                    check_expression(guard, context);
+                }
+                match &arm.body {
+                    MatchArmBody::Expression(expression) => check_expression(expression, context),
+                    MatchArmBody::Block(block) => check_block(block, context),
+                }
+            }
+        }
+        ExpressionKind::MapLiteral(map_literal) => {
+            for (key, value) in &map_literal.entries {
+                check_expression(key, context);
+                check_expression(value, context);
+            }
+        }
+        ExpressionKind::Index(index) => {
+            check_expression(&index.expression, context);
+            check_expression(&index.index, context);
+        }
+        ExpressionKind::Tuple(tuple) => {
+            for element in &tuple.elements {
+                check_expression(element, context);
+            }
+        }
+        ExpressionKind::Closure(closure) => match &closure.body {
+            ClosureBody::Expression(expression) => check_expression(expression, context),
+            ClosureBody::Block(block) => check_block(block, context),
+        },
+    }
+}
+
+// Returns true iff control never reaches the statement after `statement`.
+fn diverges(statement: &Statement) -> bool {
+    match statement {
+        Statement::BreakStatement(_) | Statement::ContinueStatement(_) => true,
+        Statement::IfStatement(statement) => match &statement._else {
+            None => false,
+            Some(block) => {
+                std::iter::once(&statement._if)
+                    .chain(&statement.else_if)
+                    .all(|condition_with_block| block_diverges(&condition_with_block.block))
+                    && block_diverges(block)
+            }
+        },
+        Statement::LoopStatement(statement) => !has_break(&statement.block),
+        _ => false,
+    }
+}
+
+fn block_diverges(block: &Block) -> bool {
+    block.statements.iter().any(diverges)
+}
+
+// Returns true iff `block` contains a `break` out of the loop it is the body
+// of. Breaks in nested loops and closures belong to those.
+fn has_break(block: &Block) -> bool {
+    block.statements.iter().any(|statement| match statement {
+        Statement::BreakStatement(_) => true,
+        Statement::IfStatement(statement) => {
+            std::iter::once(&statement._if)
+                .chain(&statement.else_if)
+                .any(|condition_with_block| has_break(&condition_with_block.block))
+                || statement._else.as_ref().is_some_and(has_break)
+        }
+        Statement::LetStatement(statement) => statement
+            .expression
+            .as_ref()
+            .is_some_and(expression_has_break),
+        Statement::Assignment(statement) => expression_has_break(&statement.expression),
+        Statement::Expression(expression) => expression_has_break(expression),
+        _ => false,
+    })
+}
+
+// `break` can only appear in a block in a `match` arm.
+fn expression_has_break(expression: &Expression) -> bool {
+    match &expression.kind {
+        ExpressionKind::Match(_match) => _match.arms.iter().any(|arm| match &arm.body {
+            MatchArmBody::Block(block) => has_break(block),
+            MatchArmBody::Expression(expression) => expression_has_break(expression),
+        }),
+        ExpressionKind::BinaryOperation(operation) => {
+            expression_has_break(&operation.left_expression)
+                || expression_has_break(&operation.right_expression)
+        }
+        ExpressionKind::UnaryOperation(operation) => expression_has_break(&operation.expression),
+        ExpressionKind::Cast(cast) => expression_has_break(&cast.expression),
+        ExpressionKind::Try(_try) => expression_has_break(&_try.expression),
+        ExpressionKind::FunctionCall(call) => call.arguments.iter().any(expression_has_break),
+        ExpressionKind::Call(call) => {
+            expression_has_break(&call.callee) || call.arguments.iter().any(expression_has_break)
+        }
+        ExpressionKind::EnumVariant(variant) => variant.arguments.iter().any(expression_has_break),
+        ExpressionKind::MapLiteral(map_literal) => map_literal
+            .entries
+            .iter()
+            .any(|(key, value)| expression_has_break(key) || expression_has_break(value)),
+        ExpressionKind::Index(index) => {
+            expression_has_break(&index.expression) || expression_has_break(&index.index)
+        }
+        ExpressionKind::Tuple(tuple) => tuple.elements.iter().any(expression_has_break),
+        ExpressionKind::Literal(_) | ExpressionKind::Identifier(_) | ExpressionKind::Closure(_) => {
+            false
+        }
+    }
+}
+
+// The value of an expression made only of integer literals, if it can be
+// worked out without running into an error like division by zero.
+fn evaluate_constant(expression: &Expression) -> Option<u64> {
+    match &expression.kind {
+        ExpressionKind::Literal(Literal::IntegerLiteral(value)) => Some(*value),
+        ExpressionKind::UnaryOperation(operation) => {
+            let value = evaluate_constant(&operation.expression)?;
+            Some(match operation.operation_type {
+                UnaryOperationType::LogicalNot => (value == 0) as u64,
+                UnaryOperationType::BitwiseNot => !value,
+                UnaryOperationType::Plus => value,
+                UnaryOperationType::Minus => value.wrapping_neg(),
+            })
+        }
+        ExpressionKind::BinaryOperation(operation) => {
+            let left = evaluate_constant(&operation.left_expression)?;
+            let right = evaluate_constant(&operation.right_expression)?;
+            Some(match operation.operation_type {
+                BinaryOperationType::Add => left.wrapping_add(right),
+                BinaryOperationType::Subtract => left.wrapping_sub(right),
+                BinaryOperationType::Multiply => left.wrapping_mul(right),
+                BinaryOperationType::Divide => left.checked_div(right)?,
+                BinaryOperationType::Modulus => left.checked_rem(right)?,
+                BinaryOperationType::Exponentiate => left.wrapping_pow(u32::try_from(right).ok()?),
+                BinaryOperationType::Equal => (left == right) as u64,
+                BinaryOperationType::NotEqual => (left != right) as u64,
+                BinaryOperationType::GreaterEqual => (left >= right) as u64,
+                BinaryOperationType::LessEqual => (left <= right) as u64,
+                BinaryOperationType::Greater => (left > right) as u64,
+                BinaryOperationType::Less => (left < right) as u64,
+                BinaryOperationType::LogicalAnd => (left != 0 && right != 0) as u64,
+                BinaryOperationType::LogicalOr => (left != 0 || right != 0) as u64,
+                BinaryOperationType::BitwiseAnd => left & right,
+                BinaryOperationType::BitwiseOr => left | right,
+                BinaryOperationType::BitwiseXor => left ^ right,
+                BinaryOperationType::LeftShift => left.checked_shl(u32::try_from(right).ok()?)?,
+                BinaryOperationType::RightShift => left.checked_shr(u32::try_from(right).ok()?)?,
+            })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,290 @@
+mod bindings;
+mod control_flow;
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::block::Block,
+    diagnostic::{Diagnostic, Severity},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+pub struct Lint {
+    pub name: &'static str,
+    default_level: Level,
+}
+
+pub static UNUSED_VARIABLES: Lint = Lint {
+    name: "unused_variables",
+    default_level: Level::Warn,
+};
+
+pub static UNUSED_ASSIGNMENTS: Lint = Lint {
+    name: "unused_assignments",
+    default_level: Level::Warn,
+};
+
+pub static UNUSED_MUT: Lint = Lint {
+    name: "unused_mut",
+    default_level: Level::Warn,
+};
+
+pub static SAME_SCOPE_SHADOWING: Lint = Lint {
+    name: "same_scope_shadowing",
+    default_level: Level::Warn,
+};
+
+pub static UNREACHABLE_CODE: Lint = Lint {
+    name: "unreachable_code",
+    default_level: Level::Warn,
+};
+
+pub static CONSTANT_CONDITION: Lint = Lint {
+    name: "constant_condition",
+    default_level: Level::Warn,
+};
+
+pub static LOOP_WITHOUT_BREAK: Lint = Lint {
+    name: "loop_without_break",
+    default_level: Level::Warn,
+};
+
+pub static LINTS: [&Lint; 7] = [
+    &UNUSED_VARIABLES,
+    &UNUSED_ASSIGNMENTS,
+    &UNUSED_MUT,
+    &SAME_SCOPE_SHADOWING,
+    &UNREACHABLE_CODE,
+    &CONSTANT_CONDITION,
+    &LOOP_WITHOUT_BREAK,
+];
+
+// `warnings` stands for every lint.
+const ALL_LINTS: &str = "warnings";
+
+// Checks a lint name given on the command line. Dashes may be used instead
+// of underscores, like `-A unused-variables`.
+pub fn parse_lint_name(name: &str) -> Result<String, String> {
+    let name = name.replace('-', "_");
+
+    if name == ALL_LINTS || LINTS.iter().any(|lint| lint.name == name) {
+        Ok(name)
+    } else {
+        Err(format!(
+            "unknown lint, expected one of: {}, {}",
+            ALL_LINTS,
+            LINTS
+                .iter()
+                .map(|lint| lint.name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+pub struct LintLevels {
+    levels: HashMap<&'static str, Level>,
+}
+
+impl Default for LintLevels {
+    fn default() -> LintLevels {
+        LintLevels {
+            levels: LINTS
+                .iter()
+                .map(|lint| (lint.name, lint.default_level))
+                .collect(),
+        }
+    }
+}
+
+impl LintLevels {
+    // `name` has to be checked by `parse_lint_name` first.
+    pub fn set(&mut self, name: &str, level: Level) {
+        for (lint, lint_level) in &mut self.levels {
+            if name == ALL_LINTS || *lint == name {
+                *lint_level = level;
+            }
+        }
+    }
+}
+
+// Collects the lints found by the passes.
+#[derive(Default)]
+struct LintContext {
+    reported: Vec<(&'static Lint, Diagnostic)>,
+}
+
+impl LintContext {
+    fn report(&mut self, lint: &'static Lint, diagnostic: Diagnostic) {
+        self.reported.push((lint, diagnostic));
+    }
+}
+
+// Runs every lint over a program that has passed type checking. Denied lints
+// are reported as errors.
+pub fn check(program: &Block, levels: &LintLevels) -> Vec<Diagnostic> {
+    let mut context = LintContext::default();
+    bindings::check(program, &mut context);
+    control_flow::check(program, &mut context);
+
+    context
+        .reported
+        .sort_by_key(|(_, diagnostic)| diagnostic.label.span.start);
+
+    context
+        .reported
+        .into_iter()
+        .filter_map(|(lint, diagnostic)| {
+            let severity = match levels.levels[lint.name] {
+                Level::Allow => return None,
+                Level::Warn => Severity::Warning,
+                Level::Deny => Severity::Error,
+            };
+
+            Some(Diagnostic {
+                severity,
+                code: Some(lint.name),
+                ..diagnostic
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Level, LintLevels};
+    use crate::{diagnostic::Severity, ir::testing::get_checked_program, parser::Parser, typeck};
+
+    fn get_lints(source: &str, levels: &LintLevels) -> Vec<String> {
+        let program = get_checked_program(source);
+        check(&program, levels)
+            .iter()
+            .map(|diagnostic| {
+                format!(
+                    "{}[{}]: {}",
+                    diagnostic.severity,
+                    diagnostic.code.unwrap(),
+                    diagnostic.message
+                )
+            })
+            .collect()
+    }
+
+    fn get_warnings(source: &str) -> Vec<String> {
+        get_lints(source, &LintLevels::default())
+    }
+
+    #[test]
+    fn test_unused_variables() {
+        assert_eq!(
+            get_warnings("let a = 1; let _b = 2; let (c, d) = (1, 2); print(c); let f = |x, y| x;"),
+            vec![
+                "warning[unused_variables]: unused variable: `a`",
+                "warning[unused_variables]: unused variable: `d`",
+                "warning[unused_variables]: unused variable: `f`",
+                "warning[unused_variables]: unused variable: `y`",
+            ]
+        );
+
+        // A binding in a match arm is pointed at in its pattern.
+        let mut program = Parser::new("let a = (1, 2); print(match a { (b, c) => c });")
+            .get_ast()
+            .unwrap()
+            .unwrap();
+        assert!(typeck::check(&mut program).is_empty());
+        let lints = check(&program, &LintLevels::default());
+        let span = lints[0].label.span;
+        assert_eq!(
+            (lints[0].message.as_str(), span.start, span.end),
+            ("unused variable: `b`", 33, 34)
+        );
+    }
+
+    #[test]
+    fn test_unused_assignments() {
+        assert_eq!(
+            get_warnings(
+                "let mut a = 1; a = 2; print(a); a = 3;
+                let mut i = 0; while i < 3 { i += 1; };
+                let mut b = 0; if i { b = 1; }; print(b);
+                let mut c = 0; let f = || c; c = 1; print(f());"
+            ),
+            vec![
+                "warning[unused_assignments]: value assigned to `a` is never read",
+                "warning[unused_assignments]: value assigned to `a` is never read",
+                "warning[unused_assignments]: value assigned to `c` is never read",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_mut() {
+        assert_eq!(
+            get_warnings(
+                "let mut a = 1; let mut m = {}; m[1] = 2; print(m); print(a);
+                let mut n = {1: 1}; remove(n, 1); print(n);
+                loop { let mut b = 0; print(b); break; };
+                let mut c; if a { c = 1; } else { c = 2; }; print(c);
+                let mut d; while a { d = 1; print(d); };"
+            ),
+            vec![
+                "warning[unused_mut]: variable `a` does not need to be mutable",
+                "warning[unused_mut]: variable `b` does not need to be mutable",
+                "warning[unused_mut]: variable `c` does not need to be mutable",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_scope_shadowing() {
+        assert_eq!(
+            get_warnings("let a = 1; let a = a + 1; if a { let a = 3; print(a); };"),
+            vec!["warning[same_scope_shadowing]: `a` shadows a binding in the same scope"]
+        );
+    }
+
+    #[test]
+    fn test_control_flow_lints() {
+        assert_eq!(
+            get_warnings(
+                "let mut i = 0;
+                while 1 { i += 1; if i > 3 { break; print(i); }; };
+                loop { if 1 - 1 { continue; } else { break; }; i += 1; };
+                loop { print(i); };"
+            ),
+            vec![
+                "warning[constant_condition]: this condition is always true",
+                "warning[unreachable_code]: unreachable statement",
+                "warning[constant_condition]: this condition is always false",
+                "warning[unreachable_code]: unreachable statement",
+                "warning[loop_without_break]: this `loop` has no `break`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let source = "let a = 1; let mut b = 2; print(b);";
+
+        let mut levels = LintLevels::default();
+        levels.set("unused_variables", Level::Allow);
+        levels.set("unused_mut", Level::Deny);
+        assert_eq!(
+            get_lints(source, &levels),
+            vec!["error[unused_mut]: variable `b` does not need to be mutable"]
+        );
+
+        levels.set("warnings", Level::Deny);
+        let mut program = Parser::new(source).get_ast().unwrap().unwrap();
+        typeck::check(&mut program);
+        assert!(check(&program, &levels)
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+    }
+}
@@ -0,0 +1,173 @@
+mod rename;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    ast::{block::Block, import::Import},
+    diagnostic::{Diagnostic, SourceMap, Span},
+    parser::Parser,
+};
+use rename::rename_module;
+
+// Parses the program in `path` and every file it imports into one tree.
+//
+// The top-level names of an imported file are renamed to `module::name`, and
+// its statements are put before those of the first file that imports it, so
+// that it runs once, before anything that uses it.
+pub fn load(
+    path: &Path,
+    source: String,
+    sources: &mut SourceMap,
+) -> Result<Block, Box<Diagnostic>> {
+    let mut loader = Loader {
+        sources,
+        modules: HashMap::new(),
+        stack: vec![],
+    };
+    if let Ok(canonical_path) = fs::canonicalize(path) {
+        loader
+            .stack
+            .push((canonical_path, path.display().to_string()));
+    }
+    loader.load_file(path, source, None)
+}
+
+struct Loader<'a> {
+    sources: &'a mut SourceMap,
+    // The files imported so far, by module name.
+    modules: HashMap<String, PathBuf>,
+    // The files being loaded, each imported by the one before it, with the
+    // paths they were imported by.
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl Loader<'_> {
+    fn load_file(
+        &mut self,
+        path: &Path,
+        source: String,
+        module: Option<&str>,
+    ) -> Result<Block, Box<Diagnostic>> {
+        let start = self.sources.add(path.display().to_string(), source);
+        let file = self.sources.file(Span { start, end: start });
+
+        let mut parser = Parser::with_start(&file.source, start);
+        let (imports, program) = match parser.get_imports().and_then(|imports| {
+            let program = parser.get_ast()?.unwrap();
+            Ok((imports, program))
+        }) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                let position = parser.lexer.position();
+                return Err(Box::new(error.to_diagnostic(Span {
+                    start: position,
+                    end: position,
+                })));
+            }
+        };
+
+        let mut program = program;
+        if let Some(module) = module {
+            rename_module(&mut program, module);
+        }
+
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut statements = vec![];
+        for import in &imports {
+            statements.extend(self.load_import(directory, import)?.statements);
+        }
+        statements.append(&mut program.statements);
+
+        Ok(Block { statements })
+    }
+
+    // Gives the statements of the imported file, or none if it was already
+    // imported.
+    fn load_import(&mut self, directory: &Path, import: &Import) -> Result<Block, Box<Diagnostic>> {
+        let path = directory.join(&import.path);
+        let read_error = |error: std::io::Error| {
+            Box::new(Diagnostic::error(
+                format!("couldn't read `{}`: {}", path.display(), error),
+                import.span,
+                "imported here".to_string(),
+            ))
+        };
+        let canonical_path = fs::canonicalize(&path).map_err(read_error)?;
+
+        if let Some(i) = self
+            .stack
+            .iter()
+            .position(|(file, _)| *file == canonical_path)
+        {
+            let cycle: Vec<&str> = self.stack[i..]
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .chain([self.stack[i].1.as_str()])
+                .collect();
+            return Err(Box::new(
+                Diagnostic::error(
+                    format!("cycle detected when importing `{}`", import.path),
+                    import.span,
+                    "this file is already being imported".to_string(),
+                )
+                .with_note(format!("the cycle is {}", cycle.join(" -> "))),
+            ));
+        }
+
+        match self.modules.get(&import.module_name) {
+            Some(imported) if *imported == canonical_path => {
+                return Ok(Block { statements: vec![] })
+            }
+            Some(_) => {
+                return Err(Box::new(Diagnostic::error(
+                    format!(
+                        "the name `{}` is imported more than once",
+                        import.module_name
+                    ),
+                    import.span,
+                    "another file with this name was already imported".to_string(),
+                )))
+            }
+            None => {}
+        }
+
+        let source = fs::read_to_string(&path).map_err(read_error)?;
+        self.modules
+            .insert(import.module_name.clone(), canonical_path.clone());
+
+        self.stack
+            .push((canonical_path, path.display().to_string()));
+        let program = self.load_file(&path, source, Some(&import.module_name));
+        self.stack.pop();
+        program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::load;
+    use crate::diagnostic::SourceMap;
+
+    #[test]
+    fn test_import_cycle() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/semantics/imports/cycle_a.l");
+        let mut sources = SourceMap::default();
+        let diagnostic = load(&path, fs::read_to_string(&path).unwrap(), &mut sources).unwrap_err();
+
+        assert_eq!(
+            diagnostic.message,
+            "cycle detected when importing `cycle_a.l`"
+        );
+        // The error is in the file that closes the cycle.
+        assert!(sources
+            .file(diagnostic.label.span)
+            .path
+            .ends_with("cycle_b.l"));
+    }
+}
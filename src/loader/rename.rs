@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use crate::ast::{
+    block::Block,
+    expression::{ClosureBody, Expression, ExpressionKind, MatchArmBody},
+    pattern::{Pattern, PatternKind},
+    statement::{AssignmentTarget, Statement},
+    type_annotation::{TypeAnnotation, TypeAnnotationKind},
+};
+
+// Renames the top-level bindings and enums of an imported file to
+// `module::name`, along with every use of them that is not shadowed, so that
+// they are apart from the names of every other file.
+pub fn rename_module(program: &mut Block, module: &str) {
+    let mut renamer = Renamer {
+        module,
+        scopes: vec![Scope::default()],
+    };
+    for statement in &mut program.statements {
+        renamer.rename_statement(statement);
+    }
+}
+
+// Values and enums are named separately, so `Shape` can be both.
+#[derive(Default)]
+struct Scope {
+    values: HashSet<String>,
+    enums: HashSet<String>,
+}
+
+struct Renamer<'a> {
+    module: &'a str,
+    // The top-level scope is the first.
+    scopes: Vec<Scope>,
+}
+
+impl Renamer<'_> {
+    fn qualify(&self, name: &mut String) {
+        *name = format!("{}::{}", self.module, name);
+    }
+
+    fn is_top_level(&self, name: &str, get_names: fn(&Scope) -> &HashSet<String>) -> bool {
+        self.scopes
+            .iter()
+            .rposition(|scope| get_names(scope).contains(name))
+            == Some(0)
+    }
+
+    fn declare_value(&mut self, name: &mut String) {
+        self.scopes.last_mut().unwrap().values.insert(name.clone());
+        if self.scopes.len() == 1 {
+            self.qualify(name);
+        }
+    }
+
+    fn use_value(&self, name: &mut String) {
+        if self.is_top_level(name, |scope| &scope.values) {
+            self.qualify(name);
+        }
+    }
+
+    fn use_enum(&self, name: &mut String) {
+        if self.is_top_level(name, |scope| &scope.enums) {
+            self.qualify(name);
+        }
+    }
+
+    fn rename_block(&mut self, block: &mut Block) {
+        self.scopes.push(Scope::default());
+        for statement in &mut block.statements {
+            self.rename_statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn rename_statement(&mut self, statement: &mut Statement) {
+        match statement {
+            // The expression is renamed first, since it cannot see the
+            // bindings of its own `let`.
+            Statement::LetStatement(statement) => {
+                if let Some(expression) = &mut statement.expression {
+                    self.rename_expression(expression);
+                }
+                if let Some(type_annotation) = &mut statement.type_annotation {
+                    self.rename_type_annotation(type_annotation);
+                }
+                self.rename_pattern(&mut statement.pattern);
+            }
+            Statement::Assignment(statement) => {
+                self.rename_expression(&mut statement.expression);
+                self.rename_assignment_target(&mut statement.target);
+            }
+            Statement::IfStatement(statement) => {
+                for condition_with_block in
+                    std::iter::once(&mut statement._if).chain(&mut statement.else_if)
+                {
+                    self.rename_expression(&mut condition_with_block.condition);
+                    self.rename_block(&mut condition_with_block.block);
+                }
+                if let Some(block) = &mut statement._else {
+                    self.rename_block(block);
+                }
+            }
+            Statement::LoopStatement(statement) => self.rename_block(&mut statement.block),
+            Statement::WhileStatement(statement) => {
+                self.rename_expression(&mut statement.condition);
+                self.rename_block(&mut statement.block);
+            }
+            Statement::EnumDeclaration(declaration) => {
+                let scope = self.scopes.last_mut().unwrap();
+                scope.enums.insert(declaration.identifier.clone());
+                if self.scopes.len() == 1 {
+                    self.qualify(&mut declaration.identifier);
+                }
+            }
+            Statement::Expression(expression) => self.rename_expression(expression),
+            Statement::BreakStatement(_)
+            | Statement::ContinueStatement(_)
+            | Statement::EmptyStatement => {}
+        }
+    }
+
+    fn rename_assignment_target(&mut self, target: &mut AssignmentTarget) {
+        match target {
+            AssignmentTarget::Identifier(identifier) => self.use_value(identifier),
+            AssignmentTarget::Index(index) => {
+                self.rename_expression(&mut index.expression);
+                self.rename_expression(&mut index.index);
+            }
+            AssignmentTarget::Tuple(targets) => {
+                for target in targets {
+                    self.rename_assignment_target(target);
+                }
+            }
+        }
+    }
+
+    // Declares the bindings in the pattern in the innermost scope.
+    fn rename_pattern(&mut self, pattern: &mut Pattern) {
+        match &mut pattern.kind {
+            PatternKind::Binding(identifier) => self.declare_value(identifier),
+            PatternKind::Variant(variant_pattern) => {
+                self.use_enum(&mut variant_pattern.enum_name);
+                for field in &mut variant_pattern.fields {
+                    self.rename_pattern(field);
+                }
+            }
+            PatternKind::Tuple(elements) => {
+                for element in elements {
+                    self.rename_pattern(element);
+                }
+            }
+            PatternKind::Wildcard | PatternKind::IntegerLiteral(_) => {}
+        }
+    }
+
+    fn rename_type_annotation(&self, type_annotation: &mut TypeAnnotation) {
+        match &mut type_annotation.kind {
+            TypeAnnotationKind::Named(name) => self.use_enum(name),
+            TypeAnnotationKind::Tuple(elements) => {
+                for element in elements {
+                    self.rename_type_annotation(element);
+                }
+            }
+            TypeAnnotationKind::Map(key, value) | TypeAnnotationKind::Result(key, value) => {
+                self.rename_type_annotation(key);
+                self.rename_type_annotation(value);
+            }
+            TypeAnnotationKind::Function(parameters, result) => {
+                for parameter in parameters {
+                    self.rename_type_annotation(parameter);
+                }
+                self.rename_type_annotation(result);
+            }
+        }
+    }
+
+    fn rename_expression(&mut self, expression: &mut Expression) {
+        match &mut expression.kind {
+            ExpressionKind::Literal(_) => {}
+            ExpressionKind::Identifier(identifier) => self.use_value(identifier),
+            ExpressionKind::BinaryOperation(operation) => {
+                self.rename_expression(&mut operation.left_expression);
+                self.rename_expression(&mut operation.right_expression);
+            }
+            ExpressionKind::UnaryOperation(operation) => {
+                self.rename_expression(&mut operation.expression)
+            }
+            ExpressionKind::Cast(cast) => {
+                self.rename_expression(&mut cast.expression);
+                self.rename_type_annotation(&mut cast.type_annotation);
+            }
+            ExpressionKind::Try(_try) => self.rename_expression(&mut _try.expression),
+            // Builtins are never declared, so they keep their names.
+            ExpressionKind::FunctionCall(call) => {
+                self.use_value(&mut call.function_name);
+                for argument in &mut call.arguments {
+                    self.rename_expression(argument);
+                }
+            }
+            ExpressionKind::Call(call) => {
+                self.rename_expression(&mut call.callee);
+                for argument in &mut call.arguments {
+                    self.rename_expression(argument);
+                }
+            }
+            ExpressionKind::EnumVariant(variant) => {
+                self.use_enum(&mut variant.enum_name);
+                for argument in &mut variant.arguments {
+                    self.rename_expression(argument);
+                }
+            }
+            ExpressionKind::Match(_match) => {
+                self.rename_expression(&mut _match.expression);
+                for arm in &mut _match.arms {
+                    self.scopes.push(Scope::default());
+                    self.rename_pattern(&mut arm.pattern);
+                    if let Some(guard) = &mut arm.guard {
+                        self.rename_expression(guard);
+                    }
+                    match &mut arm.body {
+                        MatchArmBody::Expression(expression) => self.rename_expression(expression),
+                        MatchArmBody::Block(block) => self.rename_block(block),
+                    }
+                    self.scopes.pop();
+                }
+            }
+            ExpressionKind::MapLiteral(map_literal) => {
+                for (key, value) in &mut map_literal.entries {
+                    self.rename_expression(key);
+                    self.rename_expression(value);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.rename_expression(&mut index.expression);
+                self.rename_expression(&mut index.index);
+            }
+            ExpressionKind::Tuple(tuple) => {
+                for element in &mut tuple.elements {
+                    self.rename_expression(element);
+                }
+            }
+            ExpressionKind::Closure(closure) => {
+                self.scopes.push(Scope::default());
+                for parameter in &mut closure.parameters {
+                    self.declare_value(parameter);
+                }
+                match &mut closure.body {
+                    ClosureBody::Expression(expression) => self.rename_expression(expression),
+                    ClosureBody::Block(block) => self.rename_block(block),
+                }
+                self.scopes.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rename_module;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_rename_module() {
+        let mut program = Parser::new(
+            "enum Shape { Dot };
+            let x = 1;
+            let f = |x| x + 1;
+            let g = || { let x = 2; f(x); };
+            let y: Shape = match x { x => Shape::Dot };
+            x = len(\"a\");",
+        )
+        .get_ast()
+        .unwrap()
+        .unwrap();
+        rename_module(&mut program, "m");
+
+        // Only the top-level names and their uses are qualified.
+        let printed = format!("{:?}", program);
+        let count = |name: &str| printed.matches(&format!("\"{}\"", name)).count();
+        assert_eq!(count("m::Shape"), 3);
+        assert_eq!(count("m::x"), 3);
+        assert_eq!(count("m::f"), 2);
+        assert_eq!(count("m::g"), 1);
+        assert_eq!(count("m::y"), 1);
+        assert_eq!(count("x"), 5);
+        assert_eq!(count("len"), 1);
+    }
+}
@@ -1,23 +1,34 @@
 mod arch;
 mod ast;
 mod current_iterator;
+mod diagnostic;
 mod interp;
 mod ir;
 mod lexer;
+mod lint;
+mod loader;
+mod opt;
 mod parser;
 mod repl;
 mod syntax_error;
+mod typeck;
 
 use arch::Arch;
-use parser::Parser;
+use diagnostic::{Diagnostic, Severity, SourceMap};
+use interp::{Exit, OverflowMode};
+use lint::{Level, LintLevels};
+use opt::Pass;
 use std::fs::read_to_string;
 use std::path::PathBuf;
-use syntax_error::SyntaxError;
+
+// Errors in the source exit with 1 and arguments clap rejects with 2.
+const RUNTIME_ERROR_EXIT_CODE: i32 = 101;
 
 #[derive(clap::ValueEnum, Clone, PartialEq)]
 enum OutputStage {
-    AST,
-    IR,
+    Ast,
+    TypedAst,
+    Ir,
     Run,
 }
 
@@ -28,40 +39,156 @@ struct Args {
     arch: Arch,
     #[arg(long, value_enum, default_value_t=OutputStage::Run)]
     output_stage: OutputStage,
+    /// Report garbage collections and bytes freed when the program exits
+    #[arg(long)]
+    gc_stats: bool,
+    /// Collect garbage on every allocation
+    #[arg(long)]
+    gc_stress: bool,
+    /// Allow a lint (`warnings` for all of them)
+    #[arg(short = 'A', value_name = "LINT", value_parser = lint::parse_lint_name)]
+    allow: Vec<String>,
+    /// Warn about a lint
+    #[arg(short = 'W', value_name = "LINT", value_parser = lint::parse_lint_name)]
+    warn: Vec<String>,
+    /// Make a lint an error
+    #[arg(short = 'D', value_name = "LINT", value_parser = lint::parse_lint_name)]
+    deny: Vec<String>,
+    /// What arithmetic does when the result does not fit its type
+    #[arg(long, value_enum, default_value_t=OverflowMode::Trap)]
+    overflow: OverflowMode,
+    /// How much to optimise the program before running it, from 0 to 2
+    #[arg(short = 'O', value_name = "LEVEL", default_value_t = 0,
+          value_parser = clap::value_parser!(u8).range(0..=2))]
+    opt_level: u8,
+    /// Run these passes in order instead of those of `-O`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PASSES",
+        value_delimiter = ',',
+        conflicts_with = "opt_level"
+    )]
+    passes: Option<Vec<Pass>>,
+    /// Print the IR to stderr after each run of these passes
+    #[arg(long, value_enum, value_name = "PASSES", value_delimiter = ',')]
+    print_after: Vec<Pass>,
+    /// Print the IR to stderr after every pass
+    #[arg(long)]
+    print_after_all: bool,
 }
 
-fn main() -> Result<(), SyntaxError> {
-    let args = <Args as clap::Parser>::parse();
+fn main() {
+    let matches = <Args as clap::CommandFactory>::command().get_matches();
+    let args = <Args as clap::FromArgMatches>::from_arg_matches(&matches)
+        .unwrap_or_else(|error| error.exit());
 
     let input_file = match args.input_file {
         Some(file) => file,
         None => {
             repl::start_repl();
-            return Ok(());
+            return;
         }
     };
 
     // Read input file into String
-    let input_data = read_to_string(input_file).unwrap();
+    let input_data = read_to_string(&input_file).unwrap();
+    let mut sources = SourceMap::default();
+    let program = loader::load(&input_file, input_data, &mut sources);
 
-    let mut p = Parser::new(&input_data);
-    let program = p.get_ast()?.unwrap();
+    // Prints the diagnostics and exits if any of them is an error.
+    let report = |diagnostics: &[Diagnostic]| {
+        for diagnostic in diagnostics {
+            eprintln!("{}", sources.render(diagnostic));
+        }
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            std::process::exit(1);
+        }
+    };
 
-    if args.output_stage == OutputStage::AST {
+    let mut program = match program {
+        Ok(program) => program,
+        Err(diagnostic) => {
+            report(&[*diagnostic]);
+            unreachable!();
+        }
+    };
+
+    if args.output_stage == OutputStage::Ast {
         println!("{:#?}", program);
-        return Ok(());
+        return;
     }
 
-    let ir = ir::get_ir(&program)?;
+    let mut diagnostics = typeck::check(&mut program);
+    if diagnostics.is_empty() {
+        // Later flags win, whichever of `-A`, `-W` and `-D` they are.
+        let mut flags = vec![];
+        for (id, names, level) in [
+            ("allow", &args.allow, Level::Allow),
+            ("warn", &args.warn, Level::Warn),
+            ("deny", &args.deny, Level::Deny),
+        ] {
+            let indices = matches.indices_of(id).into_iter().flatten();
+            flags.extend(indices.zip(names).map(|(index, name)| (index, name, level)));
+        }
+        flags.sort_by_key(|(index, ..)| *index);
+
+        let mut levels = LintLevels::default();
+        for (_, name, level) in flags {
+            levels.set(name, level);
+        }
+        diagnostics = lint::check(&program, &levels);
+    }
+    report(&diagnostics);
 
-    if args.output_stage == OutputStage::IR {
+    if args.output_stage == OutputStage::TypedAst {
+        println!("{:#?}", program);
+        return;
+    }
+
+    let ir = match ir::get_ir(&program) {
+        Ok(ir) => ir,
+        Err((error, span)) => {
+            report(&[error.to_diagnostic(span)]);
+            unreachable!();
+        }
+    };
+
+    let passes = args.passes.unwrap_or_else(|| opt::preset(args.opt_level));
+    let ir = opt::run_passes(
+        &ir,
+        &passes,
+        &opt::Options {
+            overflow: args.overflow,
+            print_after: args.print_after,
+            print_after_all: args.print_after_all,
+        },
+    );
+
+    if args.output_stage == OutputStage::Ir {
         for stmt in ir.statements {
             println!("{}", stmt);
         }
-        return Ok(());
+        return;
     }
 
-    interp::interpret(&ir);
-
-    Ok(())
+    let result = interp::interpret(
+        &ir,
+        &interp::Options {
+            gc_stats: args.gc_stats,
+            gc_stress: args.gc_stress,
+            overflow: args.overflow,
+        },
+    );
+    match result {
+        Ok(Exit::Finished(_)) => {}
+        Ok(Exit::Code(code)) => std::process::exit(code),
+        Err(error) => {
+            eprintln!("{}", error.render(&sources));
+            std::process::exit(RUNTIME_ERROR_EXIT_CODE);
+        }
+    }
 }
@@ -0,0 +1,138 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{cfg::Cfg, IRStatement, Register};
+
+// Makes the readers of a `mov` read the register it copies instead, and
+// removes the `mov`. A phi is a copy too when it has the same source from
+// every predecessor, apart from itself. The graph must be in SSA form, where
+// the register a copy reads is written before everything that reads the copy.
+pub fn propagate_copies(cfg: &mut Cfg) {
+    // The register each copy reads, by the register it writes.
+    let mut copies: HashMap<Register, Register> = HashMap::new();
+    // Code that never runs need not be in SSA form, and its copies may go
+    // round in a cycle, so it is left alone.
+    let dominators = cfg.dominators();
+
+    for (i, block) in cfg.blocks.iter().enumerate() {
+        if !dominators.is_reachable(i) {
+            continue;
+        }
+        for (statement, _) in &block.statements {
+            if let IRStatement::Move { rd, rs1 } = statement {
+                copies.insert(rd.clone(), rs1.clone());
+            }
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for (i, block) in cfg.blocks.iter().enumerate() {
+            // Entries are also reached from outside of the graph, where the
+            // phis have no source.
+            if cfg.entries.contains(&i) || !dominators.is_reachable(i) {
+                continue;
+            }
+
+            for phi in &block.phis {
+                if copies.contains_key(&phi.rd) || phi.sources.len() != cfg.predecessors[i].len() {
+                    continue;
+                }
+
+                let mut sources = phi
+                    .sources
+                    .iter()
+                    .map(|(_, source)| resolve(&copies, source))
+                    .filter(|source| *source != phi.rd);
+                let Some(first) = sources.next() else {
+                    continue;
+                };
+                if sources.all(|source| source == first) {
+                    copies.insert(phi.rd.clone(), first);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    for (i, block) in cfg.blocks.iter_mut().enumerate() {
+        if !dominators.is_reachable(i) {
+            continue;
+        }
+        block.phis.retain(|phi| !copies.contains_key(&phi.rd));
+        for phi in &mut block.phis {
+            for (_, source) in &mut phi.sources {
+                *source = resolve(&copies, source);
+            }
+        }
+
+        block
+            .statements
+            .retain(|(statement, _)| !matches!(statement, IRStatement::Move { .. }));
+        for statement in block.instructions_mut() {
+            let (_, uses) = statement.registers_mut();
+            for register in uses {
+                *register = resolve(&copies, register);
+            }
+        }
+    }
+}
+
+// The register that is not a copy at the end of a chain of copies. Phis of
+// values that are never written can copy each other, so a chain that comes
+// back round ends where it does.
+fn resolve(copies: &HashMap<Register, Register>, register: &Register) -> Register {
+    let mut register = register;
+    let mut seen = HashSet::new();
+    while let Some(source) = copies.get(register) {
+        if !seen.insert(register) {
+            break;
+        }
+        register = source;
+    }
+    register.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::IRStatement,
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    fn count_moves(source: &str) -> usize {
+        let ir = get_optimized_ir(source, &[Pass::Copies], OverflowMode::Trap);
+        count(&ir, |statement| {
+            matches!(statement, IRStatement::Move { .. })
+        })
+    }
+
+    #[test]
+    fn test_propagate_copies() {
+        assert_eq!(
+            count_moves("let s = \"x\"; let t = s; let u = t; print(u);"),
+            0
+        );
+
+        // `s` is the same on every path, so there is nothing to copy back at
+        // the end of the loop.
+        assert_eq!(
+            count_moves(
+                "let s = \"x\";
+                let mut t = s;
+                let mut i = parse_int(\"3\")?;
+                while i > 0 {
+                    t = s;
+                    i -= 1;
+                };
+                print(t);"
+            ),
+            0
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+
+use super::is_pure;
+use crate::{
+    interp::OverflowMode,
+    ir::{cfg::Cfg, Register},
+};
+
+// Deletes the phis and pure instructions whose results are never read, or
+// only read by others that are deleted. Everything else is kept, along with
+// whatever it reads, so values that only feed each other around a loop go
+// too.
+pub fn eliminate_dead_code(cfg: &mut Cfg, overflow: OverflowMode) {
+    // The registers each register that can be deleted is worked out from.
+    let mut operands: HashMap<Register, Vec<Register>> = HashMap::new();
+    let mut worklist = vec![];
+
+    for block in &cfg.blocks {
+        for phi in &block.phis {
+            let sources = phi.sources.iter().map(|(_, source)| source.clone());
+            operands.entry(phi.rd.clone()).or_default().extend(sources);
+        }
+        for statement in block.instructions() {
+            if is_pure(statement, overflow) {
+                for rd in statement.defs() {
+                    operands.entry(rd).or_default().extend(statement.uses());
+                }
+            } else {
+                worklist.extend(statement.uses());
+            }
+        }
+    }
+
+    let mut read = HashSet::new();
+    while let Some(register) = worklist.pop() {
+        if read.insert(register.clone()) {
+            worklist.extend(operands.get(&register).into_iter().flatten().cloned());
+        }
+    }
+
+    for block in &mut cfg.blocks {
+        block.phis.retain(|phi| read.contains(&phi.rd));
+        block.statements.retain(|(statement, _)| {
+            !is_pure(statement, overflow) || statement.defs().iter().any(|rd| read.contains(rd))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::IRStatement,
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    #[test]
+    fn test_eliminate_dead_code() {
+        // Only the string that is printed is left, and the counter that only
+        // counts itself goes with the rest.
+        let ir = get_optimized_ir(
+            "let s = \"x\";
+            let t = \"y\";
+            let u = (s, t);
+            let mut n = parse_int(\"3\")?;
+            let mut i: u32 = 0;
+            while n > 0 {
+                n -= 1;
+                i = wrapping_add(i, 1);
+            };
+            print(s);",
+            &[Pass::Dce],
+            OverflowMode::Trap,
+        );
+        assert_eq!(
+            count(&ir, |statement| matches!(
+                statement,
+                IRStatement::LoadString { .. }
+            )),
+            2
+        );
+        assert!(!ir.statements.iter().any(|statement| matches!(
+            statement,
+            IRStatement::MakeTuple { .. } | IRStatement::Add { .. }
+        )));
+
+        // Overflow is an error unless it wraps, so unused arithmetic that may
+        // overflow is only deleted then.
+        let source = "let n = parse_int(\"3\")?; let m = n * 1000;";
+        let multiplies = |overflow| {
+            let ir = get_optimized_ir(source, &[Pass::Dce], overflow);
+            count(&ir, |statement| {
+                matches!(statement, IRStatement::Multiply { .. })
+            })
+        };
+        assert_eq!(multiplies(OverflowMode::Trap), 1);
+        assert_eq!(multiplies(OverflowMode::Wrap), 0);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::expression::BinaryOperationType,
+    diagnostic::Span,
+    interp::{self, shift_amount, OverflowMode},
+    ir::{cfg::Cfg, IRStatement, Overflow, Register},
+};
+
+// What is known about the value of a register.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    // Nothing yet: it is not written on any path found to run so far.
+    Unknown,
+    Constant(i128),
+    // It can have different values, or ones that are not integers.
+    Varying,
+}
+
+impl Value {
+    fn meet(self, other: Value) -> Value {
+        match (self, other) {
+            (Value::Unknown, value) | (value, Value::Unknown) => value,
+            (Value::Constant(a), Value::Constant(b)) if a == b => Value::Constant(a),
+            _ => Value::Varying,
+        }
+    }
+}
+
+// Replaces instructions whose result is always the same with `li`, and
+// branches whose condition is always the same with jumps, or nothing. The
+// graph must be in SSA form. This is sparse conditional constant propagation
+// by Wegman and Zadeck: code that is never reached does not make values vary,
+// so constants are also found through branches and loops.
+pub fn fold(cfg: &mut Cfg, overflow: OverflowMode) {
+    let mut values: HashMap<Register, Value> = HashMap::new();
+    for block in &cfg.blocks {
+        let phis = block.phis.iter().map(|phi| phi.rd.clone());
+        for register in phis.chain(block.instructions().flat_map(IRStatement::defs)) {
+            values.insert(register, Value::Unknown);
+        }
+    }
+    let mut reached: HashSet<usize> = cfg.entries.iter().copied().collect();
+    let mut edges: HashSet<(usize, usize)> = HashSet::new();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for (i, block) in cfg.blocks.iter().enumerate() {
+            if !reached.contains(&i) {
+                continue;
+            }
+
+            for phi in &block.phis {
+                let value = phi
+                    .sources
+                    .iter()
+                    .filter(|(predecessor, _)| edges.contains(&(*predecessor, i)))
+                    .map(|(_, source)| get(&values, source))
+                    .fold(Value::Unknown, Value::meet);
+                changed |= update(&mut values, &phi.rd, value);
+            }
+
+            for (statement, _) in &block.statements {
+                let value = evaluate(statement, &values, overflow);
+                for register in statement.defs() {
+                    changed |= update(&mut values, &register, value);
+                }
+            }
+
+            let successors = match &block.terminator {
+                Some((IRStatement::BranchZero { rs1, .. }, _))
+                | Some((IRStatement::BranchNotZero { rs1, .. }, _)) => {
+                    match (get(&values, rs1), taken(block.terminator.as_ref())) {
+                        (Value::Unknown, _) => vec![],
+                        (Value::Constant(condition), Some(taken)) => {
+                            let target = cfg.block_of(block.branch_target().unwrap());
+                            if taken(condition) {
+                                vec![target]
+                            } else {
+                                vec![i + 1]
+                            }
+                        }
+                        _ => cfg.successors[i].clone(),
+                    }
+                }
+                _ => cfg.successors[i].clone(),
+            };
+            for successor in successors {
+                if edges.insert((i, successor)) {
+                    reached.insert(successor);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    for (i, block) in cfg.blocks.iter_mut().enumerate() {
+        if !reached.contains(&i) {
+            continue;
+        }
+
+        // Phis that are constant become `li`s at the top of the block.
+        let mut loads = vec![];
+        block.phis.retain(|phi| match get(&values, &phi.rd) {
+            Value::Constant(imm) => {
+                let span = block.label.as_ref().map(|(_, span)| *span);
+                loads.push((
+                    IRStatement::LoadImmediate {
+                        rd: phi.rd.clone(),
+                        imm,
+                    },
+                    span.unwrap_or_default(),
+                ));
+                false
+            }
+            _ => true,
+        });
+        block.statements.splice(0..0, loads);
+
+        for (statement, _) in &mut block.statements {
+            if let [rd] = &statement.defs()[..] {
+                if let Value::Constant(imm) = get(&values, rd) {
+                    *statement = IRStatement::LoadImmediate {
+                        rd: rd.clone(),
+                        imm,
+                    };
+                }
+            }
+        }
+
+        let Some(taken) = taken(block.terminator.as_ref()) else {
+            continue;
+        };
+        let (terminator, span) = block.terminator.as_ref().unwrap();
+        if let Value::Constant(condition) = get(&values, &terminator.uses()[0]) {
+            let label = block.branch_target().unwrap().clone();
+            block.terminator = taken(condition).then_some((IRStatement::Branch { label }, *span));
+        }
+    }
+
+    // Phis lose the sources from branches that were removed.
+    cfg.compute_edges();
+    for (i, block) in cfg.blocks.iter_mut().enumerate() {
+        for phi in &mut block.phis {
+            phi.sources
+                .retain(|(predecessor, _)| cfg.predecessors[i].contains(predecessor));
+        }
+    }
+}
+
+// Lowers what is known about `register` by `value`, and returns true iff
+// that changed anything.
+fn update(values: &mut HashMap<Register, Value>, register: &Register, value: Value) -> bool {
+    let old = get(values, register);
+    let new = old.meet(value);
+    values.insert(register.clone(), new);
+    new != old
+}
+
+fn get(values: &HashMap<Register, Value>, register: &Register) -> Value {
+    // Registers that are read but never written are not constants.
+    values.get(register).copied().unwrap_or(Value::Varying)
+}
+
+// Whether a conditional branch is taken for a condition, if `terminator` is
+// one.
+fn taken(terminator: Option<&(IRStatement, Span)>) -> Option<fn(i128) -> bool> {
+    match terminator {
+        Some((IRStatement::BranchZero { .. }, _)) => Some(|condition| condition == 0),
+        Some((IRStatement::BranchNotZero { .. }, _)) => Some(|condition| condition != 0),
+        _ => None,
+    }
+}
+
+// The value an instruction writes, worked out the same way the interpreter
+// does. Instructions that would fail are left to fail when the program runs.
+fn evaluate(
+    statement: &IRStatement,
+    values: &HashMap<Register, Value>,
+    overflow_mode: OverflowMode,
+) -> Value {
+    match statement {
+        IRStatement::LoadImmediate { imm, .. } => return Value::Constant(*imm),
+        IRStatement::Move { rs1, .. } => return get(values, rs1),
+        _ => {}
+    }
+
+    let operands: Vec<Value> = statement
+        .uses()
+        .iter()
+        .map(|register| get(values, register))
+        .collect();
+    if operands.contains(&Value::Unknown) {
+        return Value::Unknown;
+    }
+    let constants: Vec<i128> = operands
+        .iter()
+        .filter_map(|value| match value {
+            Value::Constant(value) => Some(*value),
+            _ => None,
+        })
+        .collect();
+    let (a, b) = match constants[..] {
+        [a] if operands.len() == 1 => (a, 0),
+        [a, b] if operands.len() == 2 => (a, b),
+        _ => return Value::Varying,
+    };
+
+    let arithmetic = |operation_type, ty, overflow| {
+        if overflow == Overflow::Checked {
+            return None;
+        }
+        interp::arithmetic(operation_type, a, b, ty, overflow, overflow_mode).ok()
+    };
+    let result = match statement {
+        IRStatement::Add { ty, overflow, .. } => {
+            arithmetic(BinaryOperationType::Add, *ty, *overflow)
+        }
+        IRStatement::Subtract { ty, overflow, .. } => {
+            arithmetic(BinaryOperationType::Subtract, *ty, *overflow)
+        }
+        IRStatement::Multiply { ty, overflow, .. } => {
+            arithmetic(BinaryOperationType::Multiply, *ty, *overflow)
+        }
+        IRStatement::Divide { ty, overflow, .. } => {
+            arithmetic(BinaryOperationType::Divide, *ty, *overflow)
+        }
+        IRStatement::Exponentiate { ty, overflow, .. } => {
+            arithmetic(BinaryOperationType::Exponentiate, *ty, *overflow)
+        }
+        IRStatement::Modulus { .. } => a.checked_rem(b),
+        IRStatement::Equal { .. } => Some((a == b) as i128),
+        IRStatement::NotEqual { .. } => Some((a != b) as i128),
+        IRStatement::GreaterEqual { .. } => Some((a >= b) as i128),
+        IRStatement::LessEqual { .. } => Some((a <= b) as i128),
+        IRStatement::Greater { .. } => Some((a > b) as i128),
+        IRStatement::Less { .. } => Some((a < b) as i128),
+        IRStatement::LogicalAnd { .. } => Some((a != 0 && b != 0) as i128),
+        IRStatement::LogicalOr { .. } => Some((a != 0 || b != 0) as i128),
+        IRStatement::LogicalNot { .. } => Some((a == 0) as i128),
+        IRStatement::Negate { ty, .. } => interp::arithmetic(
+            BinaryOperationType::Subtract,
+            0,
+            a,
+            *ty,
+            Overflow::Default,
+            overflow_mode,
+        )
+        .ok(),
+        IRStatement::BitwiseNot { ty, .. } => Some(ty.wrap(!a)),
+        IRStatement::Cast { ty, .. } => Some(ty.wrap(a)),
+        IRStatement::BitwiseAnd { .. } => Some(a & b),
+        IRStatement::BitwiseOr { .. } => Some(a | b),
+        IRStatement::BitwiseXor { .. } => Some(a ^ b),
+        IRStatement::LeftShift { ty, .. } => shift_amount(b, *ty)
+            .ok()
+            .map(|amount| ty.wrap(a.wrapping_shl(amount))),
+        IRStatement::RightShift { ty, .. } => shift_amount(b, *ty).ok().map(|amount| a >> amount),
+        _ => None,
+    };
+
+    result.map_or(Value::Varying, Value::Constant)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::{IRStatement, IR},
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    // The constant the last instruction prints, if it is one.
+    fn printed_constant(ir: &IR) -> Option<i128> {
+        let Some(IRStatement::Print { rs1 }) = ir.statements.last() else {
+            return None;
+        };
+        ir.statements.iter().find_map(|statement| match statement {
+            IRStatement::LoadImmediate { rd, imm } if rd == rs1 => Some(*imm),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_fold() {
+        let ir = get_optimized_ir(
+            "let x = 1 + 2 * 3; print(x);",
+            &[Pass::Fold],
+            OverflowMode::Trap,
+        );
+        assert_eq!(printed_constant(&ir), Some(7));
+        assert_eq!(
+            count(&ir, |statement| matches!(
+                statement,
+                IRStatement::Add { .. } | IRStatement::Multiply { .. }
+            )),
+            0
+        );
+
+        // Overflow is left to fail unless it wraps.
+        let source = "let a: u8 = 200; let b = a + a; print(b);";
+        let ir = get_optimized_ir(source, &[Pass::Fold], OverflowMode::Trap);
+        assert_eq!(printed_constant(&ir), None);
+        let ir = get_optimized_ir(source, &[Pass::Fold], OverflowMode::Wrap);
+        assert_eq!(printed_constant(&ir), Some(144));
+
+        let ir = get_optimized_ir(
+            "let a = 0; let b = 1 / a; print(b);",
+            &[Pass::Fold],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, |s| matches!(s, IRStatement::Divide { .. })), 1);
+    }
+
+    #[test]
+    fn test_fold_branches() {
+        let is_branch = |statement: &IRStatement| {
+            matches!(
+                statement,
+                IRStatement::BranchZero { .. } | IRStatement::BranchNotZero { .. }
+            )
+        };
+
+        let ir = get_optimized_ir(
+            "if 1 { print(1); } else { print(2); };",
+            &[Pass::Fold],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, is_branch), 0);
+
+        // `x` is 1 on both paths, so the second `if` goes, leaving the `?`
+        // and the first `if`.
+        let ir = get_optimized_ir(
+            "let c = parse_int(\"5\")?;
+            let mut x = 0;
+            if c > 3 { x = 1; } else { x = 2 - 1; };
+            if x == 1 { print(c); };",
+            &[Pass::Fold],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, is_branch), 2);
+
+        // The loop runs more than once, so `i` is not constant.
+        let ir = get_optimized_ir(
+            "let mut i = 0; while i < 100 { i += 1; }; print(i);",
+            &[Pass::Fold],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, is_branch), 1);
+    }
+}
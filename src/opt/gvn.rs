@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use crate::{
+    diagnostic::Span,
+    ir::{
+        cfg::{Cfg, Dominators},
+        IRStatement, Register,
+    },
+};
+
+// What an instruction or phi computes, with the register it writes left out
+// and the registers it reads by the value they hold.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Expression {
+    Statement(IRStatement),
+    Phi(usize, Vec<(usize, Register)>),
+}
+
+// Replaces instructions that compute what an instruction in a dominating
+// block already has with a `mov` of its result, for copy propagation to
+// remove. An instruction that fails on some operands can be replaced too, as
+// the one before it has run without failing. The graph must be in SSA form.
+pub fn number_values(cfg: &mut Cfg) {
+    let dominators = cfg.dominators();
+    // The register first written with each value, by the registers written
+    // with it later.
+    let mut values: HashMap<Register, Register> = HashMap::new();
+
+    for entry in cfg.entries.clone() {
+        let mut available: HashMap<Expression, Register> = HashMap::new();
+        number_block(cfg, &dominators, entry, &mut available, &mut values);
+    }
+}
+
+// Numbers the values in `block` and the blocks it dominates, with the
+// expressions computed in the blocks that dominate it available.
+fn number_block(
+    cfg: &mut Cfg,
+    dominators: &Dominators,
+    block: usize,
+    available: &mut HashMap<Expression, Register>,
+    values: &mut HashMap<Register, Register>,
+) {
+    let value = |values: &HashMap<Register, Register>, register: &Register| {
+        values.get(register).cloned().unwrap_or(register.clone())
+    };
+    // What this block makes available, to take away again after the blocks
+    // it dominates.
+    let mut added = vec![];
+
+    // Phis with the same sources as one before them become copies of it.
+    let mut moves = vec![];
+    let phis = std::mem::take(&mut cfg.blocks[block].phis);
+    for phi in phis {
+        let mut sources: Vec<(usize, Register)> = phi
+            .sources
+            .iter()
+            .map(|(predecessor, source)| (*predecessor, value(values, source)))
+            .collect();
+        sources.sort_by_key(|(predecessor, _)| *predecessor);
+        let expression = Expression::Phi(block, sources);
+        match available.get(&expression) {
+            Some(first) => {
+                values.insert(phi.rd.clone(), first.clone());
+                let statement = IRStatement::Move {
+                    rd: phi.rd,
+                    rs1: first.clone(),
+                };
+                moves.push((statement, Span::default()));
+            }
+            None => {
+                available.insert(expression.clone(), phi.rd.clone());
+                added.push(expression);
+                cfg.blocks[block].phis.push(phi);
+            }
+        }
+    }
+    cfg.blocks[block].statements.splice(0..0, moves);
+
+    for (statement, _) in &mut cfg.blocks[block].statements {
+        let Some(expression) = expression(statement, values) else {
+            continue;
+        };
+        let rd = statement.defs().remove(0);
+        match available.get(&expression) {
+            Some(first) => {
+                values.insert(rd.clone(), first.clone());
+                *statement = IRStatement::Move {
+                    rd,
+                    rs1: first.clone(),
+                };
+            }
+            None => {
+                available.insert(expression.clone(), rd);
+                added.push(expression);
+            }
+        }
+    }
+
+    for child in dominators.children[block].clone() {
+        number_block(cfg, dominators, child, available, values);
+    }
+    for expression in added {
+        available.remove(&expression);
+    }
+}
+
+// The expression an instruction computes, if it computes the same thing
+// whenever its operands hold the same values and does nothing else. Maps can
+// change, so nothing that reads them counts, and each new map is a different
+// one, as are the copies closures make of the maps they capture.
+fn expression(statement: &IRStatement, values: &HashMap<Register, Register>) -> Option<Expression> {
+    let numbered = matches!(
+        statement,
+        IRStatement::LoadImmediate { .. }
+            | IRStatement::Add { .. }
+            | IRStatement::Subtract { .. }
+            | IRStatement::Multiply { .. }
+            | IRStatement::Divide { .. }
+            | IRStatement::Modulus { .. }
+            | IRStatement::Exponentiate { .. }
+            | IRStatement::Equal { .. }
+            | IRStatement::NotEqual { .. }
+            | IRStatement::GreaterEqual { .. }
+            | IRStatement::LessEqual { .. }
+            | IRStatement::Greater { .. }
+            | IRStatement::Less { .. }
+            | IRStatement::LogicalAnd { .. }
+            | IRStatement::LogicalOr { .. }
+            | IRStatement::LogicalNot { .. }
+            | IRStatement::Negate { .. }
+            | IRStatement::BitwiseNot { .. }
+            | IRStatement::Cast { .. }
+            | IRStatement::BitwiseAnd { .. }
+            | IRStatement::BitwiseOr { .. }
+            | IRStatement::BitwiseXor { .. }
+            | IRStatement::LeftShift { .. }
+            | IRStatement::RightShift { .. }
+            | IRStatement::MakeVariant { .. }
+            | IRStatement::LoadTag { .. }
+            | IRStatement::LoadField { .. }
+            | IRStatement::LoadString { .. }
+            | IRStatement::MakeTuple { .. }
+            | IRStatement::ParseInteger { .. }
+    );
+    if !numbered {
+        return None;
+    }
+
+    let mut statement = statement.clone();
+    let (defs, uses) = statement.registers_mut();
+    for register in defs {
+        *register = Register(u32::MAX);
+    }
+    for register in uses {
+        if let Some(value) = values.get(register) {
+            *register = value.clone();
+        }
+    }
+
+    // `a > b` is `b < a`, and the order of the operands of `a + b` does not
+    // matter.
+    let statement = match statement {
+        IRStatement::Greater { rd, rs1, rs2 } => IRStatement::Less {
+            rd,
+            rs1: rs2,
+            rs2: rs1,
+        },
+        IRStatement::GreaterEqual { rd, rs1, rs2 } => IRStatement::LessEqual {
+            rd,
+            rs1: rs2,
+            rs2: rs1,
+        },
+        mut statement => {
+            if let IRStatement::Add { rs1, rs2, .. }
+            | IRStatement::Multiply { rs1, rs2, .. }
+            | IRStatement::Equal { rs1, rs2, .. }
+            | IRStatement::NotEqual { rs1, rs2, .. }
+            | IRStatement::LogicalAnd { rs1, rs2, .. }
+            | IRStatement::LogicalOr { rs1, rs2, .. }
+            | IRStatement::BitwiseAnd { rs1, rs2, .. }
+            | IRStatement::BitwiseOr { rs1, rs2, .. }
+            | IRStatement::BitwiseXor { rs1, rs2, .. } = &mut statement
+            {
+                if rs2.0 < rs1.0 {
+                    std::mem::swap(rs1, rs2);
+                }
+            }
+            statement
+        }
+    };
+    Some(Expression::Statement(statement))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::IRStatement,
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    #[test]
+    fn test_number_values() {
+        let is_multiply =
+            |statement: &IRStatement| matches!(statement, IRStatement::Multiply { .. });
+
+        // `b * a` is `a * b`, and `a > b` is `b < a`.
+        let ir = get_optimized_ir(
+            "let a = parse_int(\"6\")?;
+            let b = parse_int(\"7\")?;
+            print(a * b + b * a);
+            print(a > b);
+            print(b < a);",
+            &[Pass::Gvn],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, is_multiply), 1);
+        assert_eq!(
+            count(&ir, |statement| matches!(
+                statement,
+                IRStatement::Less { .. } | IRStatement::Greater { .. }
+            )),
+            1
+        );
+
+        // Neither arm runs before the other.
+        let ir = get_optimized_ir(
+            "let a = parse_int(\"6\")?;
+            if a > 3 { print(a * a); } else { print(a * a + 1); };",
+            &[Pass::Gvn],
+            OverflowMode::Trap,
+        );
+        assert_eq!(count(&ir, is_multiply), 2);
+
+        // The map changes in between.
+        let ir = get_optimized_ir(
+            "let mut m = {};
+            m[1] = 1;
+            print(m[1]);
+            m[1] = 2;
+            print(m[1]);",
+            &[Pass::Gvn],
+            OverflowMode::Trap,
+        );
+        assert_eq!(
+            count(&ir, |statement| matches!(
+                statement,
+                IRStatement::MapGet { .. }
+            )),
+            2
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::{
+    interp::OverflowMode,
+    ir::{
+        cfg::{Cfg, Loop},
+        IRStatement, Overflow, Register,
+    },
+    typeck::types::IntegerType,
+};
+
+// A phi in a loop header that changes by the same amount on every
+// iteration.
+pub struct InductionVariable {
+    pub phi: Register,
+    // The value from before the loop.
+    pub initial: Register,
+    // The value for the next iteration, from every latch.
+    pub next: Register,
+    pub step: Register,
+    // Whether the step is subtracted rather than added.
+    pub subtract: bool,
+    pub ty: IntegerType,
+    // Whether stepping wraps instead of failing on overflow, so that the
+    // value may not only go one way.
+    pub wraps: bool,
+}
+
+// Where each register is written: the block, and the index of the statement
+// or None for a phi.
+pub type Definitions = HashMap<Register, (usize, Option<usize>)>;
+
+pub fn definitions(cfg: &Cfg) -> Definitions {
+    let mut definitions = HashMap::new();
+    for (i, block) in cfg.blocks.iter().enumerate() {
+        for phi in &block.phis {
+            definitions.insert(phi.rd.clone(), (i, None));
+        }
+        for (j, (statement, _)) in block.statements.iter().enumerate() {
+            for register in statement.defs() {
+                definitions.insert(register, (i, Some(j)));
+            }
+        }
+    }
+    definitions
+}
+
+// The value of every register that is only written by `li`, which is every
+// such register in SSA form.
+pub fn constants(cfg: &Cfg) -> HashMap<Register, i128> {
+    cfg.blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(|(statement, _)| match statement {
+            IRStatement::LoadImmediate { rd, imm } => Some((rd.clone(), *imm)),
+            _ => None,
+        })
+        .collect()
+}
+
+// The induction variables of a loop with a preheader, in SSA form.
+pub fn induction_variables(
+    cfg: &Cfg,
+    l: &Loop,
+    preheader: usize,
+    definitions: &Definitions,
+    overflow: OverflowMode,
+) -> Vec<InductionVariable> {
+    let invariant = |register: &Register| {
+        definitions
+            .get(register)
+            .is_some_and(|(block, _)| l.blocks.binary_search(block).is_err())
+    };
+
+    cfg.blocks[l.header]
+        .phis
+        .iter()
+        .filter_map(|phi| {
+            let mut initial = None;
+            let mut latches = vec![];
+            for (predecessor, source) in &phi.sources {
+                if *predecessor == preheader {
+                    initial = Some(source.clone());
+                } else {
+                    latches.push(source);
+                }
+            }
+            // Every latch must give the same next value.
+            let next = (*latches.first()?).clone();
+            if latches.iter().any(|source| **source != next)
+                || phi.sources.len() != cfg.predecessors[l.header].len()
+            {
+                return None;
+            }
+            let initial = initial?;
+
+            let (block, Some(index)) = definitions.get(&next)? else {
+                return None;
+            };
+            let (step, subtract, ty, step_overflow) = match &cfg.blocks[*block].statements[*index].0
+            {
+                IRStatement::Add {
+                    rs1,
+                    rs2,
+                    ty,
+                    overflow,
+                    ..
+                } if *rs1 == phi.rd => (rs2, false, ty, overflow),
+                IRStatement::Add {
+                    rs1,
+                    rs2,
+                    ty,
+                    overflow,
+                    ..
+                } if *rs2 == phi.rd => (rs1, false, ty, overflow),
+                IRStatement::Subtract {
+                    rs1,
+                    rs2,
+                    ty,
+                    overflow,
+                    ..
+                } if *rs1 == phi.rd => (rs2, true, ty, overflow),
+                _ => return None,
+            };
+            let wraps = match step_overflow {
+                Overflow::Wrap => true,
+                Overflow::Default => overflow == OverflowMode::Wrap,
+                // Saturating steps stop stepping evenly.
+                _ => return None,
+            };
+
+            invariant(step).then(|| InductionVariable {
+                phi: phi.rd.clone(),
+                initial,
+                next,
+                step: step.clone(),
+                subtract,
+                ty: *ty,
+                wraps,
+            })
+        })
+        .collect()
+}
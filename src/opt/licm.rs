@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use super::is_pure;
+use crate::{
+    interp::OverflowMode,
+    ir::{cfg::Cfg, IRStatement, Register},
+};
+
+// Moves instructions whose operands do not change in a loop to its
+// preheader, so that they run once instead of on every iteration. Only pure
+// instructions move, as they may not have run at all before. The graph must
+// be in SSA form.
+pub fn hoist_invariants(cfg: &mut Cfg, overflow: OverflowMode) {
+    cfg.insert_preheaders();
+    let dominators = cfg.dominators();
+    let nest = cfg.loops(&dominators);
+
+    // Inner loops go first, so what moves out of them can move on out of the
+    // loops around them.
+    for l in nest.loops.iter().rev() {
+        let Some(preheader) = cfg.preheader(l) else {
+            continue;
+        };
+
+        let mut defined_in: HashMap<Register, usize> = HashMap::new();
+        for (i, block) in cfg.blocks.iter().enumerate() {
+            let phis = block.phis.iter().map(|phi| phi.rd.clone());
+            for register in phis.chain(block.instructions().flat_map(IRStatement::defs)) {
+                defined_in.insert(register, i);
+            }
+        }
+        // Reading a register that is never written fails, which must not
+        // happen before the loop if it would not have in it.
+        let invariant = |defined_in: &HashMap<Register, usize>, register: &Register| {
+            defined_in
+                .get(register)
+                .is_some_and(|block| l.blocks.binary_search(block).is_err())
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in &l.blocks {
+                let mut i = 0;
+                while i < cfg.blocks[block].statements.len() {
+                    let (statement, _) = &cfg.blocks[block].statements[i];
+                    if !is_hoistable(statement, overflow)
+                        || !statement
+                            .uses()
+                            .iter()
+                            .all(|register| invariant(&defined_in, register))
+                    {
+                        i += 1;
+                        continue;
+                    }
+
+                    let hoisted = cfg.blocks[block].statements.remove(i);
+                    for register in hoisted.0.defs() {
+                        defined_in.insert(register, preheader);
+                    }
+                    cfg.blocks[preheader].statements.push(hoisted);
+                    changed = true;
+                }
+            }
+        }
+    }
+}
+
+// Returns true iff the instruction gives the same result wherever it runs
+// in the loop. A new map is a different map each time, and maps can change
+// in the loop, so nothing to do with them moves. That includes closures,
+// which copy the maps they capture.
+fn is_hoistable(statement: &IRStatement, overflow: OverflowMode) -> bool {
+    is_pure(statement, overflow)
+        && !matches!(
+            statement,
+            IRStatement::MakeMap { .. }
+                | IRStatement::Clone { .. }
+                | IRStatement::MakeClosure { .. }
+                | IRStatement::MapContains { .. }
+                | IRStatement::MapKeys { .. }
+                | IRStatement::Length { .. }
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::{cfg::Cfg, IRStatement},
+        opt::{testing::get_optimized_ir, Pass},
+    };
+
+    // The instructions in the loops of the optimised program.
+    fn loop_statements(source: &str) -> Vec<IRStatement> {
+        let cfg = Cfg::new(&get_optimized_ir(source, &[Pass::Licm], OverflowMode::Trap));
+        let nest = cfg.loops(&cfg.dominators());
+        nest.loops
+            .iter()
+            .flat_map(|l| &l.blocks)
+            .flat_map(|block| cfg.blocks[*block].instructions().cloned())
+            .collect()
+    }
+
+    #[test]
+    fn test_hoist_invariants() {
+        let statements = loop_statements(
+            "let n = parse_int(\"10\")?;
+            let s = \"x\";
+            let mut i = 0;
+            while i < n {
+                let t = (s, n);
+                print(t);
+                print(n * 2);
+                i += 1;
+            };",
+        );
+        assert!(!statements.iter().any(|statement| matches!(
+            statement,
+            IRStatement::LoadImmediate { .. } | IRStatement::MakeTuple { .. }
+        )));
+        // `n * 2` may overflow, so it only happens when the loop runs.
+        assert!(statements
+            .iter()
+            .any(|statement| matches!(statement, IRStatement::Multiply { .. })));
+
+        // Each iteration gets a map of its own.
+        let statements = loop_statements(
+            "let mut i = 0;
+            while i < 3 {
+                let mut m = {};
+                m[i] = i;
+                print(len(m));
+                i += 1;
+            };",
+        );
+        assert!(statements
+            .iter()
+            .any(|statement| matches!(statement, IRStatement::MakeMap { .. })));
+    }
+}
@@ -0,0 +1,236 @@
+mod copies;
+mod dce;
+mod fold;
+mod gvn;
+mod induction;
+mod licm;
+mod rotate;
+mod simplify;
+mod strength;
+mod unroll;
+
+use crate::{
+    interp::OverflowMode,
+    ir::{cfg::Cfg, ssa, IRStatement, Overflow, IR},
+};
+
+// A pass over the program, named as in `--passes`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Rotate,
+    Fold,
+    Gvn,
+    Copies,
+    Dce,
+    Simplify,
+    Licm,
+    Strength,
+    Unroll,
+    // Unrolls loops that run any number of times too.
+    PartialUnroll,
+}
+
+// The form a pass needs the graph in.
+#[derive(PartialEq)]
+enum Form {
+    Ssa,
+    NotSsa,
+    Either,
+}
+
+impl Pass {
+    fn form(self) -> Form {
+        match self {
+            Pass::Rotate => Form::NotSsa,
+            Pass::Dce | Pass::Simplify => Form::Either,
+            _ => Form::Ssa,
+        }
+    }
+
+    fn run(self, cfg: &mut Cfg, overflow: OverflowMode) {
+        match self {
+            Pass::Rotate => rotate::rotate_loops(cfg),
+            Pass::Fold => fold::fold(cfg, overflow),
+            Pass::Gvn => gvn::number_values(cfg),
+            Pass::Copies => copies::propagate_copies(cfg),
+            Pass::Dce => dce::eliminate_dead_code(cfg, overflow),
+            Pass::Simplify => simplify::simplify_cfg(cfg),
+            Pass::Licm => licm::hoist_invariants(cfg, overflow),
+            Pass::Strength => strength::reduce_strength(cfg, overflow),
+            Pass::Unroll => unroll::unroll_loops(cfg, overflow, false),
+            Pass::PartialUnroll => unroll::unroll_loops(cfg, overflow, true),
+        }
+    }
+
+    fn name(self) -> String {
+        clap::ValueEnum::to_possible_value(&self)
+            .unwrap()
+            .get_name()
+            .to_string()
+    }
+}
+
+// The passes each `-O` level runs. Level 0 leaves the program as it is, and
+// level 2 also unrolls loops that run any number of times.
+pub fn preset(level: u8) -> Vec<Pass> {
+    if level == 0 {
+        return vec![];
+    }
+
+    vec![
+        Pass::Rotate,
+        Pass::Fold,
+        Pass::Gvn,
+        Pass::Copies,
+        // Putting loops back together after SSA form split their back edges
+        // lets the loop passes find their tests.
+        Pass::Dce,
+        Pass::Simplify,
+        Pass::Licm,
+        Pass::Strength,
+        if level >= 2 {
+            Pass::PartialUnroll
+        } else {
+            Pass::Unroll
+        },
+        // Starting values and steps of the new induction variables may fold,
+        // as may the copies of unrolled loops.
+        Pass::Fold,
+        Pass::Gvn,
+        Pass::Copies,
+        Pass::Dce,
+        Pass::Simplify,
+        // Branches that are gone leave their conditions to delete.
+        Pass::Dce,
+    ]
+}
+
+#[derive(Default)]
+pub struct Options {
+    pub overflow: OverflowMode,
+    // The passes to print the program after, on stderr.
+    pub print_after: Vec<Pass>,
+    pub print_after_all: bool,
+}
+
+// Rewrites the program to do less work when it runs, without changing what
+// it prints or how it ends. The passes run in order, with the graph put into
+// or out of SSA form for each one that needs it to be.
+pub fn run_passes(ir: &IR, passes: &[Pass], options: &Options) -> IR {
+    if passes.is_empty() {
+        return ir.clone();
+    }
+
+    let mut cfg = Cfg::new(ir);
+    let mut in_ssa = false;
+    for &pass in passes {
+        match pass.form() {
+            Form::Ssa if !in_ssa => {
+                ssa::to_ssa(&mut cfg);
+                in_ssa = true;
+            }
+            Form::NotSsa if in_ssa => {
+                leave_ssa(&mut cfg);
+                in_ssa = false;
+            }
+            _ => {}
+        }
+        pass.run(&mut cfg, options.overflow);
+
+        if options.print_after_all || options.print_after.contains(&pass) {
+            // The flat IR has no phis.
+            let mut cfg = cfg.clone();
+            if in_ssa {
+                leave_ssa(&mut cfg);
+            }
+            eprint!("*** IR after {} ***\n{}", pass.name(), cfg.to_ir());
+        }
+    }
+
+    if in_ssa {
+        leave_ssa(&mut cfg);
+    }
+    cfg.to_ir()
+}
+
+// Leaving SSA form puts blocks on edges that may not need them, which go
+// again.
+fn leave_ssa(cfg: &mut Cfg) {
+    ssa::from_ssa(cfg);
+    simplify::simplify_cfg(cfg);
+}
+
+// Returns true iff running the instruction does nothing but write its
+// result: it cannot fail and changes nothing else. Field loads and map gets
+// fail on the wrong value, so they are only safe where the code before them
+// made sure they do not.
+fn is_pure(statement: &IRStatement, overflow: OverflowMode) -> bool {
+    match statement {
+        IRStatement::Add { overflow: o, .. }
+        | IRStatement::Subtract { overflow: o, .. }
+        | IRStatement::Multiply { overflow: o, .. }
+        | IRStatement::Exponentiate { overflow: o, .. } => {
+            *o != Overflow::Default || overflow == OverflowMode::Wrap
+        }
+        IRStatement::Negate { .. } => overflow == OverflowMode::Wrap,
+        // Dividing by zero is an error unless it is checked.
+        IRStatement::Divide { overflow: o, .. } => *o == Overflow::Checked,
+        IRStatement::LoadImmediate { .. }
+        | IRStatement::Move { .. }
+        | IRStatement::Clone { .. }
+        | IRStatement::Equal { .. }
+        | IRStatement::NotEqual { .. }
+        | IRStatement::GreaterEqual { .. }
+        | IRStatement::LessEqual { .. }
+        | IRStatement::Greater { .. }
+        | IRStatement::Less { .. }
+        | IRStatement::LogicalAnd { .. }
+        | IRStatement::LogicalOr { .. }
+        | IRStatement::LogicalNot { .. }
+        | IRStatement::BitwiseNot { .. }
+        | IRStatement::Cast { .. }
+        | IRStatement::BitwiseAnd { .. }
+        | IRStatement::BitwiseOr { .. }
+        | IRStatement::BitwiseXor { .. }
+        | IRStatement::MakeVariant { .. }
+        | IRStatement::LoadTag { .. }
+        | IRStatement::LoadString { .. }
+        | IRStatement::MakeTuple { .. }
+        | IRStatement::MakeMap { .. }
+        | IRStatement::MapContains { .. }
+        | IRStatement::MapKeys { .. }
+        | IRStatement::Length { .. }
+        | IRStatement::ParseInteger { .. }
+        | IRStatement::MakeClosure { .. } => true,
+        _ => false,
+    }
+}
+
+// What the tests of the passes share.
+#[cfg(test)]
+mod testing {
+    use super::{run_passes, Options, Pass};
+    use crate::{
+        interp::OverflowMode,
+        ir::{testing::get_checked_ir, IRStatement, IR},
+    };
+
+    pub fn optimize(ir: &IR, passes: &[Pass], overflow: OverflowMode) -> IR {
+        let options = Options {
+            overflow,
+            ..Default::default()
+        };
+        run_passes(ir, passes, &options)
+    }
+
+    pub fn get_optimized_ir(source: &str, passes: &[Pass], overflow: OverflowMode) -> IR {
+        optimize(&get_checked_ir(source), passes, overflow)
+    }
+
+    pub fn count(ir: &IR, predicate: impl Fn(&IRStatement) -> bool) -> usize {
+        ir.statements
+            .iter()
+            .filter(|statement| predicate(statement))
+            .count()
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+
+use crate::{
+    diagnostic::Span,
+    ir::{
+        cfg::{BasicBlock, Cfg, Loop},
+        IRStatement, Label,
+    },
+};
+
+// Headers longer than this are not copied.
+const MAX_HEADER_LENGTH: usize = 16;
+
+// Moves the test of loops that test at the top and jump back to it to the
+// bottom, so that each iteration runs one branch instead of two. The test
+// stays at the top too, to skip the loop when it does not run at all. The
+// graph must not be in SSA form, as the test is copied with the registers it
+// writes.
+pub fn rotate_loops(cfg: &mut Cfg) {
+    let dominators = cfg.dominators();
+    let mut pending: HashSet<Label> = cfg
+        .loops(&dominators)
+        .loops
+        .iter()
+        .filter_map(|l| Some(cfg.blocks[l.header].label.as_ref()?.0.clone()))
+        .collect();
+
+    // Rotating one loop moves the blocks of the others, so the loops are
+    // found again each time, and known by their header's label.
+    loop {
+        let dominators = cfg.dominators();
+        let nest = cfg.loops(&dominators);
+        let rotation = nest.loops.iter().find_map(|l| {
+            let (label, _) = cfg.blocks[l.header].label.as_ref()?;
+            if !pending.remove(label) {
+                return None;
+            }
+            Some((l, rotatable(cfg, l)?))
+        });
+        match rotation {
+            Some((l, (stay, exit))) => {
+                let (header, latches) = (l.header, l.latches.clone());
+                rotate(cfg, header, &latches, stay, exit);
+            }
+            None => return,
+        }
+    }
+}
+
+// The successors of the header that stay in and leave the loop, if the
+// header tests whether to leave and every latch jumps back to it.
+fn rotatable(cfg: &Cfg, l: &Loop) -> Option<(usize, usize)> {
+    let header = &cfg.blocks[l.header];
+    if cfg.entries.contains(&l.header)
+        || !header.phis.is_empty()
+        || header.statements.len() > MAX_HEADER_LENGTH
+        || !matches!(
+            header.terminator,
+            Some((IRStatement::BranchZero { .. }, _))
+                | Some((IRStatement::BranchNotZero { .. }, _))
+        )
+    {
+        return None;
+    }
+
+    let (stay, exit) = match cfg.successors[l.header][..] {
+        [a, b] if l.blocks.binary_search(&a).is_ok() && l.blocks.binary_search(&b).is_err() => {
+            (a, b)
+        }
+        [a, b] if l.blocks.binary_search(&b).is_ok() && l.blocks.binary_search(&a).is_err() => {
+            (b, a)
+        }
+        _ => return None,
+    };
+    let jumps_back = l.latches.iter().all(|&latch| {
+        latch != l.header
+            && matches!(
+                cfg.blocks[latch].terminator,
+                Some((IRStatement::Branch { .. }, _))
+            )
+    });
+    (stay != l.header && jumps_back).then_some((stay, exit))
+}
+
+fn rotate(cfg: &mut Cfg, header: usize, latches: &[usize], stay: usize, exit: usize) {
+    let [stay_label, exit_label] = [stay, exit].map(|block| match &cfg.blocks[block].label {
+        Some((label, _)) => label.clone(),
+        None => {
+            let label = cfg.new_label();
+            cfg.blocks[block].label = Some((label.clone(), Span::default()));
+            label
+        }
+    });
+    cfg.compute_edges();
+
+    // The copy of the test branches back when the header would have stayed.
+    let (test, test_span) = cfg.blocks[header].terminator.clone().unwrap();
+    let stays_on_target = cfg.blocks[header].branch_target() == Some(&stay_label);
+    let back = match (test, stays_on_target) {
+        (IRStatement::BranchZero { rs1, .. }, true)
+        | (IRStatement::BranchNotZero { rs1, .. }, false) => IRStatement::BranchZero {
+            rs1,
+            label: stay_label,
+        },
+        (IRStatement::BranchNotZero { rs1, .. }, true)
+        | (IRStatement::BranchZero { rs1, .. }, false) => IRStatement::BranchNotZero {
+            rs1,
+            label: stay_label,
+        },
+        _ => unreachable!(),
+    };
+    let statements = cfg.blocks[header].statements.clone();
+
+    // Later latches first, so that inserting blocks after them does not move
+    // the ones still to do.
+    let mut latches = latches.to_vec();
+    latches.sort_unstable_by(|a, b| b.cmp(a));
+    for latch in latches {
+        let block = &mut cfg.blocks[latch];
+        block.statements.extend(statements.iter().cloned());
+        block.terminator = Some((back.clone(), test_span));
+
+        // Leaving the loop falls through, so it needs a jump unless the exit
+        // is next.
+        if cfg.block_of(&exit_label) != latch + 1 {
+            cfg.insert_block(
+                latch + 1,
+                BasicBlock {
+                    terminator: Some((
+                        IRStatement::Branch {
+                            label: exit_label.clone(),
+                        },
+                        Span::default(),
+                    )),
+                    ..Default::default()
+                },
+            );
+        } else {
+            cfg.compute_edges();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::{cfg::Cfg, IRStatement},
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    #[test]
+    fn test_rotate_loops() {
+        let optimized = get_optimized_ir(
+            "let n = parse_int(\"10\")?;
+            let mut i = 0;
+            while i < n {
+                let mut j = 0;
+                while j < i {
+                    print(j);
+                    j += 1;
+                };
+                i += 1;
+            };",
+            &[Pass::Rotate],
+            OverflowMode::Trap,
+        );
+
+        // Both loops test at the bottom, and nothing jumps.
+        let cfg = Cfg::new(&optimized);
+        let nest = cfg.loops(&cfg.dominators());
+        assert_eq!(nest.loops.len(), 2);
+        for l in &nest.loops {
+            let [latch] = l.latches[..] else {
+                panic!("more than one latch");
+            };
+            assert!(matches!(
+                cfg.blocks[latch].terminator,
+                Some((IRStatement::BranchNotZero { .. }, _))
+            ));
+        }
+        assert_eq!(
+            count(&optimized, |statement| matches!(
+                statement,
+                IRStatement::Branch { .. }
+            )),
+            0
+        );
+    }
+}
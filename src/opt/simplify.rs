@@ -0,0 +1,389 @@
+use std::collections::HashSet;
+
+use crate::{
+    diagnostic::Span,
+    ir::{cfg::Cfg, IRStatement, Label},
+};
+
+// Removes blocks that nothing reaches, makes branches to empty blocks go
+// straight to where those lead, drops jumps to the next block and branches
+// over them, merges blocks with the only block that reaches them, and drops
+// labels that nothing uses, until none of these change anything. Phis are
+// kept right, so the graph can be in SSA form or not.
+pub fn simplify_cfg(cfg: &mut Cfg) {
+    loop {
+        let changed = remove_unreachable_blocks(cfg)
+            | thread_jumps(cfg)
+            | remove_jumps_to_next(cfg)
+            | invert_branches(cfg)
+            | merge_blocks(cfg)
+            | remove_unused_labels(cfg);
+        if !changed {
+            return;
+        }
+    }
+}
+
+fn remove_unreachable_blocks(cfg: &mut Cfg) -> bool {
+    let dominators = cfg.dominators();
+    let keep: Vec<bool> = (0..cfg.blocks.len())
+        .map(|block| dominators.is_reachable(block))
+        .collect();
+    if keep.iter().all(|keep| *keep) {
+        return false;
+    }
+    cfg.retain_blocks(&keep);
+    true
+}
+
+// Returns true iff the block does nothing but go on to its only successor.
+fn is_forwarding(cfg: &Cfg, block: usize) -> bool {
+    let forwarding = &cfg.blocks[block];
+    forwarding.phis.is_empty()
+        && forwarding.statements.is_empty()
+        && matches!(
+            forwarding.terminator,
+            None | Some((IRStatement::Branch { .. }, _))
+        )
+        && cfg.successors[block].len() == 1
+        && !cfg.entries.contains(&block)
+}
+
+// The last of the empty blocks that `block` leads through, or None if it is
+// not empty or they go round in a circle.
+fn last_forwarding(cfg: &Cfg, mut block: usize) -> Option<usize> {
+    let mut seen = HashSet::new();
+    while is_forwarding(cfg, block) && seen.insert(block) {
+        let next = cfg.successors[block][0];
+        if !is_forwarding(cfg, next) {
+            return Some(block);
+        }
+        block = next;
+    }
+    None
+}
+
+fn thread_jumps(cfg: &mut Cfg) -> bool {
+    let mut changed = false;
+
+    for block in 0..cfg.blocks.len() {
+        let Some(label) = cfg.blocks[block].branch_target() else {
+            continue;
+        };
+
+        let Some(skipped) = last_forwarding(cfg, cfg.block_of(label)) else {
+            continue;
+        };
+        let target = cfg.successors[skipped][0];
+
+        // The block takes the place of the skipped one in the phis, which it
+        // cannot do if it already has a place of its own.
+        if !cfg.blocks[target].phis.is_empty() {
+            if cfg.predecessors[target].contains(&block) {
+                continue;
+            }
+            for phi in &mut cfg.blocks[target].phis {
+                let source = phi
+                    .sources
+                    .iter()
+                    .find(|(predecessor, _)| *predecessor == skipped)
+                    .map(|(_, source)| source.clone());
+                if let Some(source) = source {
+                    phi.sources.push((block, source));
+                }
+            }
+        }
+
+        let label = match &cfg.blocks[target].label {
+            Some((label, _)) => label.clone(),
+            None => {
+                let label = cfg.new_label();
+                cfg.blocks[target].label = Some((label.clone(), Span::default()));
+                label
+            }
+        };
+        *cfg.blocks[block].branch_target_mut().unwrap() = label;
+        cfg.compute_edges();
+        changed = true;
+    }
+
+    changed
+}
+
+// Branching to the next block is the same as falling through to it.
+fn remove_jumps_to_next(cfg: &mut Cfg) -> bool {
+    let mut changed = false;
+
+    for block in 0..cfg.blocks.len() {
+        let Some(label) = cfg.blocks[block].branch_target() else {
+            continue;
+        };
+        if cfg.block_of(label) == block + 1 {
+            cfg.blocks[block].terminator = None;
+            changed = true;
+        }
+    }
+
+    if changed {
+        cfg.compute_edges();
+    }
+    changed
+}
+
+// Turns a conditional branch over a jump, like `bnz r1, L1; j L2; L1:`, into
+// the opposite branch to where the jump goes, `bz r1, L2`.
+fn invert_branches(cfg: &mut Cfg) -> bool {
+    let mut changed = false;
+
+    for block in 0..cfg.blocks.len().saturating_sub(2) {
+        let over = block + 1;
+        let (Some(label), Some(target)) = (
+            cfg.blocks[block].branch_target(),
+            cfg.blocks[over].branch_target(),
+        ) else {
+            continue;
+        };
+        let (next, target) = (cfg.block_of(label), cfg.block_of(target));
+        if next != block + 2
+            || !cfg.blocks[block].falls_through()
+            || target == next
+            || cfg.blocks[over].label.is_some()
+            || !is_forwarding(cfg, over)
+            || !matches!(
+                cfg.blocks[over].terminator,
+                Some((IRStatement::Branch { .. }, _))
+            )
+        {
+            continue;
+        }
+
+        let (jump, _) = cfg.blocks[over].terminator.take().unwrap();
+        let IRStatement::Branch { label } = jump else {
+            unreachable!();
+        };
+        let terminator = &mut cfg.blocks[block].terminator.as_mut().unwrap().0;
+        *terminator = match terminator {
+            IRStatement::BranchZero { rs1, .. } => IRStatement::BranchNotZero {
+                rs1: rs1.clone(),
+                label,
+            },
+            IRStatement::BranchNotZero { rs1, .. } => IRStatement::BranchZero {
+                rs1: rs1.clone(),
+                label,
+            },
+            _ => unreachable!(),
+        };
+
+        // The two edges swap over, and the phis with them.
+        for (phis, from, to) in [(next, block, over), (target, over, block)] {
+            for phi in &mut cfg.blocks[phis].phis {
+                for (predecessor, _) in &mut phi.sources {
+                    if *predecessor == from {
+                        *predecessor = to;
+                    }
+                }
+            }
+        }
+        cfg.compute_edges();
+        changed = true;
+    }
+
+    changed
+}
+
+// Appends blocks to the only block that reaches them, when that block always
+// goes on to them. A merged block must come next, or not fall through, so
+// that it does not need to be next.
+fn merge_blocks(cfg: &mut Cfg) -> bool {
+    let mut changed = false;
+
+    let mut block = 0;
+    while block < cfg.blocks.len() {
+        let successor = match cfg.successors[block][..] {
+            [successor] => successor,
+            _ => {
+                block += 1;
+                continue;
+            }
+        };
+
+        let mergeable = matches!(
+            cfg.blocks[block].terminator,
+            None | Some((IRStatement::Branch { .. }, _))
+        ) && successor != block
+            && cfg.predecessors[successor] == [block]
+            && !cfg.entries.contains(&successor)
+            && cfg.blocks[successor].phis.is_empty()
+            && (successor == block + 1 || !cfg.blocks[successor].falls_through());
+        if !mergeable {
+            block += 1;
+            continue;
+        }
+
+        let merged = std::mem::take(&mut cfg.blocks[successor]);
+        cfg.blocks[block].statements.extend(merged.statements);
+        cfg.blocks[block].terminator = merged.terminator;
+        for next in cfg.successors[successor].clone() {
+            for phi in &mut cfg.blocks[next].phis {
+                for (predecessor, _) in &mut phi.sources {
+                    if *predecessor == successor {
+                        *predecessor = block;
+                    }
+                }
+            }
+        }
+
+        let keep: Vec<bool> = (0..cfg.blocks.len()).map(|i| i != successor).collect();
+        cfg.retain_blocks(&keep);
+        if successor < block {
+            block -= 1;
+        }
+        changed = true;
+    }
+
+    changed
+}
+
+// Drops the labels that nothing branches to and no closure starts at, and
+// then the blocks left with nothing in them.
+fn remove_unused_labels(cfg: &mut Cfg) -> bool {
+    let mut changed = false;
+
+    let used: HashSet<Label> = cfg
+        .blocks
+        .iter()
+        .flat_map(|block| {
+            let closures = block
+                .statements
+                .iter()
+                .filter_map(|(statement, _)| match statement {
+                    IRStatement::MakeClosure { label, .. } => Some(label.clone()),
+                    _ => None,
+                });
+            block.branch_target().cloned().into_iter().chain(closures)
+        })
+        .collect();
+    for block in &mut cfg.blocks {
+        if let Some((label, _)) = &block.label {
+            if !used.contains(label) {
+                block.label = None;
+                changed = true;
+            }
+        }
+    }
+    if changed {
+        cfg.compute_edges();
+    }
+
+    // Without a label, only the block before can reach an empty block, by
+    // falling through. It then falls through to the block after instead.
+    let mut block = 0;
+    while block < cfg.blocks.len() {
+        let empty = &cfg.blocks[block];
+        let previous = cfg.predecessors[block].first().copied();
+        let next = cfg.successors[block].first().copied();
+        let removable = empty.label.is_none()
+            && empty.phis.is_empty()
+            && empty.statements.is_empty()
+            && empty.terminator.is_none()
+            && cfg.blocks.len() > 1;
+        let clashes = match (previous, next) {
+            (Some(previous), Some(next)) => {
+                !cfg.blocks[next].phis.is_empty() && cfg.predecessors[next].contains(&previous)
+            }
+            _ => false,
+        };
+        if !removable || clashes {
+            block += 1;
+            continue;
+        }
+
+        if let (Some(previous), Some(next)) = (previous, next) {
+            for phi in &mut cfg.blocks[next].phis {
+                for (predecessor, _) in &mut phi.sources {
+                    if *predecessor == block {
+                        *predecessor = previous;
+                    }
+                }
+            }
+        }
+        let keep: Vec<bool> = (0..cfg.blocks.len()).map(|i| i != block).collect();
+        cfg.retain_blocks(&keep);
+        changed = true;
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::{
+        interp::OverflowMode,
+        ir::IRStatement,
+        opt::{
+            testing::{count, get_optimized_ir},
+            Pass,
+        },
+    };
+
+    #[test]
+    fn test_simplify_cfg() {
+        let ir = get_optimized_ir(
+            "let mut i = parse_int(\"0\")?;
+            while i < 10 {
+                if i == 3 { i += 2; } else { if i == 7 { break; print(i); }; };
+                i += 1;
+            };
+            let f = |x| x + i;
+            print(f(2));",
+            &[Pass::Simplify],
+            OverflowMode::Trap,
+        );
+
+        // The code after `break` is gone.
+        assert_eq!(
+            count(&ir, |statement| matches!(
+                statement,
+                IRStatement::Print { .. }
+            )),
+            1
+        );
+
+        // Every label is used, none comes straight after another or after a
+        // branch to it, and no branch goes to a jump.
+        let mut used = HashSet::new();
+        let mut jumps = HashSet::new();
+        for (i, statement) in ir.statements.iter().enumerate() {
+            match statement {
+                IRStatement::Branch { label }
+                | IRStatement::BranchZero { label, .. }
+                | IRStatement::BranchNotZero { label, .. }
+                | IRStatement::MakeClosure { label, .. } => {
+                    used.insert(label.clone());
+                }
+                IRStatement::Label { label } => {
+                    if let Some(IRStatement::Branch { .. }) = ir.statements.get(i + 1) {
+                        jumps.insert(label.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (i, statement) in ir.statements.iter().enumerate() {
+            if let IRStatement::Label { label } = statement {
+                assert!(used.contains(label), "{} is not used", label);
+                assert!(!jumps.contains(label), "{} is a jump", label);
+                let previous = &ir.statements[i - 1];
+                assert!(!matches!(previous, IRStatement::Label { .. }));
+                if let IRStatement::Branch { label: target }
+                | IRStatement::BranchZero { label: target, .. }
+                | IRStatement::BranchNotZero { label: target, .. } = previous
+                {
+                    assert_ne!(target, label);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+
+use super::induction::{constants, definitions, induction_variables, InductionVariable};
+use crate::{
+    interp::OverflowMode,
+    ir::{
+        cfg::{Cfg, Dominators, Loop, Phi},
+        IRStatement, Overflow, Register,
+    },
+};
+
+// Replaces multiplications of an induction variable by a loop invariant with
+// a new induction variable, which steps by the product instead. The graph
+// must be in SSA form, and loop invariants in preheaders.
+//
+// The additions wrap, so they cannot fail where the multiplication would
+// not have. A multiplication that could fail on overflow is only replaced
+// where a test in the loop keeps it in range.
+pub fn reduce_strength(cfg: &mut Cfg, overflow: OverflowMode) {
+    cfg.insert_preheaders();
+    let dominators = cfg.dominators();
+    let nest = cfg.loops(&dominators);
+
+    for l in nest.loops.iter().rev() {
+        let Some(preheader) = cfg.preheader(l) else {
+            continue;
+        };
+
+        loop {
+            let definitions = definitions(cfg);
+            let constants = constants(cfg);
+            let variables = induction_variables(cfg, l, preheader, &definitions, overflow);
+            let invariant = |register: &Register| {
+                definitions
+                    .get(register)
+                    .is_some_and(|(block, _)| l.blocks.binary_search(block).is_err())
+            };
+
+            let candidate =
+                l.blocks.iter().find_map(|&block| {
+                    cfg.blocks[block].statements.iter().enumerate().find_map(
+                        |(i, (statement, _))| {
+                            let IRStatement::Multiply {
+                                rd,
+                                rs1,
+                                rs2,
+                                ty,
+                                overflow: multiply_overflow,
+                            } = statement
+                            else {
+                                return None;
+                            };
+                            let (variable, factor) = variables.iter().find_map(|variable| {
+                                if variable.phi == *rs1 && invariant(rs2) {
+                                    Some((variable, rs2))
+                                } else if variable.phi == *rs2 && invariant(rs1) {
+                                    Some((variable, rs1))
+                                } else {
+                                    None
+                                }
+                            })?;
+                            let cannot_fail = match multiply_overflow {
+                                Overflow::Wrap => true,
+                                Overflow::Default => {
+                                    overflow == OverflowMode::Wrap
+                                        || in_range(
+                                            cfg,
+                                            l,
+                                            &dominators,
+                                            block,
+                                            variable,
+                                            factor,
+                                            &constants,
+                                        )
+                                }
+                                _ => false,
+                            };
+                            (variable.ty == *ty && cannot_fail)
+                                .then(|| (block, i, rd.clone(), variable, factor.clone()))
+                        },
+                    )
+                });
+            let Some((block, i, rd, variable, factor)) = candidate else {
+                break;
+            };
+
+            let span = cfg.blocks[block].statements[i].1;
+            let next_register = cfg.next_register();
+            let [phi, next, initial, step] =
+                [0, 1, 2, 3].map(|offset| Register(next_register + offset));
+            let ty = variable.ty;
+
+            cfg.blocks[preheader].statements.extend([
+                (
+                    IRStatement::Multiply {
+                        rd: initial.clone(),
+                        rs1: variable.initial.clone(),
+                        rs2: factor.clone(),
+                        ty,
+                        overflow: Overflow::Wrap,
+                    },
+                    span,
+                ),
+                (
+                    IRStatement::Multiply {
+                        rd: step.clone(),
+                        rs1: variable.step.clone(),
+                        rs2: factor,
+                        ty,
+                        overflow: Overflow::Wrap,
+                    },
+                    span,
+                ),
+            ]);
+
+            let sources = cfg.blocks[l.header]
+                .phis
+                .iter()
+                .find(|phi| phi.rd == variable.phi)
+                .unwrap()
+                .sources
+                .iter()
+                .map(|(predecessor, _)| {
+                    let source = if *predecessor == preheader {
+                        initial.clone()
+                    } else {
+                        next.clone()
+                    };
+                    (*predecessor, source)
+                })
+                .collect();
+            cfg.blocks[l.header].phis.push(Phi {
+                rd: phi.clone(),
+                sources,
+            });
+
+            let step_statement = if variable.subtract {
+                IRStatement::Subtract {
+                    rd: next,
+                    rs1: phi.clone(),
+                    rs2: step,
+                    ty,
+                    overflow: Overflow::Wrap,
+                }
+            } else {
+                IRStatement::Add {
+                    rd: next,
+                    rs1: phi.clone(),
+                    rs2: step,
+                    ty,
+                    overflow: Overflow::Wrap,
+                }
+            };
+            let (next_block, next_index) = definitions[&variable.next];
+            let next_index = next_index.unwrap();
+            cfg.blocks[next_block]
+                .statements
+                .insert(next_index + 1, (step_statement, span));
+
+            let i = if next_block == block && next_index < i {
+                i + 1
+            } else {
+                i
+            };
+            cfg.blocks[block].statements[i].0 = IRStatement::Move { rd, rs1: phi };
+        }
+    }
+}
+
+// Returns true iff the variable times `factor` fits its type wherever the
+// multiplication in `block` runs. The variable only goes one way from a
+// constant, and a test on it must have passed to get to the multiplication,
+// either this iteration or at the end of the last one.
+fn in_range(
+    cfg: &Cfg,
+    l: &Loop,
+    dominators: &Dominators,
+    block: usize,
+    variable: &InductionVariable,
+    factor: &Register,
+    constants: &HashMap<Register, i128>,
+) -> bool {
+    let (Some(&initial), Some(&step), Some(&factor)) = (
+        constants.get(&variable.initial),
+        constants.get(&variable.step),
+        constants.get(factor),
+    ) else {
+        return false;
+    };
+    let step = if variable.subtract { -step } else { step };
+    if variable.wraps || step == 0 {
+        return false;
+    }
+
+    let bound = l.blocks.iter().find_map(|&test| {
+        let condition = match &cfg.blocks[test].terminator {
+            Some((IRStatement::BranchZero { rs1, .. }, _))
+            | Some((IRStatement::BranchNotZero { rs1, .. }, _)) => rs1,
+            _ => return None,
+        };
+        let target = cfg.block_of(cfg.blocks[test].branch_target()?);
+        let falls_to = test + 1;
+        let (stay, taken_stays) = match (
+            l.blocks.binary_search(&target).is_ok(),
+            l.blocks.binary_search(&falls_to).is_ok(),
+        ) {
+            (true, false) => (target, true),
+            (false, true) => (falls_to, false),
+            _ => return None,
+        };
+        let branches_on_zero = matches!(
+            cfg.blocks[test].terminator,
+            Some((IRStatement::BranchZero { .. }, _))
+        );
+        // Whether the condition holds when staying in the loop.
+        let holds = taken_stays != branches_on_zero;
+
+        let tested = if stay == l.header && l.latches == [test] {
+            &variable.next
+        } else if cfg.predecessors[stay] == [test] && dominators.dominates(stay, block) {
+            &variable.phi
+        } else {
+            return None;
+        };
+
+        let comparison = cfg
+            .blocks
+            .iter()
+            .flat_map(|block| &block.statements)
+            .find_map(|(statement, _)| match statement {
+                IRStatement::Less { rd, rs1, rs2 }
+                | IRStatement::LessEqual { rd, rs1, rs2 }
+                | IRStatement::Greater { rd, rs1, rs2 }
+                | IRStatement::GreaterEqual { rd, rs1, rs2 }
+                    if rd == condition =>
+                {
+                    Some((statement, rs1, rs2))
+                }
+                _ => None,
+            })?;
+        let (statement, rs1, rs2) = comparison;
+        // `tested < limit` is the same as `limit > tested`.
+        let (limit, flipped) = if rs1 == tested {
+            (constants.get(rs2)?, false)
+        } else if rs2 == tested {
+            (constants.get(rs1)?, true)
+        } else {
+            return None;
+        };
+
+        // The inclusive range `tested` is in when the condition holds, as
+        // (below, limit) or (above, limit).
+        let (below, limit) = match (statement, flipped) {
+            (IRStatement::Less { .. }, false) | (IRStatement::Greater { .. }, true) => {
+                (true, limit - 1)
+            }
+            (IRStatement::LessEqual { .. }, false) | (IRStatement::GreaterEqual { .. }, true) => {
+                (true, *limit)
+            }
+            (IRStatement::Greater { .. }, false) | (IRStatement::Less { .. }, true) => {
+                (false, limit + 1)
+            }
+            _ => (false, *limit),
+        };
+        // Not `tested < limit` is `tested >= limit`, and so on.
+        let (below, limit) = match (holds, below) {
+            (true, _) => (below, limit),
+            (false, true) => (false, limit + 1),
+            (false, false) => (true, limit - 1),
+        };
+
+        // The variable starts at `initial` and goes towards the limit.
+        (below == (step > 0)).then_some(limit)
+    });
+    let Some(limit) = bound else {
+        return false;
+    };
+
+    let (low, high) = if step > 0 {
+        (initial, initial.max(limit))
+    } else {
+        (initial.min(limit), initial)
+    };
+    [low, high]
+        .iter()
+        .all(|value| (variable.ty.min()..=variable.ty.max()).contains(&(value * factor)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::{interpret, Options, OverflowMode},
+        ir::{cfg::Cfg, testing::get_checked_ir, IRStatement, IR},
+        opt::{testing::optimize, Pass},
+    };
+
+    // Induction variables are only found once the copies of them have gone,
+    // and factors once they are hoisted into preheaders.
+    fn get_ir_and_optimized(source: &str, overflow: OverflowMode) -> (IR, IR) {
+        let ir = get_checked_ir(source);
+        let passes = [Pass::Copies, Pass::Licm, Pass::Strength];
+        let optimized = optimize(&ir, &passes, overflow);
+        (ir, optimized)
+    }
+
+    // The multiplications in loops, leaving out those that work out where
+    // new induction variables start and how far they step.
+    fn multiplies(ir: &IR) -> usize {
+        let cfg = Cfg::new(ir);
+        let nest = cfg.loops(&cfg.dominators());
+        nest.loops
+            .iter()
+            .flat_map(|l| &l.blocks)
+            .flat_map(|block| cfg.blocks[*block].instructions())
+            .filter(|statement| matches!(statement, IRStatement::Multiply { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_reduce_strength() {
+        // `i * 7` is 0, 7, 14 and so on, which is never near overflowing.
+        let (ir, optimized) = get_ir_and_optimized(
+            "let mut i = 0;
+            while i < 10 {
+                print(i * 7);
+                i += 1;
+            };",
+            OverflowMode::Trap,
+        );
+        // Where to start and how far to step are worked out before the loop.
+        assert_eq!(multiplies(&optimized), 0);
+        let options = Options::default();
+        assert_eq!(
+            interpret(&ir, &options).unwrap(),
+            interpret(&optimized, &options).unwrap()
+        );
+
+        // Counting down from 200 with a `u8` overflows in the first
+        // iteration, which must still fail.
+        let (_, optimized) = get_ir_and_optimized(
+            "let mut i: u8 = 200;
+            while i > 0 {
+                print(i * 2);
+                i -= 1;
+            };",
+            OverflowMode::Trap,
+        );
+        assert_eq!(multiplies(&optimized), 1);
+
+        // Nothing says where `i` stops, unless it wraps anyway.
+        let source = "let n = parse_int(\"10\")?;
+            let mut i = 0;
+            while i < n {
+                print(i * 7);
+                i += 1;
+            };";
+        let (_, optimized) = get_ir_and_optimized(source, OverflowMode::Trap);
+        assert_eq!(multiplies(&optimized), 1);
+        let (_, optimized) = get_ir_and_optimized(source, OverflowMode::Wrap);
+        assert_eq!(multiplies(&optimized), 0);
+    }
+}
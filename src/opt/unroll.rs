@@ -0,0 +1,535 @@
+use std::collections::{HashMap, HashSet};
+
+use super::induction::{constants, definitions, induction_variables};
+use crate::{
+    ast::expression::BinaryOperationType,
+    diagnostic::Span,
+    interp::{self, OverflowMode},
+    ir::{
+        cfg::{Cfg, Loop, Phi},
+        IRStatement, Label, Overflow, Register,
+    },
+};
+
+// Loops that run at most this many times are unrolled fully, as long as
+// that makes no more than `MAX_UNROLLED_LENGTH` instructions.
+const MAX_TRIP_COUNT: usize = 16;
+const MAX_UNROLLED_LENGTH: usize = 128;
+// How many copies of the body loops get when they are unrolled partially,
+// if they are no longer than `MAX_PARTIAL_LENGTH` instructions.
+const PARTIAL_FACTOR: usize = 4;
+const MAX_PARTIAL_LENGTH: usize = 32;
+
+// Replaces loops that always run the same small number of times with that
+// many copies of their body, and with `partial`, runs other loops' bodies
+// several times per trip around. The graph must be in SSA form, with loops
+// tested at the bottom.
+pub fn unroll_loops(cfg: &mut Cfg, overflow: OverflowMode, partial: bool) {
+    cfg.insert_preheaders();
+    let dominators = cfg.dominators();
+    // Inner loops first, so that outer loops copy them unrolled.
+    let mut pending: Vec<Label> = cfg
+        .loops(&dominators)
+        .loops
+        .iter()
+        .filter_map(|l| Some(cfg.blocks[l.header].label.as_ref()?.0.clone()))
+        .collect();
+
+    // Unrolling one loop moves the blocks of the others, so the loops are
+    // found again each time, and known by their header's label.
+    while let Some(label) = pending.pop() {
+        let dominators = cfg.dominators();
+        let nest = cfg.loops(&dominators);
+        let Some(l) = nest.loops.iter().find(|l| {
+            cfg.blocks[l.header]
+                .label
+                .as_ref()
+                .is_some_and(|(header, _)| *header == label)
+        }) else {
+            continue;
+        };
+        let Some(latch) = latch(cfg, l) else {
+            continue;
+        };
+
+        let length: usize = (l.header..=latch)
+            .map(|block| {
+                let block = &cfg.blocks[block];
+                block.phis.len() + block.statements.len() + block.terminator.iter().len()
+            })
+            .sum();
+        let trip_count = cfg
+            .preheader(l)
+            .and_then(|preheader| trip_count(cfg, l, preheader, latch, overflow))
+            .filter(|trips| *trips <= MAX_TRIP_COUNT && trips * length <= MAX_UNROLLED_LENGTH);
+
+        match trip_count {
+            Some(trips) => unroll(cfg, l.header, latch, trips, true),
+            None if partial && length <= MAX_PARTIAL_LENGTH => {
+                unroll(cfg, l.header, latch, PARTIAL_FACTOR, false)
+            }
+            None => {}
+        }
+    }
+}
+
+// The latch of a loop that can be unrolled: the last of its blocks, which
+// all come one after another from the header, and the only one that leaves
+// the loop, by branching back to the header or falling through.
+fn latch(cfg: &Cfg, l: &Loop) -> Option<usize> {
+    let [latch] = l.latches[..] else {
+        return None;
+    };
+    let contiguous = l.blocks.first() == Some(&l.header)
+        && l.blocks.last() == Some(&latch)
+        && l.blocks.len() == latch - l.header + 1;
+    if !contiguous || cfg.entries.contains(&l.header) || latch + 1 >= cfg.blocks.len() {
+        return None;
+    }
+
+    let header_label = &cfg.blocks[l.header].label.as_ref()?.0;
+    let tests = match &cfg.blocks[latch].terminator {
+        Some((IRStatement::BranchZero { label, .. }, _))
+        | Some((IRStatement::BranchNotZero { label, .. }, _)) => label == header_label,
+        _ => false,
+    };
+    let leaves_once = l.blocks.iter().all(|&block| {
+        cfg.successors[block]
+            .iter()
+            .all(|successor| *successor <= latch || (block == latch && *successor == latch + 1))
+    });
+    (tests && leaves_once).then_some(latch)
+}
+
+// How many times the body of the loop runs, worked out by stepping an
+// induction variable from its start until the test at the bottom fails.
+fn trip_count(
+    cfg: &Cfg,
+    l: &Loop,
+    preheader: usize,
+    latch: usize,
+    overflow: OverflowMode,
+) -> Option<usize> {
+    let definitions = definitions(cfg);
+    let constants = constants(cfg);
+    let (condition, stays_on_zero) = match &cfg.blocks[latch].terminator {
+        Some((IRStatement::BranchZero { rs1, .. }, _)) => (rs1, true),
+        Some((IRStatement::BranchNotZero { rs1, .. }, _)) => (rs1, false),
+        _ => return None,
+    };
+    let (block, Some(index)) = definitions.get(condition)? else {
+        return None;
+    };
+    let comparison = &cfg.blocks[*block].statements[*index].0;
+    let (rs1, rs2) = match comparison {
+        IRStatement::Equal { rs1, rs2, .. }
+        | IRStatement::NotEqual { rs1, rs2, .. }
+        | IRStatement::Less { rs1, rs2, .. }
+        | IRStatement::LessEqual { rs1, rs2, .. }
+        | IRStatement::Greater { rs1, rs2, .. }
+        | IRStatement::GreaterEqual { rs1, rs2, .. } => (rs1, rs2),
+        _ => return None,
+    };
+
+    for variable in induction_variables(cfg, l, preheader, &definitions, overflow) {
+        let (Some(&initial), Some(&step)) = (
+            constants.get(&variable.initial),
+            constants.get(&variable.step),
+        ) else {
+            continue;
+        };
+        // Which operand is the variable, before or after stepping, and the
+        // constant it is compared with.
+        let operand = |register: &Register| {
+            if *register == variable.phi {
+                Some(false)
+            } else if *register == variable.next {
+                Some(true)
+            } else {
+                None
+            }
+        };
+        let (stepped, limit, flipped) = match (operand(rs1), operand(rs2)) {
+            (Some(stepped), None) => (stepped, constants.get(rs2), false),
+            (None, Some(stepped)) => (stepped, constants.get(rs1), true),
+            _ => continue,
+        };
+        let Some(&limit) = limit else {
+            continue;
+        };
+
+        let (operation_type, step_overflow) = (
+            if variable.subtract {
+                BinaryOperationType::Subtract
+            } else {
+                BinaryOperationType::Add
+            },
+            if variable.wraps {
+                Overflow::Wrap
+            } else {
+                Overflow::Default
+            },
+        );
+        let mut value = initial;
+        for trips in 1..=MAX_TRIP_COUNT {
+            // Stepping that fails is left to happen when the program runs.
+            let next = interp::arithmetic(
+                operation_type,
+                value,
+                step,
+                variable.ty,
+                step_overflow,
+                OverflowMode::Trap,
+            )
+            .ok()?;
+            let tested = if stepped { next } else { value };
+            let (a, b) = if flipped {
+                (limit, tested)
+            } else {
+                (tested, limit)
+            };
+            let holds = match comparison {
+                IRStatement::Equal { .. } => a == b,
+                IRStatement::NotEqual { .. } => a != b,
+                IRStatement::Less { .. } => a < b,
+                IRStatement::LessEqual { .. } => a <= b,
+                IRStatement::Greater { .. } => a > b,
+                _ => a >= b,
+            };
+            if holds == stays_on_zero {
+                return Some(trips);
+            }
+            value = next;
+        }
+        return None;
+    }
+
+    None
+}
+
+// Puts `copies - 1` more copies of the loop from `header` to `latch` after
+// it, with registers and labels of their own. Fully unrolled, each copy
+// goes on to the next and the last leaves the loop. Otherwise every copy
+// keeps the test, and the last goes back to the header.
+fn unroll(cfg: &mut Cfg, header: usize, latch: usize, copies: usize, full: bool) {
+    let length = latch - header + 1;
+    let exit = latch + 1;
+    let shift = (copies - 1) * length;
+    // The block in copy `k` that comes from `block`.
+    let index = |k: usize, block: usize| {
+        if k == 0 {
+            block
+        } else {
+            exit + (k - 1) * length + block - header
+        }
+    };
+
+    let exit_label = match &cfg.blocks[exit].label {
+        Some((label, _)) => label.clone(),
+        None => {
+            let label = cfg.new_label();
+            cfg.blocks[exit].label = Some((label.clone(), Span::default()));
+            label
+        }
+    };
+    let header_label = cfg.blocks[header].label.clone().unwrap().0;
+
+    // New registers and labels for each copy, with none for the first, which
+    // is the loop as it is.
+    let mut defined = vec![];
+    for block in &cfg.blocks[header..=latch] {
+        defined.extend(block.phis.iter().map(|phi| phi.rd.clone()));
+        defined.extend(block.instructions().flat_map(IRStatement::defs));
+    }
+    let mut next_register = cfg.next_register();
+    let mut next_label = cfg.new_label().0;
+    let mut registers = vec![HashMap::new()];
+    let mut labels = vec![HashMap::new()];
+    for _ in 1..copies {
+        registers.push(
+            defined
+                .iter()
+                .map(|register| {
+                    next_register += 1;
+                    (register.clone(), Register(next_register - 1))
+                })
+                .collect::<HashMap<_, _>>(),
+        );
+        labels.push(
+            cfg.blocks[header..=latch]
+                .iter()
+                .filter_map(|block| {
+                    next_label += 1;
+                    Some((block.label.as_ref()?.0.clone(), Label(next_label - 1)))
+                })
+                .collect::<HashMap<_, _>>(),
+        );
+    }
+    let rename = |k: usize, register: &Register| {
+        registers[k]
+            .get(register)
+            .cloned()
+            .unwrap_or_else(|| register.clone())
+    };
+    let latch_source = |phi: &Phi| {
+        phi.sources
+            .iter()
+            .find(|(predecessor, _)| *predecessor == latch)
+            .map(|(_, source)| source.clone())
+    };
+
+    let mut clones = vec![];
+    for (k, labels) in labels.iter().enumerate().skip(1) {
+        for block in header..=latch {
+            let mut clone = cfg.blocks[block].clone();
+            if let Some((label, _)) = &mut clone.label {
+                *label = labels[label].clone();
+            }
+
+            // Each copy's header comes only from the copy before, so its
+            // phis are copies of what that one ends with.
+            let moves: Vec<_> = if block == header {
+                clone
+                    .phis
+                    .drain(..)
+                    .filter_map(|phi| {
+                        let statement = IRStatement::Move {
+                            rd: rename(k, &phi.rd),
+                            rs1: rename(k - 1, &latch_source(&phi)?),
+                        };
+                        Some((statement, Span::default()))
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+            for phi in &mut clone.phis {
+                phi.rd = rename(k, &phi.rd);
+                for (predecessor, source) in &mut phi.sources {
+                    *predecessor = index(k, *predecessor);
+                    *source = rename(k, source);
+                }
+            }
+            for statement in clone.instructions_mut() {
+                let (defs, uses) = statement.registers_mut();
+                for register in defs.into_iter().chain(uses) {
+                    *register = rename(k, register);
+                }
+            }
+            if let Some(label) = clone.branch_target_mut() {
+                *label = labels[label].clone();
+            }
+            clone.statements.splice(0..0, moves);
+            clones.push(clone);
+        }
+    }
+
+    // Blocks after the loop move down past the copies.
+    for phi in cfg.blocks.iter_mut().flat_map(|block| &mut block.phis) {
+        for (predecessor, _) in &mut phi.sources {
+            if *predecessor >= exit {
+                *predecessor += shift;
+            }
+        }
+    }
+    cfg.blocks.splice(exit..exit, clones);
+    let latches: Vec<usize> = (0..copies).map(|k| index(k, latch)).collect();
+    let exit = exit + shift;
+
+    let (condition, stays_on_zero) = match &cfg.blocks[latch].terminator {
+        Some((IRStatement::BranchZero { rs1, .. }, _)) => (rs1.clone(), true),
+        Some((IRStatement::BranchNotZero { rs1, .. }, _)) => (rs1.clone(), false),
+        _ => unreachable!(),
+    };
+    let span = cfg.blocks[latch].terminator.as_ref().unwrap().1;
+    for (k, &block) in latches.iter().enumerate() {
+        let rs1 = rename(k, &condition);
+        cfg.blocks[block].terminator = if full {
+            None
+        } else if k + 1 < copies {
+            let label = exit_label.clone();
+            Some((
+                if stays_on_zero {
+                    IRStatement::BranchNotZero { rs1, label }
+                } else {
+                    IRStatement::BranchZero { rs1, label }
+                },
+                span,
+            ))
+        } else {
+            let label = header_label.clone();
+            Some((
+                if stays_on_zero {
+                    IRStatement::BranchZero { rs1, label }
+                } else {
+                    IRStatement::BranchNotZero { rs1, label }
+                },
+                span,
+            ))
+        };
+    }
+
+    // The header is now reached from the last copy, if at all.
+    for phi in &mut cfg.blocks[header].phis {
+        let source = latch_source(phi);
+        phi.sources.retain(|(predecessor, _)| *predecessor != latch);
+        if let (false, Some(source)) = (full, source) {
+            phi.sources
+                .push((latches[copies - 1], rename(copies - 1, &source)));
+        }
+    }
+
+    // The loop is left from the last copy, or from any of them.
+    let exiting: Vec<usize> = if full {
+        vec![copies - 1]
+    } else {
+        (0..copies).collect()
+    };
+    for phi in &mut cfg.blocks[exit].phis {
+        let Some(source) = latch_source(phi) else {
+            continue;
+        };
+        phi.sources.retain(|(predecessor, _)| *predecessor != latch);
+        for &k in &exiting {
+            phi.sources.push((latches[k], rename(k, &source)));
+        }
+    }
+
+    // Whatever reads a value from the loop after it reads the one from the
+    // copy it left, which takes a phi if there are several.
+    let outside = (0..header).chain(exit..cfg.blocks.len());
+    let defined: HashSet<Register> = defined.into_iter().collect();
+    let mut read = HashSet::new();
+    for block in outside.clone() {
+        let block = &cfg.blocks[block];
+        let phis = block
+            .phis
+            .iter()
+            .flat_map(|phi| &phi.sources)
+            .filter(|(predecessor, _)| !latches.contains(predecessor))
+            .map(|(_, source)| source.clone());
+        for register in phis.chain(block.instructions().flat_map(IRStatement::uses)) {
+            if defined.contains(&register) {
+                read.insert(register);
+            }
+        }
+    }
+    let mut read: Vec<Register> = read.into_iter().collect();
+    read.sort_by_key(|register| register.0);
+    let mut after: HashMap<Register, Register> = HashMap::new();
+    for register in read {
+        if let [k] = exiting[..] {
+            after.insert(register.clone(), rename(k, &register));
+            continue;
+        }
+        let rd = Register(next_register);
+        next_register += 1;
+        let sources = exiting
+            .iter()
+            .map(|&k| (latches[k], rename(k, &register)))
+            .collect();
+        cfg.blocks[exit].phis.push(Phi {
+            rd: rd.clone(),
+            sources,
+        });
+        after.insert(register, rd);
+    }
+    for block in outside {
+        let block = &mut cfg.blocks[block];
+        for phi in &mut block.phis {
+            for (predecessor, source) in &mut phi.sources {
+                if !latches.contains(predecessor) {
+                    *source = after.get(source).cloned().unwrap_or(source.clone());
+                }
+            }
+        }
+        for statement in block.instructions_mut() {
+            let (_, uses) = statement.registers_mut();
+            for register in uses {
+                *register = after.get(register).cloned().unwrap_or(register.clone());
+            }
+        }
+    }
+
+    cfg.compute_edges();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        interp::OverflowMode,
+        ir::{cfg::Cfg, IRStatement, IR},
+        opt::{
+            preset,
+            testing::{count, get_optimized_ir},
+        },
+    };
+
+    fn loops(ir: &IR) -> usize {
+        let cfg = Cfg::new(ir);
+        cfg.loops(&cfg.dominators()).loops.len()
+    }
+
+    #[test]
+    fn test_unroll_loops() {
+        // Four trips leave four prints of constants.
+        let optimized = get_optimized_ir(
+            "let mut i = 0;
+            while i < 4 {
+                print(i * i);
+                i += 1;
+            };
+            print(i);",
+            &preset(1),
+            OverflowMode::Trap,
+        );
+        assert_eq!(loops(&optimized), 0);
+        assert!(optimized.statements.iter().all(|statement| matches!(
+            statement,
+            IRStatement::LoadImmediate { .. } | IRStatement::Print { .. }
+        )));
+
+        // Too many trips, and a trip that overflows, which must still fail.
+        let optimized = get_optimized_ir(
+            "let mut i = 0;
+            while i < 100 {
+                print(i);
+                i += 1;
+            };",
+            &preset(1),
+            OverflowMode::Trap,
+        );
+        assert_eq!(loops(&optimized), 1);
+        let optimized = get_optimized_ir(
+            "let mut i: u8 = 250;
+            while i > 0 {
+                print(i);
+                i += 1;
+            };",
+            &preset(1),
+            OverflowMode::Trap,
+        );
+        assert_eq!(loops(&optimized), 1);
+
+        // Only -O2 unrolls loops that run any number of times, into four
+        // copies that each test whether to go on.
+        let source = "let n = parse_int(\"10\")?;
+            let mut i = 0;
+            let mut s = 0;
+            while i < n {
+                s += i;
+                i += 1;
+            };
+            print(s);";
+        let tests = |ir: &IR| {
+            count(ir, |statement| {
+                matches!(statement, IRStatement::Less { .. })
+            })
+        };
+        let optimized = get_optimized_ir(source, &preset(1), OverflowMode::Trap);
+        assert_eq!(tests(&optimized), 2);
+        let optimized = get_optimized_ir(source, &preset(2), OverflowMode::Trap);
+        assert_eq!(loops(&optimized), 1);
+        assert_eq!(tests(&optimized), 5);
+    }
+}
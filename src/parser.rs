@@ -1,16 +1,31 @@
+use std::collections::HashSet;
+
 use crate::ast::block::Block;
 use crate::ast::statement::Statement;
-use crate::lexer::Lexer;
+use crate::diagnostic::Span;
+use crate::lexer::{Lexer, SimpleToken, Token};
 use crate::syntax_error::SyntaxError;
 
 pub struct Parser<'a> {
     pub lexer: Lexer<'a>,
+    // The names of the files imported so far, so that `module::name` can be
+    // told apart from `Enum::Variant`.
+    pub modules: HashSet<String>,
 }
 
 impl Parser<'_> {
     pub fn new<'a>(input_data: &'a str) -> Parser<'a> {
         Parser {
             lexer: Lexer::new(input_data),
+            modules: HashSet::new(),
+        }
+    }
+
+    // A parser for a file whose spans start at `start` in the source map.
+    pub fn with_start<'a>(input_data: &'a str, start: usize) -> Parser<'a> {
+        Parser {
+            lexer: Lexer::with_start(input_data, start),
+            modules: HashSet::new(),
         }
     }
 
@@ -20,14 +35,66 @@ impl Parser<'_> {
             statements.push(statement);
         }
 
-        match self.lexer.get_next_token()? {
-            Some(_) => {
-                return Err(SyntaxError::InvalidToken);
-            }
-            None => {}
+        if self.lexer.get_next_token()?.is_some() {
+            return Err(SyntaxError::InvalidToken);
         }
 
         let block = Block { statements };
         Ok(Some(block))
     }
+
+    // The span from `start` to the end of the last token.
+    pub fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.lexer.position(),
+        }
+    }
+
+    pub fn consume_simple_token(
+        &mut self,
+        expected: SimpleToken,
+    ) -> Result<Option<()>, SyntaxError> {
+        let old_lexer = self.lexer.clone();
+
+        match self.lexer.get_next_token()? {
+            Some(Token::SimpleToken(simple_token)) if simple_token == expected => Ok(Some(())),
+            _ => {
+                self.lexer = old_lexer;
+                Ok(None)
+            }
+        }
+    }
+
+    // `a::b::c`, as the identifiers in it.
+    pub fn get_next_path(&mut self) -> Result<Option<Vec<String>>, SyntaxError> {
+        let mut path = match self.get_next_identifier()? {
+            None => return Ok(None),
+            Some(identifier) => vec![identifier],
+        };
+
+        while self
+            .consume_simple_token(SimpleToken::PathSeparator)?
+            .is_some()
+        {
+            match self.get_next_identifier()? {
+                None => return Err(SyntaxError::NoIdentifierAfterPathSeparator),
+                Some(identifier) => path.push(identifier),
+            }
+        }
+
+        Ok(Some(path))
+    }
+
+    pub fn get_next_identifier(&mut self) -> Result<Option<String>, SyntaxError> {
+        let old_lexer = self.lexer.clone();
+
+        match self.lexer.get_next_token()? {
+            Some(Token::Identifier(identifier)) => Ok(Some(identifier)),
+            _ => {
+                self.lexer = old_lexer;
+                Ok(None)
+            }
+        }
+    }
 }
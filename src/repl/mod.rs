@@ -1,7 +1,9 @@
 use crate::ast::block::Block;
-use crate::interp;
+use crate::diagnostic::Span;
+use crate::interp::{self, Exit};
 use crate::ir;
 use crate::parser::Parser;
+use crate::typeck;
 use std::io::{self, Write};
 
 pub fn start_repl() {
@@ -33,25 +35,53 @@ pub fn start_repl() {
                     panic!();
                 }
             },
-            Err(e) => {
-                println!("Error: {:#?}", e);
+            Err(error) => {
+                let position = p.lexer.position();
+                let diagnostic = error.to_diagnostic(Span {
+                    start: position,
+                    end: position,
+                });
+                println!("{}", diagnostic.render(&input_data, "<repl>"));
                 continue;
             }
         };
 
-        for stmt in ast.statements {
-            megablock.statements.push(stmt);
+        // Lines that fail to check are dropped, so that they don't break the next ones.
+        let mut candidate = megablock.clone();
+        candidate.statements.extend(ast.statements);
+
+        // Lints are not run, since a later line may still fix what they warn
+        // about, e.g. by reading a variable.
+        let errors = typeck::check(&mut candidate);
+        if !errors.is_empty() {
+            for diagnostic in errors {
+                println!("{}", diagnostic.render(&input_data, "<repl>"));
+            }
+            continue;
         }
 
-        let ir = match ir::get_ir(&megablock) {
+        let ir = match ir::get_ir(&candidate) {
             Ok(ir) => ir,
-            Err(e) => {
-                println!("Error: {:#?}", e);
+            Err((error, span)) => {
+                println!(
+                    "{}",
+                    error.to_diagnostic(span).render(&input_data, "<repl>")
+                );
                 continue;
             }
         };
 
-        let result = interp::interpret(&ir);
-        println!("{}", result);
+        // Spans point into the whole program rather than this line, so
+        // runtime errors are shown without a snippet.
+        match interp::interpret(&ir, &interp::Options::default()) {
+            Ok(Exit::Finished(result)) => println!("{}", result),
+            Ok(Exit::Code(code)) => std::process::exit(code),
+            Err(error) => {
+                println!("error: {}", error.kind);
+                continue;
+            }
+        }
+
+        megablock = candidate;
     }
 }
@@ -1,3 +1,7 @@
+use core::fmt;
+
+use crate::diagnostic::{Diagnostic, Span};
+
 #[derive(Debug)]
 pub enum SyntaxError {
     InvalidToken,
@@ -10,6 +14,7 @@ pub enum SyntaxError {
     NoExpressionAfterUnaryOperator,
     NoExpressionAfterBinaryOperator,
     NoExpressionAfterLParen,
+    NoTypeInCast,
     UnmatchedParen,
     UnmatchedBrace,
     StatementWithoutSemicolon,
@@ -28,4 +33,61 @@ pub enum SyntaxError {
     ContinueStatementOutsideLoop,
     BreakStatementOutsideLoop,
     AssignedUndeclaredVariable,
+    NoExpressionInArgumentList,
+    NoIdentifierAfterPathSeparator,
+    NoIdentifierInEnumDeclaration,
+    NoVariantsInEnumDeclaration,
+    NoExpressionInMatch,
+    NoArmsInMatch,
+    NoConditionInMatchGuard,
+    NoFatArrowInMatchArm,
+    NoExpressionInMatchArm,
+    UndefinedFunction,
+    WrongNumberOfArguments,
+    UndefinedEnum,
+    UndefinedEnumVariant,
+    DuplicateEnumVariant,
+    WrongNumberOfVariantFields,
+    MismatchedPatterns,
+    NoIntegerAfterMinusInPattern,
+    NonExhaustiveMatch,
+    NoExpressionInIndex,
+    UnmatchedBracket,
+    NoColonInMapEntry,
+    NoValueInMapEntry,
+    RefutablePatternInLet,
+    WrongNumberOfTupleElements,
+    UnmatchedPipe,
+    NoBodyInClosure,
+    DuplicateParameter,
+    AssignedCapturedVariable,
+    NoTypeInTypeAnnotation,
+    NoColonInMapType,
+    NoResultInFunctionType,
+    NonIntegerCast,
+    NoCommaInResultType,
+    UnmatchedAngleBracket,
+    NoPathInImport,
+    InvalidModuleName,
+    ImportAfterStatement,
+}
+
+// The name of the error in words, e.g. `unmatched brace` for `UnmatchedBrace`.
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = format!("{:?}", self);
+        for (i, c) in name.chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", c.to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxError {
+    pub fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::error(self.to_string(), span, String::new())
+    }
 }
@@ -0,0 +1,468 @@
+use super::{IntegerLiteral, TypeCheck, TypeChecker};
+use crate::{
+    ast::expression::{
+        BinaryOperationType, ClosureBody, Expression, ExpressionKind, Index, Literal, MatchArmBody,
+        UnaryOperationType,
+    },
+    diagnostic::Span,
+    typeck::types::{IntegerType, Type},
+};
+
+impl TypeCheck for Expression {
+    type Output = Type;
+
+    fn check(&mut self, tc: &mut TypeChecker) -> Type {
+        let span = self.span;
+        let ty = match &mut self.kind {
+            ExpressionKind::Literal(Literal::IntegerLiteral(value)) => {
+                let ty = tc.new_integer_variable();
+                tc.integer_literals.push(IntegerLiteral {
+                    value: *value,
+                    ty: ty.clone(),
+                    span,
+                    negated: false,
+                    pattern: false,
+                });
+                ty
+            }
+            ExpressionKind::Literal(Literal::StringLiteral(_)) => Type::String,
+            ExpressionKind::Identifier(identifier) => match tc.get_symbol(identifier) {
+                Some(ty) => ty,
+                None => {
+                    tc.error(
+                        format!("cannot find value `{}` in this scope", identifier),
+                        span,
+                        "not found in this scope".to_string(),
+                    );
+                    tc.new_variable()
+                }
+            },
+            ExpressionKind::BinaryOperation(operation) => {
+                let left = operation.left_expression.check(tc);
+                let right = operation.right_expression.check(tc);
+
+                let left_span = operation.left_expression.span;
+                let right_span = operation.right_expression.span;
+
+                match operation.operation_type {
+                    // Any two values of the same type can be compared.
+                    BinaryOperationType::Equal | BinaryOperationType::NotEqual => {
+                        tc.expect(&left, &right, right_span);
+                        Type::U32
+                    }
+                    BinaryOperationType::GreaterEqual
+                    | BinaryOperationType::LessEqual
+                    | BinaryOperationType::Greater
+                    | BinaryOperationType::Less => {
+                        tc.expect_integer(&left, left_span);
+                        tc.expect(&left, &right, right_span);
+                        Type::U32
+                    }
+                    BinaryOperationType::LogicalAnd | BinaryOperationType::LogicalOr => {
+                        tc.expect_integer(&left, left_span);
+                        tc.expect_integer(&right, right_span);
+                        Type::U32
+                    }
+                    // The exponent is always a `u32` and a shift amount can
+                    // be any integer, whatever the type of the left side.
+                    BinaryOperationType::Exponentiate => {
+                        tc.expect_integer(&left, left_span);
+                        tc.expect(&Type::U32, &right, right_span);
+                        left
+                    }
+                    BinaryOperationType::LeftShift | BinaryOperationType::RightShift => {
+                        tc.expect_integer(&left, left_span);
+                        tc.expect_integer(&right, right_span);
+                        left
+                    }
+                    _ => {
+                        tc.expect_integer(&left, left_span);
+                        tc.expect(&left, &right, right_span);
+                        left
+                    }
+                }
+            }
+            ExpressionKind::UnaryOperation(operation) => {
+                let operand = operation.expression.check(tc);
+                tc.expect_integer(&operand, operation.expression.span);
+
+                match operation.operation_type {
+                    UnaryOperationType::LogicalNot => Type::U32,
+                    UnaryOperationType::Minus => {
+                        if let ExpressionKind::Literal(Literal::IntegerLiteral(_)) =
+                            operation.expression.kind
+                        {
+                            tc.integer_literals.last_mut().unwrap().negated = true;
+                        }
+                        operand
+                    }
+                    UnaryOperationType::BitwiseNot | UnaryOperationType::Plus => operand,
+                }
+            }
+            ExpressionKind::Cast(cast) => {
+                let operand = cast.expression.check(tc);
+                tc.expect_integer(&operand, cast.expression.span);
+
+                let target = tc.get_annotated_type(&cast.type_annotation);
+                if !matches!(target, Type::Integer(_)) {
+                    tc.error(
+                        format!("cannot cast to `{}`", target),
+                        cast.type_annotation.span,
+                        "not an integer type".to_string(),
+                    );
+                }
+                target
+            }
+            ExpressionKind::FunctionCall(call) => {
+                let arguments: Vec<Type> = call
+                    .arguments
+                    .iter_mut()
+                    .map(|argument| argument.check(tc))
+                    .collect();
+
+                // Variables shadow builtins of the same name.
+                if let Some(function) = tc.get_symbol(&call.function_name) {
+                    check_call(tc, &function, &call.arguments, span)
+                } else {
+                    // `remove` modifies the map, like assigning to an element of it.
+                    if call.function_name == "remove" {
+                        if let Some(mut map) = call.arguments.first() {
+                            while let ExpressionKind::Index(inner) = &map.kind {
+                                map = &inner.expression;
+                            }
+                            if let ExpressionKind::Identifier(identifier) = &map.kind {
+                                tc.assign(identifier, span);
+                            }
+                        }
+                    }
+                    check_builtin_call(tc, &call.function_name, &arguments, span)
+                }
+            }
+            ExpressionKind::Call(call) => {
+                let function = call.callee.check(tc);
+                for argument in &mut call.arguments {
+                    argument.check(tc);
+                }
+
+                check_call(tc, &function, &call.arguments, span)
+            }
+            ExpressionKind::EnumVariant(variant) => {
+                let arguments: Vec<Type> = variant
+                    .arguments
+                    .iter_mut()
+                    .map(|argument| argument.check(tc))
+                    .collect();
+
+                match tc.get_variant(&variant.enum_name, &variant.variant_name, span) {
+                    None => Type::Enum(variant.enum_name.clone()),
+                    Some((enum_type, fields)) => {
+                        // A wrong number of fields is reported when the variant is lowered.
+                        for ((field, argument), expression) in
+                            fields.iter().zip(&arguments).zip(&variant.arguments)
+                        {
+                            tc.expect(field, argument, expression.span);
+                        }
+                        enum_type
+                    }
+                }
+            }
+            ExpressionKind::Try(_try) => {
+                let operand = _try.expression.check(tc);
+                let ok = tc.new_variable();
+                let err = tc.new_variable();
+                tc.expect(
+                    &Type::Result(Box::new(ok.clone()), Box::new(err.clone())),
+                    &operand,
+                    _try.expression.span,
+                );
+
+                // The error is returned as it is, so the closure has to
+                // return a `Result` with the same error type.
+                if let Some(return_type) = tc.return_types.last().cloned() {
+                    let value = tc.new_variable();
+                    let expected = Type::Result(Box::new(value), Box::new(err));
+                    if !tc.substitution.unify(&return_type, &expected) {
+                        let return_type = tc.substitution.apply(&return_type);
+                        let expected = tc.substitution.apply(&expected);
+                        tc.error(
+                            "the `?` operator cannot be used here".to_string(),
+                            span,
+                            format!(
+                                "the closure returns `{}`, which is not `{}`",
+                                return_type, expected
+                            ),
+                        );
+                    }
+                }
+
+                ok
+            }
+            ExpressionKind::Match(_match) => {
+                let scrutinee = _match.expression.check(tc);
+                let result = tc.new_variable();
+
+                for arm in &mut _match.arms {
+                    tc.enter_scope();
+                    tc.check_pattern(&arm.pattern, &scrutinee, false, None);
+
+                    if let Some(guard) = &mut arm.guard {
+                        let guard_type = guard.check(tc);
+                        tc.expect_integer(&guard_type, guard.span);
+                    }
+
+                    match &mut arm.body {
+                        MatchArmBody::Expression(expression) => {
+                            let body = expression.check(tc);
+                            tc.expect(&result, &body, expression.span);
+                        }
+                        MatchArmBody::Block(block) => {
+                            block.check(tc);
+                            tc.expect(&result, &Type::U32, span);
+                        }
+                    }
+
+                    tc.exit_scope();
+                }
+
+                result
+            }
+            ExpressionKind::MapLiteral(map_literal) => {
+                let key = tc.new_variable();
+                let value = tc.new_variable();
+
+                for (key_expression, value_expression) in &mut map_literal.entries {
+                    let key_type = key_expression.check(tc);
+                    tc.expect(&key, &key_type, key_expression.span);
+                    tc.map_keys.push((key_type, key_expression.span));
+                    let value_type = value_expression.check(tc);
+                    tc.expect(&value, &value_type, value_expression.span);
+                }
+
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            ExpressionKind::Index(index) => tc.check_index(index, span),
+            ExpressionKind::Tuple(tuple) => Type::Tuple(
+                tuple
+                    .elements
+                    .iter_mut()
+                    .map(|element| element.check(tc))
+                    .collect(),
+            ),
+            ExpressionKind::Closure(closure) => {
+                tc.enter_scope();
+
+                // A closure with a block body returns 0, like statements do.
+                let result = match closure.body {
+                    ClosureBody::Expression(_) => tc.new_variable(),
+                    ClosureBody::Block(_) => Type::U32,
+                };
+                tc.return_types.push(result.clone());
+
+                let parameters: Vec<Type> = closure
+                    .parameters
+                    .iter()
+                    .map(|parameter| {
+                        let ty = tc.new_variable();
+                        tc.declare(parameter, ty.clone(), false, span);
+                        ty
+                    })
+                    .collect();
+
+                match &mut closure.body {
+                    ClosureBody::Expression(expression) => {
+                        let body = expression.check(tc);
+                        tc.expect(&result, &body, expression.span);
+                    }
+                    ClosureBody::Block(block) => block.check(tc),
+                }
+
+                tc.return_types.pop();
+                tc.exit_scope();
+                Type::Function(parameters, Box::new(result))
+            }
+        };
+
+        self.ty = Some(ty.clone());
+        ty
+    }
+}
+
+impl TypeChecker {
+    pub fn check_index(&mut self, index: &mut Index, span: Span) -> Type {
+        let map = index.expression.check(self);
+        let key = index.index.check(self);
+        self.map_keys.push((key.clone(), index.index.span));
+
+        match self.substitution.apply(&map) {
+            Type::Map(..) | Type::Variable(_) => {}
+            found => {
+                self.error(
+                    format!("cannot index into a value of type `{}`", found),
+                    index.expression.span,
+                    "not a map".to_string(),
+                );
+                return self.new_variable();
+            }
+        }
+
+        let value = self.new_variable();
+        self.expect(
+            &map,
+            &Type::Map(Box::new(key.clone()), Box::new(value.clone())),
+            span,
+        );
+        value
+    }
+}
+
+// `arguments` have already been checked.
+fn check_call(tc: &mut TypeChecker, function: &Type, arguments: &[Expression], span: Span) -> Type {
+    match tc.substitution.apply(function) {
+        Type::Function(parameters, _) if parameters.len() != arguments.len() => {
+            tc.error(
+                format!(
+                    "this function takes {} arguments but {} were supplied",
+                    parameters.len(),
+                    arguments.len()
+                ),
+                span,
+                format!("expected {} arguments", parameters.len()),
+            );
+            tc.new_variable()
+        }
+        Type::Function(parameters, result) => {
+            for (parameter, argument) in parameters.iter().zip(arguments) {
+                tc.expect(parameter, argument.ty.as_ref().unwrap(), argument.span);
+            }
+            *result
+        }
+        Type::Variable(_) => {
+            let result = tc.new_variable();
+            let parameters = arguments
+                .iter()
+                .map(|argument| argument.ty.clone().unwrap())
+                .collect();
+            tc.expect(
+                function,
+                &Type::Function(parameters, Box::new(result.clone())),
+                span,
+            );
+            result
+        }
+        found => {
+            tc.error(
+                format!("expected function, found `{}`", found),
+                span,
+                "not a function".to_string(),
+            );
+            tc.new_variable()
+        }
+    }
+}
+
+fn check_builtin_call(
+    tc: &mut TypeChecker,
+    function_name: &str,
+    arguments: &[Type],
+    span: Span,
+) -> Type {
+    let arity = match function_name {
+        "print" | "keys" | "len" => 1,
+        "ok" | "err" | "parse_int" => 1,
+        "assert" | "panic" | "exit" => 1,
+        "contains" | "remove" | "assert_eq" => 2,
+        "wrapping_add" | "wrapping_sub" | "wrapping_mul" | "wrapping_pow" | "saturating_add"
+        | "saturating_sub" | "saturating_mul" | "saturating_pow" | "checked_add"
+        | "checked_sub" | "checked_mul" | "checked_div" | "checked_pow" => 2,
+        _ => {
+            tc.error(
+                format!("cannot find function `{}` in this scope", function_name),
+                span,
+                "not found in this scope".to_string(),
+            );
+            return tc.new_variable();
+        }
+    };
+
+    if arguments.len() != arity {
+        tc.error(
+            format!(
+                "this function takes {} arguments but {} were supplied",
+                arity,
+                arguments.len()
+            ),
+            span,
+            format!("expected {} arguments", arity),
+        );
+        return tc.new_variable();
+    }
+
+    let key = tc.new_variable();
+    let value = tc.new_variable();
+    let map = Type::Map(Box::new(key.clone()), Box::new(value));
+
+    match function_name {
+        "print" => Type::U32,
+        "len" => {
+            match tc.substitution.apply(&arguments[0]) {
+                Type::Map(..) | Type::String | Type::Variable(_) => {}
+                found => tc.error(
+                    format!("`len` expects a map or a string, found `{}`", found),
+                    span,
+                    "expected a map or a string".to_string(),
+                ),
+            }
+            Type::U32
+        }
+        "keys" => {
+            tc.expect(&map, &arguments[0], span);
+            Type::Map(Box::new(Type::U32), Box::new(key))
+        }
+        "contains" | "remove" => {
+            tc.expect(&map, &arguments[0], span);
+            tc.expect(&key, &arguments[1], span);
+            Type::U32
+        }
+        "assert" => {
+            tc.expect_integer(&arguments[0], span);
+            Type::U32
+        }
+        "assert_eq" => {
+            tc.expect(&arguments[0], &arguments[1], span);
+            Type::U32
+        }
+        // These never return, so the call can be used as any type.
+        "panic" => {
+            tc.expect(&Type::String, &arguments[0], span);
+            tc.new_variable()
+        }
+        "exit" => {
+            tc.expect(&Type::Integer(IntegerType::I32), &arguments[0], span);
+            tc.new_variable()
+        }
+        "ok" => Type::Result(Box::new(arguments[0].clone()), Box::new(tc.new_variable())),
+        "err" => Type::Result(Box::new(tc.new_variable()), Box::new(arguments[0].clone())),
+        // Fails with a message if the string is not a number of the type.
+        "parse_int" => {
+            tc.expect(&Type::String, &arguments[0], span);
+            let integer = tc.new_integer_variable();
+            Type::Result(Box::new(integer), Box::new(Type::String))
+        }
+        // Like the operators, except for what happens on overflow.
+        // `checked_*` returns an error message instead of overflowing.
+        _ => {
+            tc.expect_integer(&arguments[0], span);
+            if function_name.ends_with("_pow") {
+                tc.expect(&Type::U32, &arguments[1], span);
+            } else {
+                tc.expect(&arguments[0], &arguments[1], span);
+            }
+
+            if function_name.starts_with("checked_") {
+                Type::Result(Box::new(arguments[0].clone()), Box::new(Type::String))
+            } else {
+                arguments[0].clone()
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{
+        block::Block,
+        expression::{ClosureBody, Expression, ExpressionKind, MatchArmBody},
+        pattern::{Pattern, PatternKind},
+        statement::{AssignmentTarget, Statement},
+    },
+    diagnostic::{Diagnostic, Span},
+};
+
+// Checks that every binding is assigned on every path that reaches a use of
+// it, and that an immutable binding declared without a value is assigned at
+// most once. This is a forward dataflow analysis over the control flow of the
+// program: the set of initialized bindings is intersected where paths join
+// after `if`, `match` and loops, and the set of possibly assigned ones is
+// united.
+pub fn check_initialization(program: &Block) -> Vec<Diagnostic> {
+    let mut checker = InitializationChecker::default();
+    let mut state = State::default();
+    checker.check_block(program, &mut state);
+    checker.diagnostics
+}
+
+// The bindings that are definitely initialized at a point in the program.
+#[derive(Clone, Default)]
+struct State {
+    initialized: HashSet<usize>,
+    // The bindings assigned on some path to this point.
+    assigned: HashSet<usize>,
+    // Code after `break` and `continue` is never run, so it places no
+    // restrictions on the paths it joins.
+    unreachable: bool,
+}
+
+impl State {
+    fn join(self, other: State) -> State {
+        if self.unreachable {
+            return other;
+        }
+        if other.unreachable {
+            return self;
+        }
+
+        State {
+            initialized: self
+                .initialized
+                .intersection(&other.initialized)
+                .copied()
+                .collect(),
+            assigned: self.assigned.union(&other.assigned).copied().collect(),
+            unreachable: false,
+        }
+    }
+
+    fn unreachable() -> State {
+        State {
+            initialized: HashSet::new(),
+            assigned: HashSet::new(),
+            unreachable: true,
+        }
+    }
+}
+
+// The states at the `break`s and `continue`s of a loop.
+struct LoopStates {
+    breaks: State,
+    continues: State,
+}
+
+#[derive(Default)]
+struct InitializationChecker {
+    // Where each binding was declared, indexed by its id.
+    declarations: Vec<Span>,
+    // The immutable bindings declared without a value, which can be assigned
+    // once.
+    assigned_once: HashSet<usize>,
+    scopes: Vec<HashMap<String, usize>>,
+    loops: Vec<LoopStates>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl InitializationChecker {
+    fn declare(&mut self, identifier: &str, span: Span) -> usize {
+        self.declarations.push(span);
+        let id = self.declarations.len() - 1;
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(identifier.to_string(), id);
+        id
+    }
+
+    fn get_binding(&self, identifier: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(identifier))
+            .copied()
+    }
+
+    // The bindings of a `let` are declared by the whole statement,
+    // `declaration`, and those of a match arm by their own patterns.
+    fn bind_pattern(&mut self, pattern: &Pattern, state: &mut State, declaration: Option<Span>) {
+        match &pattern.kind {
+            PatternKind::Binding(identifier) => {
+                let id = self.declare(identifier, declaration.unwrap_or(pattern.span));
+                state.initialized.insert(id);
+            }
+            PatternKind::Tuple(elements) => {
+                for element in elements {
+                    self.bind_pattern(element, state, declaration);
+                }
+            }
+            PatternKind::Variant(variant_pattern) => {
+                for field in &variant_pattern.fields {
+                    self.bind_pattern(field, state, declaration);
+                }
+            }
+            PatternKind::Wildcard | PatternKind::IntegerLiteral(_) => {}
+        }
+    }
+
+    fn use_binding(&mut self, identifier: &str, state: &State, span: Span) {
+        let id = match self.get_binding(identifier) {
+            Some(id) => id,
+            // Undefined names and builtins are reported elsewhere.
+            None => return,
+        };
+
+        if !state.unreachable && !state.initialized.contains(&id) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("used binding `{}` is possibly uninitialized", identifier),
+                    span,
+                    format!(
+                        "`{}` used here but it is possibly uninitialized",
+                        identifier
+                    ),
+                )
+                .with_secondary_label(
+                    self.declarations[id],
+                    "binding declared here but left uninitialized".to_string(),
+                ),
+            );
+        }
+    }
+
+    fn check_block(&mut self, block: &Block, state: &mut State) {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.check_statement(statement, state);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_statement(&mut self, statement: &Statement, state: &mut State) {
+        match statement {
+            Statement::LetStatement(statement) => match &statement.expression {
+                Some(expression) => {
+                    self.check_expression(expression, state);
+                    self.bind_pattern(&statement.pattern, state, Some(statement.span));
+                }
+                None => {
+                    if let PatternKind::Binding(identifier) = &statement.pattern.kind {
+                        let id = self.declare(identifier, statement.span);
+                        if !statement.mutable {
+                            self.assigned_once.insert(id);
+                        }
+                    }
+                }
+            },
+            Statement::Assignment(statement) => {
+                self.check_expression(&statement.expression, state);
+                self.check_assignment_target(&statement.target, state, statement.span);
+            }
+            Statement::IfStatement(statement) => {
+                // The state when every condition so far was false.
+                let mut otherwise = state.clone();
+                let mut after = State::unreachable();
+
+                for condition_with_block in
+                    std::iter::once(&statement._if).chain(&statement.else_if)
+                {
+                    self.check_expression(&condition_with_block.condition, &mut otherwise);
+                    let mut branch = otherwise.clone();
+                    self.check_block(&condition_with_block.block, &mut branch);
+                    after = after.join(branch);
+                }
+
+                if let Some(block) = &statement._else {
+                    self.check_block(block, &mut otherwise);
+                }
+
+                *state = after.join(otherwise);
+            }
+            Statement::BreakStatement(_) => {
+                if let Some(states) = self.loops.last_mut() {
+                    states.breaks = std::mem::take(&mut states.breaks).join(state.clone());
+                }
+                *state = State::unreachable();
+            }
+            Statement::ContinueStatement(_) => {
+                if let Some(states) = self.loops.last_mut() {
+                    states.continues = std::mem::take(&mut states.continues).join(state.clone());
+                }
+                *state = State::unreachable();
+            }
+            Statement::LoopStatement(statement) => self.check_loop(None, &statement.block, state),
+            Statement::WhileStatement(statement) => {
+                self.check_loop(Some(&statement.condition), &statement.block, state)
+            }
+            Statement::Expression(expression) => self.check_expression(expression, state),
+            Statement::EnumDeclaration(_) | Statement::EmptyStatement => {}
+        }
+    }
+
+    // Nothing assigned in the body can be relied on when the loop starts, so
+    // the top of the body starts from the state before the loop. What the
+    // body assigns may already be assigned on the next iteration, so the body
+    // is walked again until that stops growing.
+    fn check_loop(&mut self, condition: Option<&Expression>, block: &Block, state: &mut State) {
+        let diagnostics = self.diagnostics.len();
+        // Bindings declared in the body are new on every iteration.
+        let outer = self.declarations.len();
+        let mut entry = state.clone();
+
+        loop {
+            self.diagnostics.truncate(diagnostics);
+
+            let mut top = entry.clone();
+            if let Some(condition) = condition {
+                self.check_expression(condition, &mut top);
+            }
+
+            self.loops.push(LoopStates {
+                breaks: State::unreachable(),
+                continues: State::unreachable(),
+            });
+            let mut body = top.clone();
+            self.check_block(block, &mut body);
+            let states = self.loops.pop().unwrap();
+
+            let back = body.join(states.continues);
+            let assigned: Vec<usize> = back
+                .assigned
+                .iter()
+                .copied()
+                .filter(|id| *id < outer && !entry.assigned.contains(id))
+                .collect();
+            if back.unreachable || assigned.is_empty() {
+                *state = match condition {
+                    Some(_) => top.join(states.breaks),
+                    None => states.breaks,
+                };
+                return;
+            }
+            entry.assigned.extend(assigned);
+        }
+    }
+
+    fn check_assignment_target(
+        &mut self,
+        target: &AssignmentTarget,
+        state: &mut State,
+        span: Span,
+    ) {
+        match target {
+            AssignmentTarget::Identifier(identifier) => {
+                if let Some(id) = self.get_binding(identifier) {
+                    if self.assigned_once.contains(&id)
+                        && !state.unreachable
+                        && state.assigned.contains(&id)
+                    {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                format!(
+                                    "cannot assign twice to immutable variable `{}`",
+                                    identifier
+                                ),
+                                span,
+                                "cannot assign twice to immutable variable".to_string(),
+                            )
+                            .with_secondary_label(
+                                self.declarations[id],
+                                "declared here".to_string(),
+                            )
+                            .with_help(format!(
+                                "consider making this binding mutable: `let mut {}`",
+                                identifier
+                            )),
+                        );
+                    }
+                    state.initialized.insert(id);
+                    state.assigned.insert(id);
+                }
+            }
+            // Writing to an element reads the map it is in.
+            AssignmentTarget::Index(index) => {
+                self.check_expression(&index.expression, state);
+                self.check_expression(&index.index, state);
+            }
+            AssignmentTarget::Tuple(targets) => {
+                for target in targets {
+                    self.check_assignment_target(target, state, span);
+                }
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression, state: &mut State) {
+        match &expression.kind {
+            ExpressionKind::Literal(_) => {}
+            ExpressionKind::Identifier(identifier) => {
+                self.use_binding(identifier, state, expression.span)
+            }
+            ExpressionKind::BinaryOperation(operation) => {
+                self.check_expression(&operation.left_expression, state);
+                self.check_expression(&operation.right_expression, state);
+            }
+            ExpressionKind::UnaryOperation(operation) => {
+                self.check_expression(&operation.expression, state)
+            }
+            ExpressionKind::Cast(cast) => self.check_expression(&cast.expression, state),
+            ExpressionKind::Try(_try) => self.check_expression(&_try.expression, state),
+            ExpressionKind::FunctionCall(call) => {
+                for argument in &call.arguments {
+                    self.check_expression(argument, state);
+                }
+                // Variables shadow builtins of the same name.
+                if self.get_binding(&call.function_name).is_some() {
+                    self.use_binding(&call.function_name, state, expression.span);
+                } else if matches!(call.function_name.as_str(), "panic" | "exit") {
+                    *state = State::unreachable();
+                }
+            }
+            ExpressionKind::Call(call) => {
+                self.check_expression(&call.callee, state);
+                for argument in &call.arguments {
+                    self.check_expression(argument, state);
+                }
+            }
+            ExpressionKind::EnumVariant(variant) => {
+                for argument in &variant.arguments {
+                    self.check_expression(argument, state);
+                }
+            }
+            ExpressionKind::Match(_match) => {
+                self.check_expression(&_match.expression, state);
+
+                let mut after = State::unreachable();
+                for arm in &_match.arms {
+                    self.scopes.push(HashMap::new());
+                    let mut branch = state.clone();
+                    self.bind_pattern(&arm.pattern, &mut branch, None);
+
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard, &mut branch);
+                    }
+                    match &arm.body {
+                        MatchArmBody::Expression(expression) => {
+                            self.check_expression(expression, &mut branch)
+                        }
+                        MatchArmBody::Block(block) => self.check_block(block, &mut branch),
+                    }
+
+                    self.scopes.pop();
+                    after = after.join(branch);
+                }
+
+                *state = after;
+            }
+            ExpressionKind::MapLiteral(map_literal) => {
+                for (key, value) in &map_literal.entries {
+                    self.check_expression(key, state);
+                    self.check_expression(value, state);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.check_expression(&index.expression, state);
+                self.check_expression(&index.index, state);
+            }
+            ExpressionKind::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.check_expression(element, state);
+                }
+            }
+            // Closures copy the variables they capture when they are created,
+            // so those have to be initialized by then. A closure can be called
+            // more than once, so anything it assigns may already be assigned.
+            ExpressionKind::Closure(closure) => {
+                let loops = std::mem::take(&mut self.loops);
+                self.scopes.push(HashMap::new());
+                let mut body = state.clone();
+                body.assigned.extend(self.assigned_once.iter().copied());
+
+                for parameter in &closure.parameters {
+                    let id = self.declare(parameter, expression.span);
+                    body.initialized.insert(id);
+                }
+                match &closure.body {
+                    ClosureBody::Expression(expression) => {
+                        self.check_expression(expression, &mut body)
+                    }
+                    ClosureBody::Block(block) => self.check_block(block, &mut body),
+                }
+
+                self.scopes.pop();
+                self.loops = loops;
+            }
+        }
+    }
+}
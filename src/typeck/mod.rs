@@ -0,0 +1,619 @@
+mod expression;
+mod initialization;
+mod pattern;
+mod resolve;
+mod statement;
+pub mod types;
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        block::Block,
+        type_annotation::{TypeAnnotation, TypeAnnotationKind},
+    },
+    diagnostic::{Diagnostic, Span},
+};
+use types::{IntegerType, Substitution, Type};
+
+// Infers the type of every expression and `let` binding in `program` and
+// writes them into the tree. Closures are not generic: every use of a
+// closure has to agree on its parameter types.
+//
+// Returns the errors found. The tree is only fully typed if there are none.
+pub fn check(program: &mut Block) -> Vec<Diagnostic> {
+    let mut tc = TypeChecker::default();
+    program.check(&mut tc);
+    tc.substitution.default_integers();
+    tc.check_integer_literals();
+    tc.check_map_keys();
+    tc.diagnostics
+        .extend(initialization::check_initialization(program));
+
+    if tc.diagnostics.is_empty() {
+        resolve::resolve_block(program, &tc.substitution);
+    }
+
+    tc.diagnostics
+}
+
+trait TypeCheck {
+    type Output;
+    fn check(&mut self, tc: &mut TypeChecker) -> Self::Output;
+}
+
+#[derive(Default)]
+struct TypeChecker {
+    substitution: Substitution,
+    scopes: Vec<Scope>,
+    // Checked against the range of their type once every type is known.
+    integer_literals: Vec<IntegerLiteral>,
+    // The types of map keys and where they come from, checked once every
+    // type is known.
+    map_keys: Vec<(Type, Span)>,
+    // The result types of the closures being checked, innermost last.
+    return_types: Vec<Type>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+struct IntegerLiteral {
+    value: u64,
+    ty: Type,
+    span: Span,
+    // `-128` fits in an `i8` even though `128` does not.
+    negated: bool,
+    // Negative expressions of unsigned types wrap or overflow when they run,
+    // but a pattern that does not fit would never match.
+    pattern: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    symbols: HashMap<String, Symbol>,
+    enums: HashMap<String, EnumType>,
+}
+
+struct Symbol {
+    ty: Type,
+    mutable: bool,
+    // Declared by a `let` without a value, so it can be assigned once even if
+    // it is immutable. initialization.rs checks that it is only assigned once.
+    assigned_once: bool,
+    // The statement that declared the binding.
+    declaration: Span,
+}
+
+// The field types of every variant, in declaration order.
+#[derive(Clone)]
+struct EnumType {
+    variants: Vec<(String, Vec<Type>)>,
+}
+
+impl TypeChecker {
+    fn enter_scope(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    fn exit_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, identifier: &str, ty: Type, mutable: bool, declaration: Span) {
+        let symbol = Symbol {
+            ty,
+            mutable,
+            assigned_once: false,
+            declaration,
+        };
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .symbols
+            .insert(identifier.to_string(), symbol);
+    }
+
+    fn get_symbol(&self, identifier: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.symbols.get(identifier))
+            .map(|symbol| symbol.ty.clone())
+    }
+
+    // Returns the type of the binding `identifier`, which is being assigned
+    // to at `span`.
+    // Checks that the binding can be assigned to or changed through an index.
+    fn assign(&mut self, identifier: &str, span: Span) -> Option<Type> {
+        let symbol = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.symbols.get(identifier))?;
+        let ty = symbol.ty.clone();
+
+        if !symbol.mutable {
+            let diagnostic = Diagnostic::error(
+                format!("cannot assign to immutable variable `{}`", identifier),
+                span,
+                "cannot assign to an immutable variable".to_string(),
+            )
+            .with_secondary_label(symbol.declaration, "declared here".to_string())
+            .with_help(format!(
+                "consider making this binding mutable: `let mut {}`",
+                identifier
+            ));
+            self.diagnostics.push(diagnostic);
+        }
+
+        Some(ty)
+    }
+
+    // Like `assign`, for assigning a value to the whole binding.
+    fn assign_binding(&mut self, identifier: &str, span: Span) -> Option<Type> {
+        let symbol = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.symbols.get(identifier))?;
+
+        if symbol.assigned_once {
+            return Some(symbol.ty.clone());
+        }
+        self.assign(identifier, span)
+    }
+
+    fn get_enum(&self, identifier: &str) -> Option<EnumType> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.enums.get(identifier))
+            .cloned()
+    }
+
+    fn new_variable(&mut self) -> Type {
+        self.substitution.new_variable()
+    }
+
+    fn new_integer_variable(&mut self) -> Type {
+        self.substitution.new_integer_variable()
+    }
+
+    fn error(&mut self, message: String, span: Span, label: String) {
+        self.diagnostics
+            .push(Diagnostic::error(message, span, label));
+    }
+
+    fn mismatch(&self, expected: &Type, found: &Type, span: Span) -> Diagnostic {
+        Diagnostic::error(
+            "mismatched types".to_string(),
+            span,
+            format!(
+                "expected `{}`, found `{}`",
+                self.substitution.apply(expected),
+                self.substitution.apply(found)
+            ),
+        )
+    }
+
+    // Reports a mismatch at `span` unless `found` can be made `expected`.
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span) {
+        if !self.substitution.unify(expected, found) {
+            let diagnostic = self.mismatch(expected, found, span);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // Reports a mismatch at `span` unless `found` can be an integer of any width.
+    fn expect_integer(&mut self, found: &Type, span: Span) {
+        let integer = self.new_integer_variable();
+        if !self.substitution.unify(&integer, found) {
+            self.error(
+                "mismatched types".to_string(),
+                span,
+                format!(
+                    "expected integer, found `{}`",
+                    self.substitution.apply(found)
+                ),
+            );
+        }
+    }
+
+    fn check_integer_literals(&mut self) {
+        for literal in std::mem::take(&mut self.integer_literals) {
+            let ty = match self.substitution.apply(&literal.ty) {
+                Type::Integer(ty) => ty,
+                _ => continue,
+            };
+
+            // Patterns hold the value with its sign, which has to be in range.
+            let (fits, text) = if literal.pattern {
+                let value = if literal.negated {
+                    -(literal.value as i128)
+                } else {
+                    literal.value as i128
+                };
+                (ty.min() <= value && value <= ty.max(), value.to_string())
+            } else {
+                let limit = if literal.negated && ty.is_signed() {
+                    -ty.min()
+                } else {
+                    ty.max()
+                };
+                (literal.value as i128 <= limit, literal.value.to_string())
+            };
+
+            if !fits {
+                self.error(
+                    format!("literal out of range for `{}`", ty),
+                    literal.span,
+                    format!(
+                        "the literal `{}` does not fit into the type `{}` whose range is `{}..={}`",
+                        text,
+                        ty,
+                        ty.min(),
+                        ty.max()
+                    ),
+                );
+            }
+        }
+    }
+
+    // Maps compare and hash their keys by value, which closures, maps and
+    // enums do not have.
+    fn check_map_keys(&mut self) {
+        for (ty, span) in std::mem::take(&mut self.map_keys) {
+            let ty = self.substitution.apply(&ty);
+            if !is_key_type(&ty) {
+                self.error(
+                    format!("`{}` cannot be used as a map key", ty),
+                    span,
+                    "map keys must be integers, strings or tuples of them".to_string(),
+                );
+            }
+        }
+    }
+
+    fn get_annotated_type(&mut self, annotation: &TypeAnnotation) -> Type {
+        match &annotation.kind {
+            TypeAnnotationKind::Named(name) => {
+                if let Some(ty) = IntegerType::from_name(name) {
+                    return Type::Integer(ty);
+                }
+
+                match name.as_str() {
+                    "string" => Type::String,
+                    _ if self.get_enum(name).is_some() => Type::Enum(name.clone()),
+                    _ => {
+                        self.error(
+                            format!("cannot find type `{}` in this scope", name),
+                            annotation.span,
+                            "not found in this scope".to_string(),
+                        );
+                        self.new_variable()
+                    }
+                }
+            }
+            TypeAnnotationKind::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.get_annotated_type(element))
+                    .collect(),
+            ),
+            TypeAnnotationKind::Map(key, value) => {
+                let key_type = self.get_annotated_type(key);
+                self.map_keys.push((key_type.clone(), key.span));
+                Type::Map(Box::new(key_type), Box::new(self.get_annotated_type(value)))
+            }
+            TypeAnnotationKind::Function(parameters, result) => Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.get_annotated_type(parameter))
+                    .collect(),
+                Box::new(self.get_annotated_type(result)),
+            ),
+            TypeAnnotationKind::Result(ok, err) => Type::Result(
+                Box::new(self.get_annotated_type(ok)),
+                Box::new(self.get_annotated_type(err)),
+            ),
+        }
+    }
+}
+
+// Types left unknown are reported elsewhere if they matter.
+fn is_key_type(ty: &Type) -> bool {
+    match ty {
+        Type::Integer(_) | Type::String | Type::Variable(_) | Type::IntegerVariable(_) => true,
+        Type::Tuple(elements) => elements.iter().all(is_key_type),
+        Type::Map(..) | Type::Function(..) | Type::Enum(_) | Type::Result(..) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::statement::Statement, diagnostic::Diagnostic, parser::Parser};
+
+    fn check(source: &str) -> Result<Vec<Statement>, Vec<Diagnostic>> {
+        let mut program = Parser::new(source).get_ast().unwrap().unwrap();
+        let diagnostics = super::check(&mut program);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        Ok(program.statements)
+    }
+
+    fn get_let_types(source: &str) -> Vec<String> {
+        check(source)
+            .unwrap_or_else(|_| panic!("expected {:?} to type check", source))
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::LetStatement(statement) => {
+                    Some(statement.ty.as_ref().unwrap().to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn get_errors(source: &str) -> Vec<String> {
+        match check(source) {
+            Ok(_) => panic!("expected {:?} not to type check", source),
+            Err(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| format!("{}: {}", diagnostic.message, diagnostic.label.message))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_infer_let_types() {
+        assert_eq!(
+            get_let_types(
+                "let mut m = {};
+                m[\"a\"] = (1, \"b\");
+                let k = keys(m);
+                let f = |x, y| x + y;
+                let g: fn(u32) -> u32 = |x| f(x, 1);
+                let u;
+                let (a, b) = m[\"a\"];"
+            ),
+            vec![
+                "{string: (u32, string)}",
+                "{u32: string}",
+                "fn(u32, u32) -> u32",
+                "fn(u32) -> u32",
+                "_",
+                "(u32, string)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_infer_enum_and_match_types() {
+        assert_eq!(
+            get_let_types(
+                "enum Shape { Circle(r), Rect(w, h) };
+                let s = Shape::Rect(1, 2);
+                let area = match s { Shape::Circle(r) => 3 * r * r, Shape::Rect(w, h) => w * h };"
+            ),
+            vec!["Shape", "u32"]
+        );
+    }
+
+    #[test]
+    fn test_sized_integers() {
+        assert_eq!(
+            get_let_types(
+                "let a: i8 = -128;
+                let b = a * 2;
+                let c = b as u64 + 1;
+                let d = 1 << a;
+                let e = -(1 as i64);
+                let f = 2 ** 3;
+                let g = a > -1;"
+            ),
+            vec!["i8", "i8", "u64", "u32", "i64", "u32", "u32"]
+        );
+
+        assert_eq!(
+            get_errors(
+                "let a: u8 = 256; let b: i8 = -129; let c: i8 = 1; let d: u8 = c;
+                let e = \"a\" as u8; let f = 1 as string; let g: u64 = 2 ** c;"
+            ),
+            vec![
+                "mismatched types: expected `u8`, found `i8`",
+                "mismatched types: expected integer, found `string`",
+                "cannot cast to `string`: not an integer type",
+                "mismatched types: expected `u32`, found `i8`",
+                "literal out of range for `u8`: the literal `256` does not fit into the type `u8` whose range is `0..=255`",
+                "literal out of range for `i8`: the literal `129` does not fit into the type `i8` whose range is `-128..=127`",
+            ]
+        );
+
+        assert_eq!(
+            get_errors(
+                "let a: i8 = 1; let b = match a { -128 => 1, -129 => 2, _ => 3 };
+                let c: u8 = 1; let d = match c { -3 => 1, _ => 2 };"
+            ),
+            vec![
+                "literal out of range for `i8`: the literal `-129` does not fit into the type `i8` whose range is `-128..=127`",
+                "literal out of range for `u8`: the literal `-3` does not fit into the type `u8` whose range is `0..=255`",
+            ]
+        );
+
+        // The error points at the pattern rather than at what is matched.
+        let errors = check("let a: i8 = 1; let b = match a { -129 => 1, _ => 2 };").unwrap_err();
+        let span = errors[0].label.span;
+        assert_eq!((span.start, span.end), (33, 37));
+    }
+
+    #[test]
+    fn test_results() {
+        assert_eq!(
+            get_let_types(
+                "let a: Result<u8, string> = ok(1);
+                let b = parse_int(\"12\");
+                let c = checked_div(a?, 2);
+                let d = |r| ok(r? + 1);
+                let e = match b { Result::Ok(v) => v as i8, Result::Err(_) => 0 };"
+            ),
+            vec![
+                "Result<u8, string>",
+                "Result<u32, string>",
+                "Result<u8, string>",
+                "fn(Result<u32, _>) -> Result<u32, _>",
+                "i8",
+            ]
+        );
+
+        assert_eq!(
+            get_errors("let a = 1?; let f = |r| { let x = r?; };"),
+            vec![
+                "mismatched types: expected `Result<_, _>`, found `{integer}`",
+                "the `?` operator cannot be used here: the closure returns `u32`, which is not `Result<_, _>`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mismatched_types() {
+        assert_eq!(
+            get_errors("let x: u32 = \"a\";"),
+            vec!["mismatched types: expected `u32`, found `string`"]
+        );
+        assert_eq!(
+            get_errors("let f = |x| x + 1; f(\"a\");"),
+            vec!["mismatched types: expected `{integer}`, found `string`"]
+        );
+        assert_eq!(
+            get_errors("let mut m = {1: 2}; m[1] = \"a\";"),
+            vec!["mismatched types: expected `{integer}`, found `string`"]
+        );
+        assert_eq!(
+            get_errors("assert_eq(1, \"a\"); exit(\"a\");"),
+            vec![
+                "mismatched types: expected `{integer}`, found `string`",
+                "mismatched types: expected `i32`, found `string`",
+            ]
+        );
+        assert_eq!(
+            get_errors("let a = 1; a(2);"),
+            vec!["expected function, found `{integer}`: not a function"]
+        );
+        assert_eq!(
+            get_errors("let x: Point = 1; y;"),
+            vec![
+                "cannot find type `Point` in this scope: not found in this scope",
+                "cannot find value `y` in this scope: not found in this scope",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_key_types() {
+        let error = "`{u32: u32}` cannot be used as a map key: map keys must be integers, strings or tuples of them";
+        assert_eq!(get_errors("let m = {1: 2}; let n = {m: 1};"), vec![error]);
+        assert_eq!(
+            get_errors("let m = {1: 2}; let mut n = {}; n[(1, m)] = 1;"),
+            vec![error.replace("`{u32: u32}`", "`(u32, {u32: u32})`")]
+        );
+        assert_eq!(
+            get_errors("let f = |x| x + 1; let m = {f: 1};"),
+            vec!["`fn(u32) -> u32` cannot be used as a map key: map keys must be integers, strings or tuples of them"]
+        );
+        assert_eq!(
+            get_errors("enum E { A }; let m: {E: u32} = {};"),
+            vec!["`E` cannot be used as a map key: map keys must be integers, strings or tuples of them"]
+        );
+        check("let mut m = {}; m[(1, \"a\")] = 1; let n: {(u8, string): u8} = {};").unwrap();
+    }
+
+    #[test]
+    fn test_assign_to_immutable() {
+        assert_eq!(
+            get_errors(
+                "let a = 1; a = 2; let m = {}; m[1] = 2; remove(m, 1);
+                let (b, c) = (1, 2); (b, c) = (c, b);"
+            ),
+            vec![
+                "cannot assign to immutable variable `a`: cannot assign to an immutable variable",
+                "cannot assign to immutable variable `m`: cannot assign to an immutable variable",
+                "cannot assign to immutable variable `m`: cannot assign to an immutable variable",
+                "cannot assign to immutable variable `b`: cannot assign to an immutable variable",
+                "cannot assign to immutable variable `c`: cannot assign to an immutable variable",
+            ]
+        );
+        assert_eq!(
+            get_errors("let mut a = 1; if a { let a = 2; a += 1; }; a = 3;"),
+            vec!["cannot assign to immutable variable `a`: cannot assign to an immutable variable"]
+        );
+        assert!(check("let mut a = 1; let f = |x| x; a = f(a);").is_ok());
+    }
+
+    #[test]
+    fn test_assign_twice() {
+        assert!(check(
+            "let a; if 1 { a = 1; } else { a = 2; }; print(a);
+            let b; loop { if a { b = 1; break; }; }; print(b);
+            loop { let e; e = 1; print(e); break; };"
+        )
+        .is_ok());
+
+        let error = "cannot assign twice to immutable variable `a`: cannot assign twice to immutable variable";
+        assert_eq!(get_errors("let a; a = 1; a = 2;"), vec![error]);
+        assert_eq!(
+            get_errors("let a; if 1 { a = 1; }; a = 2; print(a);"),
+            vec![error]
+        );
+        assert_eq!(get_errors("let a; while 1 { a = 1; };"), vec![error]);
+        assert_eq!(
+            get_errors("let a; loop { if 1 { continue; }; a = 1; };"),
+            vec![error]
+        );
+        assert_eq!(get_errors("let a; let f = || { a = 1; };"), vec![error]);
+        assert_eq!(
+            get_errors("let a; a = {}; a[1] = 2;"),
+            vec!["cannot assign to immutable variable `a`: cannot assign to an immutable variable"]
+        );
+    }
+
+    #[test]
+    fn test_possibly_uninitialized() {
+        assert!(check(
+            "let a; let b;
+            if 1 { a = 1; b = 1; } else if 2 { a = 2; } else { a = 3; b = 3; };
+            print(a);
+            let c; loop { c = 1; break; }; print(c);
+            let d; match a { 1 => { d = 1; }, _ => { d = 2; } }; print(d);
+            let e; if a { e = 1; } else { panic(\"no\"); }; print(e);
+            let f; match a { 1 => { f = 1; }, _ => exit(2) }; print(f);"
+        )
+        .is_ok());
+
+        let error = "used binding `a` is possibly uninitialized: `a` used here but it is possibly uninitialized";
+        assert_eq!(get_errors("let a; print(a);"), vec![error]);
+        assert_eq!(
+            get_errors("let a; if 1 { a = 1; } else if 2 { a = 2; }; print(a);"),
+            vec![error]
+        );
+        assert_eq!(
+            get_errors("let mut a; while 1 { a = 1; }; a + 1;"),
+            vec![error]
+        );
+        assert_eq!(
+            get_errors("let a; loop { if 1 { break; }; a = 1; break; }; a + 1;"),
+            vec![error]
+        );
+        assert_eq!(get_errors("let a; let f = || a;"), vec![error]);
+        assert_eq!(
+            get_errors(
+                "let a; let panic = |s| 1; if 1 { a = 1; } else { panic(\"no\"); }; print(a);"
+            ),
+            vec![error]
+        );
+        assert_eq!(
+            get_errors("let mut a; let b = 1; a = 1; loop { let mut a; a += b; break; };"),
+            vec![error]
+        );
+    }
+}
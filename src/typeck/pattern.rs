@@ -0,0 +1,124 @@
+use super::{IntegerLiteral, TypeChecker};
+use crate::{
+    ast::pattern::{Pattern, PatternKind},
+    diagnostic::Span,
+    typeck::types::Type,
+};
+
+impl TypeChecker {
+    // Declares the bindings of `pattern`, which matches values of type
+    // `expected`. The bindings of a `let` are declared by the whole statement,
+    // `declaration`, and those of a match arm by their own patterns.
+    pub fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        expected: &Type,
+        mutable: bool,
+        declaration: Option<Span>,
+    ) {
+        let span = pattern.span;
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding(identifier) => self.declare(
+                identifier,
+                expected.clone(),
+                mutable,
+                declaration.unwrap_or(span),
+            ),
+            PatternKind::IntegerLiteral(value) => {
+                self.expect_integer(expected, span);
+                self.integer_literals.push(IntegerLiteral {
+                    value: value.unsigned_abs() as u64,
+                    ty: expected.clone(),
+                    span,
+                    negated: *value < 0,
+                    pattern: true,
+                });
+            }
+            PatternKind::Tuple(elements) => {
+                let element_types: Vec<Type> =
+                    elements.iter().map(|_| self.new_variable()).collect();
+                self.expect(expected, &Type::Tuple(element_types.clone()), span);
+
+                for (element, element_type) in elements.iter().zip(&element_types) {
+                    self.check_pattern(element, element_type, mutable, declaration);
+                }
+            }
+            PatternKind::Variant(variant_pattern) => {
+                let (enum_type, field_types) = match self.get_variant(
+                    &variant_pattern.enum_name,
+                    &variant_pattern.variant_name,
+                    span,
+                ) {
+                    None => return,
+                    Some(variant) => variant,
+                };
+
+                self.expect(expected, &enum_type, span);
+
+                // A wrong number of fields is reported when the pattern is lowered.
+                for (field, field_type) in variant_pattern.fields.iter().zip(&field_types) {
+                    self.check_pattern(field, field_type, mutable, declaration);
+                }
+            }
+        }
+    }
+
+    // Returns the type of the enum and the types of the fields of the variant.
+    pub fn get_variant(
+        &mut self,
+        enum_name: &str,
+        variant_name: &str,
+        span: Span,
+    ) -> Option<(Type, Vec<Type>)> {
+        let declaration = match self.get_enum(enum_name) {
+            // `Result` is built in, unless a program declares its own.
+            None if enum_name == "Result" => {
+                let ok = self.new_variable();
+                let err = self.new_variable();
+                let result = Type::Result(Box::new(ok.clone()), Box::new(err.clone()));
+                match variant_name {
+                    "Ok" => return Some((result, vec![ok])),
+                    "Err" => return Some((result, vec![err])),
+                    _ => {
+                        self.error(
+                            format!("no variant named `{}` in enum `Result`", variant_name),
+                            span,
+                            "variant not found".to_string(),
+                        );
+                        return None;
+                    }
+                }
+            }
+            None => {
+                self.error(
+                    format!("cannot find enum `{}` in this scope", enum_name),
+                    span,
+                    "not found in this scope".to_string(),
+                );
+                return None;
+            }
+            Some(declaration) => declaration,
+        };
+
+        let fields = declaration
+            .variants
+            .into_iter()
+            .find(|(name, _)| name == variant_name)
+            .map(|(_, fields)| fields);
+
+        let fields = fields.map(|fields| (Type::Enum(enum_name.to_string()), fields));
+        if fields.is_none() {
+            self.error(
+                format!(
+                    "no variant named `{}` in enum `{}`",
+                    variant_name, enum_name
+                ),
+                span,
+                "variant not found".to_string(),
+            );
+        }
+
+        fields
+    }
+}
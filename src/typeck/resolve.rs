@@ -0,0 +1,132 @@
+use super::types::Substitution;
+use crate::ast::{
+    block::Block,
+    expression::{ClosureBody, Expression, ExpressionKind, MatchArmBody},
+    statement::{AssignmentTarget, Statement},
+};
+
+// Replaces the type variables written into the tree during checking with
+// what was eventually inferred for them.
+pub fn resolve_block(block: &mut Block, substitution: &Substitution) {
+    for statement in &mut block.statements {
+        resolve_statement(statement, substitution);
+    }
+}
+
+fn resolve_statement(statement: &mut Statement, substitution: &Substitution) {
+    match statement {
+        Statement::LetStatement(statement) => {
+            if let Some(expression) = &mut statement.expression {
+                resolve_expression(expression, substitution);
+            }
+            statement.ty = statement.ty.as_ref().map(|ty| substitution.apply(ty));
+        }
+        Statement::Assignment(statement) => {
+            resolve_assignment_target(&mut statement.target, substitution);
+            resolve_expression(&mut statement.expression, substitution);
+        }
+        Statement::IfStatement(statement) => {
+            for condition_with_block in
+                std::iter::once(&mut statement._if).chain(&mut statement.else_if)
+            {
+                resolve_expression(&mut condition_with_block.condition, substitution);
+                resolve_block(&mut condition_with_block.block, substitution);
+            }
+
+            if let Some(block) = &mut statement._else {
+                resolve_block(block, substitution);
+            }
+        }
+        Statement::LoopStatement(statement) => resolve_block(&mut statement.block, substitution),
+        Statement::WhileStatement(statement) => {
+            resolve_expression(&mut statement.condition, substitution);
+            resolve_block(&mut statement.block, substitution);
+        }
+        Statement::Expression(expression) => resolve_expression(expression, substitution),
+        Statement::BreakStatement(_)
+        | Statement::ContinueStatement(_)
+        | Statement::EnumDeclaration(_)
+        | Statement::EmptyStatement => {}
+    }
+}
+
+fn resolve_assignment_target(target: &mut AssignmentTarget, substitution: &Substitution) {
+    match target {
+        AssignmentTarget::Identifier(_) => {}
+        AssignmentTarget::Index(index) => {
+            resolve_expression(&mut index.expression, substitution);
+            resolve_expression(&mut index.index, substitution);
+        }
+        AssignmentTarget::Tuple(targets) => {
+            for target in targets {
+                resolve_assignment_target(target, substitution);
+            }
+        }
+    }
+}
+
+fn resolve_expression(expression: &mut Expression, substitution: &Substitution) {
+    expression.ty = expression.ty.as_ref().map(|ty| substitution.apply(ty));
+
+    match &mut expression.kind {
+        ExpressionKind::Literal(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::BinaryOperation(operation) => {
+            resolve_expression(&mut operation.left_expression, substitution);
+            resolve_expression(&mut operation.right_expression, substitution);
+        }
+        ExpressionKind::UnaryOperation(operation) => {
+            resolve_expression(&mut operation.expression, substitution);
+        }
+        ExpressionKind::Cast(cast) => resolve_expression(&mut cast.expression, substitution),
+        ExpressionKind::Try(_try) => resolve_expression(&mut _try.expression, substitution),
+        ExpressionKind::FunctionCall(call) => {
+            for argument in &mut call.arguments {
+                resolve_expression(argument, substitution);
+            }
+        }
+        ExpressionKind::Call(call) => {
+            resolve_expression(&mut call.callee, substitution);
+            for argument in &mut call.arguments {
+                resolve_expression(argument, substitution);
+            }
+        }
+        ExpressionKind::EnumVariant(variant) => {
+            for argument in &mut variant.arguments {
+                resolve_expression(argument, substitution);
+            }
+        }
+        ExpressionKind::Match(_match) => {
+            resolve_expression(&mut _match.expression, substitution);
+            for arm in &mut _match.arms {
+                if let Some(guard) = &mut arm.guard {
+                    resolve_expression(guard, substitution);
+                }
+                match &mut arm.body {
+                    MatchArmBody::Expression(expression) => {
+                        resolve_expression(expression, substitution)
+                    }
+                    MatchArmBody::Block(block) => resolve_block(block, substitution),
+                }
+            }
+        }
+        ExpressionKind::MapLiteral(map_literal) => {
+            for (key, value) in &mut map_literal.entries {
+                resolve_expression(key, substitution);
+                resolve_expression(value, substitution);
+            }
+        }
+        ExpressionKind::Index(index) => {
+            resolve_expression(&mut index.expression, substitution);
+            resolve_expression(&mut index.index, substitution);
+        }
+        ExpressionKind::Tuple(tuple) => {
+            for element in &mut tuple.elements {
+                resolve_expression(element, substitution);
+            }
+        }
+        ExpressionKind::Closure(closure) => match &mut closure.body {
+            ClosureBody::Expression(expression) => resolve_expression(expression, substitution),
+            ClosureBody::Block(block) => resolve_block(block, substitution),
+        },
+    }
+}
@@ -0,0 +1,161 @@
+use super::{EnumType, TypeCheck, TypeChecker};
+use crate::{
+    ast::{
+        block::Block,
+        expression::ExpressionKind,
+        pattern::PatternKind,
+        statement::{AssignmentStatement, AssignmentTarget, LetStatement, Statement},
+    },
+    diagnostic::Span,
+    typeck::types::Type,
+};
+
+impl TypeCheck for Block {
+    type Output = ();
+
+    fn check(&mut self, tc: &mut TypeChecker) {
+        tc.enter_scope();
+
+        for statement in &mut self.statements {
+            statement.check(tc);
+        }
+
+        tc.exit_scope();
+    }
+}
+
+impl TypeCheck for Statement {
+    type Output = ();
+
+    fn check(&mut self, tc: &mut TypeChecker) {
+        match self {
+            Statement::LetStatement(statement) => statement.check(tc),
+            Statement::Assignment(statement) => statement.check(tc),
+            Statement::IfStatement(statement) => {
+                for condition_with_block in
+                    std::iter::once(&mut statement._if).chain(&mut statement.else_if)
+                {
+                    let condition = condition_with_block.condition.check(tc);
+                    tc.expect_integer(&condition, condition_with_block.condition.span);
+                    condition_with_block.block.check(tc);
+                }
+
+                if let Some(block) = &mut statement._else {
+                    block.check(tc);
+                }
+            }
+            Statement::BreakStatement(_) | Statement::ContinueStatement(_) => {}
+            Statement::LoopStatement(statement) => statement.block.check(tc),
+            Statement::WhileStatement(statement) => {
+                let condition = statement.condition.check(tc);
+                tc.expect_integer(&condition, statement.condition.span);
+                statement.block.check(tc);
+            }
+            Statement::EnumDeclaration(declaration) => {
+                // Fields are not annotated, so their types are inferred from how they are used.
+                let variants = declaration
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let fields = variant.fields.iter().map(|_| tc.new_variable()).collect();
+                        (variant.identifier.clone(), fields)
+                    })
+                    .collect();
+
+                tc.scopes
+                    .last_mut()
+                    .unwrap()
+                    .enums
+                    .insert(declaration.identifier.clone(), EnumType { variants });
+            }
+            Statement::Expression(expression) => {
+                expression.check(tc);
+            }
+            Statement::EmptyStatement => {}
+        }
+    }
+}
+
+impl TypeCheck for LetStatement {
+    type Output = ();
+
+    fn check(&mut self, tc: &mut TypeChecker) {
+        let annotated_type = self
+            .type_annotation
+            .as_ref()
+            .map(|annotation| tc.get_annotated_type(annotation));
+
+        let ty = match (&mut self.expression, annotated_type) {
+            (None, None) => tc.new_variable(),
+            (None, Some(annotated_type)) => annotated_type,
+            (Some(expression), None) => expression.check(tc),
+            (Some(expression), Some(annotated_type)) => {
+                let found = expression.check(tc);
+                if !tc.substitution.unify(&annotated_type, &found) {
+                    let annotation = self.type_annotation.as_ref().unwrap();
+                    let diagnostic = tc
+                        .mismatch(&annotated_type, &found, expression.span)
+                        .with_secondary_label(annotation.span, "expected due to this".to_string());
+                    tc.diagnostics.push(diagnostic);
+                }
+                annotated_type
+            }
+        };
+
+        // The bindings are only in scope after the expression, so `let x = x;` is an error.
+        tc.check_pattern(&self.pattern, &ty, self.mutable, Some(self.span));
+        if let (None, PatternKind::Binding(identifier)) = (&self.expression, &self.pattern.kind) {
+            let symbols = &mut tc.scopes.last_mut().unwrap().symbols;
+            symbols.get_mut(identifier).unwrap().assigned_once = true;
+        }
+        self.ty = Some(ty);
+    }
+}
+
+impl TypeCheck for AssignmentStatement {
+    type Output = ();
+
+    fn check(&mut self, tc: &mut TypeChecker) {
+        let found = self.expression.check(tc);
+        let expected = check_assignment_target(tc, &mut self.target, self.span);
+        tc.expect(&expected, &found, self.expression.span);
+    }
+}
+
+fn check_assignment_target(
+    tc: &mut TypeChecker,
+    target: &mut AssignmentTarget,
+    span: Span,
+) -> Type {
+    match target {
+        AssignmentTarget::Identifier(identifier) => match tc.assign_binding(identifier, span) {
+            Some(ty) => ty,
+            None => {
+                tc.error(
+                    format!("cannot find value `{}` in this scope", identifier),
+                    span,
+                    "not found in this scope".to_string(),
+                );
+                tc.new_variable()
+            }
+        },
+        AssignmentTarget::Index(index) => {
+            // Writing to an element of a map modifies the binding that holds it.
+            let mut map = &index.expression;
+            while let ExpressionKind::Index(inner) = &map.kind {
+                map = &inner.expression;
+            }
+            if let ExpressionKind::Identifier(identifier) = &map.kind {
+                tc.assign(identifier, span);
+            }
+
+            tc.check_index(index, span)
+        }
+        AssignmentTarget::Tuple(targets) => Type::Tuple(
+            targets
+                .iter_mut()
+                .map(|target| check_assignment_target(tc, target, span))
+                .collect(),
+        ),
+    }
+}
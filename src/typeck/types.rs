@@ -0,0 +1,334 @@
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntegerType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+}
+
+impl IntegerType {
+    pub fn from_name(name: &str) -> Option<IntegerType> {
+        Some(match name {
+            "u8" => IntegerType::U8,
+            "i8" => IntegerType::I8,
+            "u16" => IntegerType::U16,
+            "i16" => IntegerType::I16,
+            "u32" => IntegerType::U32,
+            "i32" => IntegerType::I32,
+            "u64" => IntegerType::U64,
+            "i64" => IntegerType::I64,
+            _ => return None,
+        })
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            IntegerType::U8 | IntegerType::I8 => 8,
+            IntegerType::U16 | IntegerType::I16 => 16,
+            IntegerType::U32 | IntegerType::I32 => 32,
+            IntegerType::U64 | IntegerType::I64 => 64,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntegerType::I8 | IntegerType::I16 | IntegerType::I32 | IntegerType::I64
+        )
+    }
+
+    pub fn min(self) -> i128 {
+        if self.is_signed() {
+            -(1 << (self.bits() - 1))
+        } else {
+            0
+        }
+    }
+
+    pub fn max(self) -> i128 {
+        if self.is_signed() {
+            (1 << (self.bits() - 1)) - 1
+        } else {
+            (1 << self.bits()) - 1
+        }
+    }
+
+    // Keeps the low `bits()` bits of `value` and reads them back as this
+    // type, which is how results wrap around and how `as` casts.
+    pub fn wrap(self, value: i128) -> i128 {
+        let value = value & ((1 << self.bits()) - 1);
+        if value > self.max() {
+            value - (1 << self.bits())
+        } else {
+            value
+        }
+    }
+}
+
+impl fmt::Display for IntegerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IntegerType::U8 => "u8",
+            IntegerType::I8 => "i8",
+            IntegerType::U16 => "u16",
+            IntegerType::I16 => "i16",
+            IntegerType::U32 => "u32",
+            IntegerType::I32 => "i32",
+            IntegerType::U64 => "u64",
+            IntegerType::I64 => "i64",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Type {
+    Integer(IntegerType),
+    String,
+    Tuple(Vec<Type>),
+    Map(Box<Type>, Box<Type>),
+    Function(Vec<Type>, Box<Type>),
+    Enum(String),
+    // `Result<T, E>`, the one generic enum, built by `ok` and `err`.
+    Result(Box<Type>, Box<Type>),
+    // Not known yet. Left over variables are printed as `_`.
+    Variable(usize),
+    // The type of an integer literal, which can be any integer type and is
+    // `u32` if nothing else decides it.
+    IntegerVariable(usize),
+}
+
+impl Type {
+    // The type of conditions, comparisons and everything else that does not
+    // say otherwise.
+    pub const U32: Type = Type::Integer(IntegerType::U32);
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Integer(ty) => write!(f, "{}", ty),
+            Type::String => write!(f, "string"),
+            Type::Tuple(elements) => {
+                write!(f, "(")?;
+                write_types(f, elements)?;
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Type::Map(key, value) => write!(f, "{{{}: {}}}", key, value),
+            Type::Function(parameters, result) => {
+                write!(f, "fn(")?;
+                write_types(f, parameters)?;
+                write!(f, ") -> {}", result)
+            }
+            Type::Enum(name) => write!(f, "{}", name),
+            Type::Result(ok, err) => write!(f, "Result<{}, {}>", ok, err),
+            Type::Variable(_) => write!(f, "_"),
+            Type::IntegerVariable(_) => write!(f, "{{integer}}"),
+        }
+    }
+}
+
+// Types are shown the way they are written in annotations.
+impl fmt::Debug for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+fn write_types(f: &mut fmt::Formatter<'_>, types: &[Type]) -> fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", ty)?;
+    }
+    Ok(())
+}
+
+// What is known about each type variable so far.
+#[derive(Default)]
+pub struct Substitution {
+    variables: Vec<Option<Type>>,
+    integer_variables: Vec<usize>,
+}
+
+impl Substitution {
+    pub fn new_variable(&mut self) -> Type {
+        self.variables.push(None);
+        Type::Variable(self.variables.len() - 1)
+    }
+
+    pub fn new_integer_variable(&mut self) -> Type {
+        self.variables.push(None);
+        self.integer_variables.push(self.variables.len() - 1);
+        Type::IntegerVariable(self.variables.len() - 1)
+    }
+
+    // Makes every integer variable that is still unsolved a `u32`.
+    pub fn default_integers(&mut self) {
+        for variable in std::mem::take(&mut self.integer_variables) {
+            if let Type::IntegerVariable(variable) = self.apply(&Type::IntegerVariable(variable)) {
+                self.variables[variable] = Some(Type::U32);
+            }
+        }
+    }
+
+    // Replaces every variable that has been solved, recursively.
+    pub fn apply(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(variable) | Type::IntegerVariable(variable) => {
+                match &self.variables[*variable] {
+                    None => ty.clone(),
+                    Some(solution) => self.apply(solution),
+                }
+            }
+            Type::Integer(_) | Type::String | Type::Enum(_) => ty.clone(),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|element| self.apply(element)).collect())
+            }
+            Type::Map(key, value) => {
+                Type::Map(Box::new(self.apply(key)), Box::new(self.apply(value)))
+            }
+            Type::Result(ok, err) => {
+                Type::Result(Box::new(self.apply(ok)), Box::new(self.apply(err)))
+            }
+            Type::Function(parameters, result) => Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.apply(parameter))
+                    .collect(),
+                Box::new(self.apply(result)),
+            ),
+        }
+    }
+
+    // Makes `a` and `b` the same type, or returns false if they cannot be.
+    pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let a = self.apply(a);
+        let b = self.apply(b);
+
+        match (&a, &b) {
+            (Type::Variable(a), Type::Variable(b)) if a == b => true,
+            (Type::Variable(variable), ty) | (ty, Type::Variable(variable)) => {
+                // A type cannot contain itself.
+                if occurs(*variable, ty) {
+                    return false;
+                }
+                self.variables[*variable] = Some(ty.clone());
+                true
+            }
+            (Type::IntegerVariable(a), Type::IntegerVariable(b)) if a == b => true,
+            (
+                Type::IntegerVariable(variable),
+                ty @ (Type::Integer(_) | Type::IntegerVariable(_)),
+            )
+            | (ty @ Type::Integer(_), Type::IntegerVariable(variable)) => {
+                self.variables[*variable] = Some(ty.clone());
+                true
+            }
+            (Type::Integer(a), Type::Integer(b)) => a == b,
+            (Type::String, Type::String) => true,
+            (Type::Enum(a), Type::Enum(b)) => a == b,
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.unify(a, b))
+            }
+            (Type::Map(a_key, a_value), Type::Map(b_key, b_value)) => {
+                self.unify(a_key, b_key) && self.unify(a_value, b_value)
+            }
+            (Type::Result(a_ok, a_err), Type::Result(b_ok, b_err)) => {
+                self.unify(a_ok, b_ok) && self.unify(a_err, b_err)
+            }
+            (Type::Function(a_parameters, a_result), Type::Function(b_parameters, b_result)) => {
+                a_parameters.len() == b_parameters.len()
+                    && a_parameters
+                        .iter()
+                        .zip(b_parameters)
+                        .all(|(a, b)| self.unify(a, b))
+                    && self.unify(a_result, b_result)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn occurs(variable: usize, ty: &Type) -> bool {
+    match ty {
+        Type::Variable(other) | Type::IntegerVariable(other) => variable == *other,
+        Type::Integer(_) | Type::String | Type::Enum(_) => false,
+        Type::Tuple(elements) => elements.iter().any(|element| occurs(variable, element)),
+        Type::Map(key, value) | Type::Result(key, value) => {
+            occurs(variable, key) || occurs(variable, value)
+        }
+        Type::Function(parameters, result) => {
+            parameters
+                .iter()
+                .any(|parameter| occurs(variable, parameter))
+                || occurs(variable, result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IntegerType, Substitution, Type};
+
+    #[test]
+    fn test_unify() {
+        let mut substitution = Substitution::default();
+        let key = substitution.new_variable();
+        let value = substitution.new_variable();
+
+        let map = Type::Map(Box::new(key.clone()), Box::new(value.clone()));
+        let expected = Type::Map(
+            Box::new(Type::String),
+            Box::new(Type::Tuple(vec![Type::U32])),
+        );
+
+        assert!(substitution.unify(&map, &expected));
+        assert_eq!(substitution.apply(&map), expected);
+        assert_eq!(substitution.apply(&map).to_string(), "{string: (u32,)}");
+
+        assert!(!substitution.unify(&key, &Type::U32));
+        assert!(!substitution.unify(&value, &Type::Tuple(vec![value.clone()])));
+    }
+
+    #[test]
+    fn test_unify_integer_variables() {
+        let mut substitution = Substitution::default();
+        let literal = substitution.new_integer_variable();
+        let other = substitution.new_integer_variable();
+        let variable = substitution.new_variable();
+
+        assert!(!substitution.unify(&literal, &Type::String));
+        assert!(substitution.unify(&variable, &literal));
+        assert!(substitution.unify(&literal, &other));
+        assert_eq!(substitution.apply(&variable).to_string(), "{integer}");
+
+        assert!(substitution.unify(&other, &Type::Integer(IntegerType::I8)));
+        assert!(!substitution.unify(&variable, &Type::U32));
+        assert_eq!(substitution.apply(&literal).to_string(), "i8");
+
+        let unsolved = substitution.new_integer_variable();
+        substitution.default_integers();
+        assert_eq!(substitution.apply(&unsolved), Type::U32);
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(IntegerType::U8.wrap(256 + 5), 5);
+        assert_eq!(IntegerType::I8.wrap(128), -128);
+        assert_eq!(IntegerType::I8.wrap(-129), 127);
+        assert_eq!(IntegerType::U32.wrap(-1), 4294967295);
+        assert_eq!(IntegerType::I64.wrap(u64::MAX as i128), -1);
+        assert_eq!(IntegerType::U64.wrap(-1), u64::MAX as i128);
+    }
+}
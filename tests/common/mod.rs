@@ -0,0 +1,72 @@
+// Each test binary uses only some of these.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Runs the compiler on `source` and returns its exit code, stdout and stderr.
+pub fn run(arguments: &[&str], source: &str) -> (Option<i32>, String, String) {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "run-{}-{}.l",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_simple-programming-language"))
+        .args(arguments)
+        .arg(&path)
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    (
+        output.status.code(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// Runs every program in `directory` with each set of arguments and compares
+// what it prints with the file next to it that has the `expected` extension.
+pub fn check_programs(directory: &str, expected: &str, argument_sets: &[&[&str]]) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join(directory);
+
+    let mut programs: Vec<_> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "l"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty());
+
+    let mut failures = vec![];
+    for program in &programs {
+        for arguments in argument_sets {
+            let output = Command::new(env!("CARGO_BIN_EXE_simple-programming-language"))
+                .args(*arguments)
+                .arg(program)
+                .output()
+                .unwrap();
+
+            let expected = fs::read_to_string(program.with_extension(expected)).unwrap();
+            let actual = String::from_utf8(output.stdout).unwrap();
+
+            if !output.status.success() || actual != expected {
+                failures.push(format!(
+                    "{} {}:\n--- expected\n{}--- actual\n{}{}",
+                    arguments.join(" "),
+                    program.display(),
+                    expected,
+                    actual,
+                    String::from_utf8_lossy(&output.stderr)
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
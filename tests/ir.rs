@@ -0,0 +1,8 @@
+mod common;
+
+// Optimises every program in tests/ir and compares the IR with the `.ir`
+// file next to it, to show what the optimiser removes.
+#[test]
+fn test_ir() {
+    common::check_programs("tests/ir", "ir", &[&["--output-stage", "ir", "-O1"]]);
+}
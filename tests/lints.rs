@@ -0,0 +1,27 @@
+mod common;
+
+use common::run;
+
+// `-A`, `-W` and `-D` apply in the order they are given, so later ones win.
+#[test]
+fn test_lint_level_order() {
+    let source = "let x = 1;";
+
+    assert_eq!(
+        run(&["-D", "warnings", "-A", "unused_variables"], source),
+        (Some(0), String::new(), String::new())
+    );
+
+    let (code, _, stderr) = run(&["-A", "unused_variables", "-D", "warnings"], source);
+    assert_eq!(code, Some(1));
+    assert!(stderr.starts_with("error[unused_variables]"), "{stderr}");
+
+    let (code, _, stderr) = run(&["-A", "warnings", "-W", "unused_variables"], source);
+    assert_eq!(code, Some(0));
+    assert!(stderr.starts_with("warning[unused_variables]"), "{stderr}");
+
+    assert_eq!(
+        run(&["-W", "unused_variables", "-A", "warnings"], source),
+        (Some(0), String::new(), String::new())
+    );
+}
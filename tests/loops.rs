@@ -0,0 +1,173 @@
+use std::{env, fs, process::Command};
+
+// xorshift64, so that the programs are the same on every run.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn pick<'a>(&mut self, choices: &[&'a str]) -> &'a str {
+        choices[self.below(choices.len() as u64) as usize]
+    }
+}
+
+struct Generator {
+    random: Random,
+    variables: usize,
+}
+
+impl Generator {
+    fn variable(&mut self) -> String {
+        self.variables += 1;
+        format!("v{}", self.variables)
+    }
+
+    // A loop over a counter of some integer type, which counts towards a
+    // limit or until a guard stops it, with a body that reads the counter.
+    fn loop_(&mut self, out: &mut String, sum: &str, depth: usize) {
+        let r = &mut self.random;
+        let ty = r.pick(&["i8", "u8", "i16", "u16", "i32", "u32", "i64"]);
+        let up = r.below(2) == 0;
+        let step = 1 + r.below(4);
+        // Counters stay in range of every type unless they wrap, and so
+        // cannot overflow to go round forever.
+        let start = if up { r.below(10) } else { 40 + r.below(40) };
+        let limit = if up { r.below(30) } else { step + r.below(40) };
+        let comparison = match (up, r.below(3)) {
+            (true, 0) => "<=",
+            (true, _) => "<",
+            (false, 0) => ">=",
+            (false, _) => ">",
+        };
+        let operator = if up { "+" } else { "-" };
+        let wrapping = r.below(4) == 0;
+        let bottom_tested = r.below(4) == 0;
+        let step_first = r.below(2) == 0;
+
+        let i = self.variable();
+        let guard = self.variable();
+        out.push_str(&format!("let mut {i}: {ty} = {start};\n"));
+        out.push_str(&format!("let mut {guard} = 0;\n"));
+        let step_statement = if wrapping {
+            let function = if up { "wrapping_add" } else { "wrapping_sub" };
+            format!("{i} = {function}({i}, {step});\n")
+        } else {
+            format!("{i} = {i} {operator} {step};\n")
+        };
+        if bottom_tested {
+            out.push_str("loop {\n");
+        } else {
+            out.push_str(&format!("while {i} {comparison} {limit} {{\n"));
+        }
+        // Wrapping counters may never get to the limit.
+        if wrapping {
+            out.push_str(&format!("{guard} += 1;\nif {guard} > 40 {{\nbreak;\n}};\n"));
+        }
+        if step_first {
+            out.push_str(&step_statement);
+        }
+
+        for _ in 0..1 + self.random.below(3) {
+            let factor = self.random.below(9);
+            match self.random.below(8) {
+                0 | 1 => out.push_str(&format!("print({i} * {factor});\n")),
+                2 => out.push_str(&format!("{sum} += ({i} as i64) * {factor};\n")),
+                3 => out.push_str(&format!("print(({i}, {factor}));\n")),
+                // Skipping a test at the bottom could skip it forever.
+                4 if step_first && !bottom_tested => out.push_str(&format!(
+                    "if {i} % 3 == {} {{\ncontinue;\n}};\n",
+                    factor % 3
+                )),
+                5 if depth == 0 => self.loop_(out, sum, depth + 1),
+                // Code after `break` never runs, but is still compiled.
+                6 => out.push_str(&format!(
+                    "if {i} == {factor} {{\nbreak;\n{sum} = match {sum} {{\n0 => 1,\n_ => {sum},\n}};\n}};\n"
+                )),
+                _ => out.push_str(&format!("{sum} += {i} as i64;\n")),
+            }
+        }
+
+        if !step_first {
+            out.push_str(&step_statement);
+        }
+        if bottom_tested {
+            let negated = match comparison {
+                "<" => ">=",
+                "<=" => ">",
+                ">" => "<=",
+                _ => "<",
+            };
+            out.push_str(&format!("if {i} {negated} {limit} {{\nbreak;\n}};\n"));
+        }
+        out.push_str("};\n");
+        out.push_str(&format!("print({i});\nprint({guard});\n"));
+    }
+
+    fn program(&mut self) -> String {
+        let mut out = String::from("let mut sum: i64 = 0;\n");
+        for _ in 0..1 + self.random.below(3) {
+            self.loop_(&mut out, "sum", 0);
+        }
+        out.push_str("print(sum);\n");
+        out
+    }
+}
+
+// Optimising random loops must not change what they print, or whether and
+// how they fail.
+#[test]
+fn test_random_loops() {
+    let directory = env::temp_dir().join(format!("loops-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut generator = Generator {
+        random: Random(0x2545f4914f6cdd1d),
+        variables: 0,
+    };
+    let mut failures = vec![];
+    let mut successes = 0;
+    for n in 0..100 {
+        let source = generator.program();
+        let path = directory.join(format!("{n}.l"));
+        fs::write(&path, &source).unwrap();
+
+        for overflow in ["trap", "wrap"] {
+            let run = |level: &str| {
+                let output = Command::new(env!("CARGO_BIN_EXE_simple-programming-language"))
+                    .args(["-A", "warnings", "--overflow", overflow, level])
+                    .arg(&path)
+                    .output()
+                    .unwrap();
+                (output.status.code(), output.stdout)
+            };
+            let expected = run("-O0");
+            successes += usize::from(expected.0 == Some(0));
+            for level in ["-O1", "-O2"] {
+                let actual = run(level);
+                if actual != expected {
+                    failures.push(format!(
+                        "{level} --overflow {overflow}:\n{source}--- expected\n{:?}\n{}--- actual\n{:?}\n{}",
+                        expected.0,
+                        String::from_utf8_lossy(&expected.1),
+                        actual.0,
+                        String::from_utf8_lossy(&actual.1),
+                    ));
+                }
+            }
+        }
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    // Most programs should run to the end rather than overflow.
+    assert!(successes >= 100, "only {successes} of 200 runs succeeded");
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn test_passes() {
+    let source = "let a = parse_int(\"6\")?;
+        let x = 2 + 3;
+        print(a * x + a * x);";
+
+    // Only the passes asked for run, and the IR is printed after those asked
+    // for.
+    let (code, stdout, stderr) = run(
+        &["--passes=fold,gvn,copies,dce", "--print-after=gvn,dce"],
+        source,
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "60\n");
+    assert_eq!(
+        stderr,
+        "*** IR after gvn ***
+ls r1, \"6\"
+parse r2, r1
+tag r3, r2
+bz r3, L1
+field r4, r2, 0
+fail r4
+L1:
+field r5, r2, 0
+li r6, 2
+li r7, 3
+li r8, 5
+mul r9, r5, r8
+mov r10, r9
+add r11, r9, r10
+print r11
+*** IR after dce ***
+ls r1, \"6\"
+parse r2, r1
+tag r3, r2
+bz r3, L1
+field r4, r2, 0
+fail r4
+L1:
+field r5, r2, 0
+li r6, 5
+mul r7, r5, r6
+add r8, r7, r7
+print r8
+"
+    );
+
+    // Rotating loops needs the graph out of SSA form, and the other passes
+    // need it in.
+    let source = "let n = parse_int(\"4\")?;
+        let mut i = 0;
+        while i < n {
+            print(i * 3);
+            i += 1;
+        };";
+    let (code, stdout, stderr) = run(
+        &["--passes=fold,rotate,licm,strength", "--print-after-all"],
+        source,
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, "0\n3\n6\n9\n");
+    let headers: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("***"))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "*** IR after fold ***",
+            "*** IR after rotate ***",
+            "*** IR after licm ***",
+            "*** IR after strength ***",
+        ]
+    );
+
+    let (code, _, _) = run(&["-O1", "--passes=fold"], source);
+    assert_eq!(code, Some(2));
+}
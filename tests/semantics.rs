@@ -0,0 +1,8 @@
+mod common;
+
+// Runs every program in tests/semantics and compares what it prints with the
+// `.out` file next to it. Optimising must not change what a program prints.
+#[test]
+fn test_semantics() {
+    common::check_programs("tests/semantics", "out", &[&[], &["-O1"], &["-O2"]]);
+}